@@ -0,0 +1,15 @@
+CREATE TABLE command_aliases (
+  command_alias_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  command_id bigint NOT NULL,
+  alias TEXT NOT NULL,
+  UNIQUE (guild_id, alias),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION,
+  CONSTRAINT FK_commands FOREIGN KEY (command_id)
+    REFERENCES commands (command_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
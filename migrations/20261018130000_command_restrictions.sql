@@ -0,0 +1,11 @@
+CREATE TABLE command_restrictions (
+  command_restriction_id bigserial PRIMARY KEY,
+  command_id bigint NOT NULL,
+  kind TEXT NOT NULL CHECK (kind IN ('allow_role', 'deny_role', 'allow_channel', 'deny_channel')),
+  target_id bigint NOT NULL,
+  UNIQUE (command_id, target_id),
+  CONSTRAINT FK_commands FOREIGN KEY (command_id)
+    REFERENCES commands (command_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
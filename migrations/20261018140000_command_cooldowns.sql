@@ -0,0 +1,5 @@
+ALTER TABLE commands
+  ADD COLUMN guild_cooldown integer NOT NULL DEFAULT 0,
+  ADD COLUMN user_cooldown integer NOT NULL DEFAULT 0,
+  ADD COLUMN channel_cooldown integer NOT NULL DEFAULT 0,
+  ADD COLUMN cooldown_message boolean NOT NULL DEFAULT false;
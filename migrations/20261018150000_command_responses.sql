@@ -0,0 +1,18 @@
+CREATE TABLE command_responses (
+  command_response_id bigserial PRIMARY KEY,
+  command_id bigint NOT NULL,
+  content TEXT NOT NULL,
+  weight integer NOT NULL DEFAULT 1 CHECK (weight > 0),
+  -- Responses saved before template variables were supported are sent as they are,
+  -- as their braces were meant literally
+  templated boolean NOT NULL DEFAULT true,
+  CONSTRAINT FK_commands FOREIGN KEY (command_id)
+    REFERENCES commands (command_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
+
+INSERT INTO command_responses (command_id, content, templated)
+SELECT command_id, content, false FROM commands;
+
+ALTER TABLE commands DROP COLUMN content;
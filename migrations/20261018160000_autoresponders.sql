@@ -0,0 +1,16 @@
+CREATE TABLE autoresponders (
+  autoresponder_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  name TEXT NOT NULL,
+  trigger_kind TEXT NOT NULL CHECK (trigger_kind IN ('contains', 'exact', 'regex')),
+  pattern TEXT NOT NULL,
+  content TEXT NOT NULL,
+  -- Empty means all channels
+  channel_ids bigint[] NOT NULL DEFAULT '{}',
+  cooldown integer NOT NULL DEFAULT 0,
+  UNIQUE (guild_id, name),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
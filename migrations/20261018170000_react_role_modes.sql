@@ -0,0 +1,3 @@
+ALTER TABLE react_roles
+  ADD COLUMN mode TEXT NOT NULL DEFAULT 'toggle'
+    CHECK (mode IN ('toggle', 'add-only', 'remove-only', 'verify', 'keep-reaction'));
@@ -0,0 +1,17 @@
+CREATE TABLE react_role_groups (
+  react_role_group_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  name TEXT NOT NULL,
+  UNIQUE (guild_id, name),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
+
+ALTER TABLE react_roles
+  ADD COLUMN react_role_group_id bigint,
+  ADD CONSTRAINT FK_react_role_groups FOREIGN KEY (react_role_group_id)
+    REFERENCES react_role_groups (react_role_group_id)
+    ON DELETE SET NULL
+    ON UPDATE NO ACTION;
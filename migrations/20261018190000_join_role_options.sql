@@ -0,0 +1,32 @@
+ALTER TABLE join_roles
+  ADD COLUMN target TEXT NOT NULL DEFAULT 'both' CHECK (target IN ('humans', 'bots', 'both')),
+  ADD COLUMN delay integer NOT NULL DEFAULT 0,
+  ADD COLUMN skip_pending boolean NOT NULL DEFAULT false;
+
+-- Delayed join roles waiting to be given, so they survive restarts
+CREATE TABLE pending_join_roles (
+  pending_join_role_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  user_id bigint NOT NULL,
+  role_id bigint NOT NULL,
+  grant_at timestamptz NOT NULL,
+  -- Failed grants are retried later, each failure doubles the delay
+  failed_attempts integer NOT NULL DEFAULT 0,
+  UNIQUE (guild_id, user_id, role_id),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
+
+-- Members whose join roles are held back until they pass membership screening
+CREATE TABLE screening_members (
+  screening_member_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  user_id bigint NOT NULL,
+  UNIQUE (guild_id, user_id),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
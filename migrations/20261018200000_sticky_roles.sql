@@ -0,0 +1,25 @@
+-- Roles which are given back to members when they rejoin
+CREATE TABLE sticky_roles (
+  sticky_role_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  role_id bigint NOT NULL,
+  UNIQUE (guild_id, role_id),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
+
+-- Roles members had when they left
+CREATE TABLE departed_member_roles (
+  departed_member_role_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  user_id bigint NOT NULL,
+  role_ids bigint[] NOT NULL,
+  left_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE (guild_id, user_id),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
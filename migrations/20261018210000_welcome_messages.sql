@@ -0,0 +1,13 @@
+-- Messages sent when members join or leave, NULL channel means direct message
+CREATE TABLE welcome_messages (
+  welcome_message_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  kind TEXT NOT NULL CHECK (kind IN ('welcome', 'goodbye')),
+  channel_id bigint,
+  content TEXT NOT NULL,
+  UNIQUE (guild_id, kind),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
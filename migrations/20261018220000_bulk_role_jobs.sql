@@ -0,0 +1,40 @@
+-- Bulk role changes running in background, so they can be followed, cancelled and resumed after restart
+CREATE TABLE bulk_role_jobs (
+  bulk_role_job_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  channel_id bigint NOT NULL,
+  progress_message_id bigint,
+  created_by bigint NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  role_id bigint NOT NULL,
+  mode TEXT NOT NULL CHECK (mode IN ('add', 'remove')),
+  all_role_ids bigint[] NOT NULL,
+  any_role_ids bigint[] NOT NULL,
+  none_role_ids bigint[] NOT NULL,
+  target TEXT NOT NULL CHECK (target IN ('humans', 'bots', 'both')),
+  joined_before timestamptz,
+  joined_after timestamptz,
+  status TEXT NOT NULL DEFAULT 'running' CHECK (status IN ('running', 'cancelled', 'failed', 'finished')),
+  error TEXT,
+  -- Members are processed in order of their ids, this is the last one processed
+  last_user_id bigint NOT NULL DEFAULT 0,
+  processed integer NOT NULL DEFAULT 0,
+  changed integer NOT NULL DEFAULT 0,
+  failed integer NOT NULL DEFAULT 0,
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
+
+-- Members whose roles couldn't be changed by a job
+CREATE TABLE bulk_role_job_errors (
+  bulk_role_job_error_id bigserial PRIMARY KEY,
+  bulk_role_job_id bigint NOT NULL,
+  user_id bigint NOT NULL,
+  error TEXT NOT NULL,
+  CONSTRAINT FK_bulk_role_jobs FOREIGN KEY (bulk_role_job_id)
+    REFERENCES bulk_role_jobs (bulk_role_job_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
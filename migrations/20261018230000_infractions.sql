@@ -0,0 +1,28 @@
+-- Moderation actions, numbered per guild as cases
+CREATE TABLE infractions (
+  infraction_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  case_number integer NOT NULL,
+  kind TEXT NOT NULL CHECK (kind IN ('warn', 'kick', 'ban', 'unban', 'softban', 'mute', 'unmute')),
+  moderator_id bigint NOT NULL,
+  target_id bigint NOT NULL,
+  reason TEXT,
+  created_at timestamptz NOT NULL DEFAULT now(),
+  UNIQUE (guild_id, case_number),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
+
+CREATE TABLE moderation_settings (
+  moderation_setting_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL UNIQUE,
+  mute_role_id bigint,
+  -- Bumped with the row locked, so concurrent infractions get distinct case numbers
+  next_case_number integer NOT NULL DEFAULT 1,
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
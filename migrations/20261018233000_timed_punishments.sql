@@ -0,0 +1,19 @@
+ALTER TABLE infractions
+  ADD COLUMN duration integer;
+
+-- Punishments and roles which are lifted once they expire
+CREATE TABLE timed_punishments (
+  timed_punishment_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  user_id bigint NOT NULL,
+  kind TEXT NOT NULL CHECK (kind IN ('mute', 'ban', 'role')),
+  -- Role taken away on expiry, for mutes and role grants
+  role_id bigint,
+  expires_at timestamptz NOT NULL,
+  -- Failed lifts are retried later, each failure doubles the delay
+  failed_attempts integer NOT NULL DEFAULT 0,
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
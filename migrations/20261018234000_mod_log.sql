@@ -0,0 +1,2 @@
+ALTER TABLE moderation_settings
+  ADD COLUMN mod_log_channel_id bigint;
@@ -0,0 +1,14 @@
+ALTER TABLE moderation_settings
+  ADD COLUMN message_log_channel_id bigint;
+
+CREATE TABLE message_log_ignores (
+  message_log_ignore_id bigserial PRIMARY KEY,
+  guild_id bigint NOT NULL,
+  kind text NOT NULL CHECK (kind IN ('channel', 'role')),
+  target_id bigint NOT NULL,
+  UNIQUE (guild_id, target_id),
+  CONSTRAINT FK_guild_info FOREIGN KEY (guild_id)
+    REFERENCES guild_info (guild_id)
+    ON DELETE CASCADE
+    ON UPDATE NO ACTION
+);
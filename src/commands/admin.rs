@@ -1,30 +1,63 @@
 use crate::{
-    database::queries::ReactionRoles,
-    structures::context::{PublicData},
+    database::queries::{
+        BulkRoleJob,
+        BulkRoleJobs,
+        JobStatus,
+        ReactRole,
+        ReactionRoleMode,
+        ReactionRoles,
+        RoleChange,
+    },
+    parsers::{
+        bulk_role::{BulkRoleArgs, BulkRoleMode, MemberFilter},
+        message::{Message as MessageBuilder, MAX_CONTENT_LENGTH, MAX_EMBED_DESCRIPTION_LENGTH},
+        role_menu::RoleMenu,
+    },
+    structures::{
+        context::{PublicData},
+        errors::{CommandError, ParseError},
+        mod_log::{log_event, ModLogEvent},
+    },
     unwrap_or_return,
     utils::{
         channel::AsEmoji,
-        misc::{get_rich_from_args_or_prompt, role_from_name_or_mention},
+        misc::{get_message_url, get_rich_from_args_or_prompt, role_from_name_or_mention, split_into_chunks},
+        prompts,
     },
 };
 use anyhow::Context as AnyContext;
+use chrono::Utc;
 use core::convert::TryFrom;
 use serenity::{
+    builder::CreateMessage,
     framework::standard::{macros::command, Args, CommandResult},
+    http::{AttachmentType, Http},
     model::prelude::*,
     prelude::*,
     utils::parse_channel,
 };
 
 use serenity::futures::StreamExt;
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tracing::{debug, error, info};
 
+/// Pause between role changes of a bulk role job
+const BULK_ROLE_CHANGE_INTERVAL: Duration = Duration::from_millis(100);
+/// How often job progress is saved and its message edited
+const JOB_PROGRESS_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+/// Discord lists at most this many members per request
+const MEMBERS_PAGE_SIZE: u64 = 1000;
+
 /// Custom messages supporting embeds
-/// You can edit existing message
+/// You can edit existing message, or export it to json5 to tweak it
 #[command]
 #[only_in("guilds")]
-#[sub_commands(message_send, message_edit)]
+#[sub_commands(message_send, message_edit, message_export)]
 async fn message(ctx: &Context, msg: &Message) -> CommandResult {
     msg.channel_id
         .say(ctx, "Please use one of the subcommands!")
@@ -43,6 +76,7 @@ async fn message(ctx: &Context, msg: &Message) -> CommandResult {
 ///   footer: "Created with <3"
 /// } }
 /// ```
+/// Up to 10 embeds can be sent at once using `embeds: [{...}, {...}]`
 #[command("send")]
 #[required_permissions(Administrator)]
 #[aliases("new")]
@@ -105,14 +139,58 @@ async fn message_edit(ctx: &Context, msg: &Message, mut args: Args) -> CommandRe
     Ok(())
 }
 
-// TODO: Add handlers to automatically delete RR, when:
-// Message with assigned RR is deleted
-// Channel with message with RR is deleted
-// Role is deleted
+/// Export previously sent message as json5, which can be tweaked and passed to `message edit`
+/// Requires channel mention, followed by message ID
+/// Example: `message export #welcome 12345678`
+#[command("export")]
+#[required_permissions(Administrator)]
+#[aliases("dump")]
+#[num_args(2)]
+async fn message_export(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let channel_mention = args.single::<String>().context("Unable to get first argument")?;
+    let message_id_str = args.single::<String>().context("Unable to get second argument")?;
+
+    let channel_number = parse_channel(channel_mention.clone())
+        .with_context(|| format!("Not a valid channel mention: {:?}", channel_mention))?;
+    let channel = ChannelId(channel_number);
+    let message_id = message_id_str.parse::<u64>().context("Invalid message number")?;
+
+    let message = channel
+        .message(ctx, message_id)
+        .await
+        .context("Unable to find message")?;
+
+    let exported = serde_json::to_string_pretty(&MessageBuilder::from(&message))
+        .context("Unable to serialize message")?;
+    let content = format!("```json\n{}\n```", exported);
+
+    if content.chars().count() > MAX_CONTENT_LENGTH || exported.contains("```") {
+        // Wouldn't fit in a code block, so send it as a file instead
+        msg.channel_id
+            .send_message(ctx, |m| {
+                m.add_file(AttachmentType::Bytes {
+                    data: exported.into_bytes().into(),
+                    filename: format!("message-{}.json5", message_id),
+                })
+            })
+            .await?;
+    } else {
+        msg.channel_id.say(ctx, content).await?;
+    }
+
+    Ok(())
+}
+
 /// React to message to get a role
 #[command]
 #[only_in("guilds")]
-#[sub_commands(reaction_role_set, reaction_role_remove)]
+#[sub_commands(
+    reaction_role_set,
+    reaction_role_remove,
+    reaction_role_list,
+    reaction_role_menu,
+    reaction_role_group
+)]
 #[aliases("rr")]
 async fn reaction_role(ctx: &Context, msg: &Message) -> CommandResult {
     msg.channel_id
@@ -121,14 +199,18 @@ async fn reaction_role(ctx: &Context, msg: &Message) -> CommandResult {
     Ok(())
 }
 
-/// Sets reaction role toggle.
-/// React once to get the role assigned, react second time to get it removed.
-/// Requires channel mention, followed by message ID, role name and reaction (emoji)
-/// Example: `reaction_role set #welcome 12345678 CatPeople :cat:`
+/// Sets reaction role.
+/// Requires channel mention, followed by message ID, role name, reaction (emoji) and optionally mode:
+/// `toggle` (default) - react once to get the role assigned, react second time to get it removed
+/// `add-only` / `remove-only` - reacting only gives / only takes the role
+/// `verify` - reacting gives the role for good, the reaction stays
+/// `keep-reaction` - the role is kept as long as the reaction is
+/// Example: `reaction_role set #welcome 12345678 CatPeople :cat: keep-reaction`
 #[command("set")]
 #[required_permissions(Administrator)]
 #[aliases("new", "add", "create")]
-#[num_args(4)]
+#[min_args(4)]
+#[max_args(5)]
 async fn reaction_role_set(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let reaction_roles = {
         let data = ctx.data.read().await;
@@ -142,6 +224,15 @@ async fn reaction_role_set(ctx: &Context, msg: &Message, mut args: Args) -> Comm
     let message_id_str = args.single::<String>().context("Unable to get second argument")?;
     let role_name = args.single::<String>().context("Unable to get third argument")?;
     let reaction_string = args.single::<String>().context("Unable to get fourth argument")?;
+    let mode = match args.single::<String>() {
+        Ok(mode_str) => ReactionRoleMode::from_str(&mode_str).map_err(|_| {
+            CommandError::UserError(format!(
+                "Unknown mode `{}` - use toggle, add-only, remove-only, verify or keep-reaction",
+                mode_str
+            ))
+        })?,
+        Err(_) => ReactionRoleMode::Toggle,
+    };
 
     let channel_number = parse_channel(channel_mention.clone())
         .with_context(|| format!("Not a valid channel mention: {:?}", channel_mention))?;
@@ -163,9 +254,29 @@ async fn reaction_role_set(ctx: &Context, msg: &Message, mut args: Args) -> Comm
         .await
         .context("Unable to react to message. Is the emoji valid?")?;
     reaction_roles
-        .set_react_role(guild_id, channel, message.id, role_id, reaction_string)
+        .set_react_role(
+            guild_id,
+            channel,
+            message.id,
+            role_id,
+            reaction_string.clone(),
+            mode,
+        )
         .await
         .context("Unable to save reaction role")?;
+    log_reaction_role_change(
+        ctx,
+        msg,
+        guild_id,
+        format!(
+            "{} gives {} on {} ({})",
+            reaction_string,
+            role_id.mention(),
+            get_message_url(guild_id, channel, message.id),
+            mode.as_ref()
+        ),
+    )
+    .await;
     msg.channel_id
         .send_message(ctx, |m| m.content("Reaction role set sucessfully"))
         .await?;
@@ -201,12 +312,23 @@ async fn reaction_role_remove(ctx: &Context, msg: &Message, mut args: Args) -> C
     let guild_id = msg.guild_id.context("Not in a guild")?;
 
     let deleted = reaction_roles
-        .delete_react_role(guild_id, channel, message_id.into(), reaction_string)
+        .delete_react_role(guild_id, channel, message_id.into(), reaction_string.clone())
         .await
         .context("Error removing reaction role")?;
     let delete_msg = if deleted == 0 {
         "Reaction role not found"
     } else {
+        log_reaction_role_change(
+            ctx,
+            msg,
+            guild_id,
+            format!(
+                "Removed {} from {}",
+                reaction_string,
+                get_message_url(guild_id, channel, message_id.into())
+            ),
+        )
+        .await;
         "Reaction role removed sucessfully"
     };
     msg.channel_id
@@ -216,7 +338,338 @@ async fn reaction_role_remove(ctx: &Context, msg: &Message, mut args: Args) -> C
     Ok(())
 }
 
-pub async fn reaction_role_handler(ctx: &Context, reaction: &Reaction) {
+/// Lists reaction roles, optionally only in given channel
+/// Example: `reaction_role list #welcome`
+#[command("list")]
+#[required_permissions(Administrator)]
+#[aliases("ls")]
+#[max_args(1)]
+async fn reaction_role_list(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reaction_roles = {
+        let data = ctx.data.read().await;
+        let reaction_roles = data
+            .get::<ReactionRoles>()
+            .context("Can't get reaction roles")?
+            .clone();
+        reaction_roles
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let channel = match args.single::<String>() {
+        Ok(channel_mention) => Some(ChannelId(
+            parse_channel(channel_mention.clone())
+                .with_context(|| format!("Not a valid channel mention: {:?}", channel_mention))?,
+        )),
+        Err(_) => None,
+    };
+
+    let react_roles = reaction_roles.get_react_roles(guild_id, channel).await?;
+    if react_roles.is_empty() {
+        msg.channel_id.say(ctx, "No reaction roles found").await?;
+        return Ok(());
+    }
+
+    // Entries come sorted by message, so each message gets one section
+    let mut sections: Vec<String> = Vec::new();
+    let mut previous_message = None;
+    for react_role in &react_roles {
+        if previous_message != Some(react_role.message_id) {
+            previous_message = Some(react_role.message_id);
+            let url = get_message_url(guild_id, react_role.channel_id, react_role.message_id);
+            sections.push(format!(
+                "\n[Message]({}) in {}",
+                url,
+                react_role.channel_id.mention()
+            ));
+        }
+        sections.push(format!(
+            "{} → {} ({})",
+            react_role.reaction,
+            react_role.role_id.mention(),
+            react_role.mode.as_ref()
+        ));
+    }
+
+    let chunks = split_into_chunks(&sections, MAX_EMBED_DESCRIPTION_LENGTH);
+    let chunk_count = chunks.len();
+    for (idx, chunk) in chunks.into_iter().enumerate() {
+        msg.channel_id
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    if chunk_count > 1 {
+                        e.title(format!("Reaction roles ({}/{})", idx + 1, chunk_count));
+                    } else {
+                        e.title("Reaction roles");
+                    }
+                    e.description(chunk.trim_start())
+                });
+
+                m
+            })
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Posts role menu to given channel - an embed listing roles, with reactions to get them
+/// Takes json5 spec, or asks for the description and roles if it's not given
+/// Optional mode applies to all roles, see `reaction_role set`
+/// Example:
+/// ```
+/// .reaction_role menu #roles {
+///   title: "Colours",
+///   description: "Pick your favourite colour",
+///   mode: "toggle",
+///   roles: [
+///     { emoji: "🔴", role: "Red" },
+///     { emoji: "🔵", role: "Blue" },
+///   ]
+/// }
+/// ```
+#[command("menu")]
+#[required_permissions(Administrator)]
+#[min_args(1)]
+async fn reaction_role_menu(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reaction_roles = {
+        let data = ctx.data.read().await;
+        let reaction_roles = data
+            .get::<ReactionRoles>()
+            .context("Can't get reaction roles")?
+            .clone();
+        reaction_roles
+    };
+    let channel_mention = args.single::<String>().context("Unable to get first argument")?;
+    let channel_number = parse_channel(channel_mention.clone())
+        .with_context(|| format!("Not a valid channel mention: {:?}", channel_mention))?;
+    let channel = ChannelId(channel_number);
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+
+    let menu = if args.rest().trim().is_empty() {
+        match prompts::get_role_menu(ctx, msg.channel_id, &msg.author).await? {
+            Some(menu) => menu,
+            None => {
+                msg.channel_id.say(ctx, "Role menu creation cancelled").await?;
+                return Ok(());
+            }
+        }
+    } else {
+        json5::from_str::<RoleMenu>(args.rest()).map_err(ParseError::InvalidJson)?
+    };
+    menu.validate()?;
+    let mode = match &menu.mode {
+        Some(mode_str) => ReactionRoleMode::from_str(mode_str).map_err(|_| {
+            CommandError::UserError(format!(
+                "Unknown mode `{}` - use toggle, add-only, remove-only, verify or keep-reaction",
+                mode_str
+            ))
+        })?,
+        None => ReactionRoleMode::Toggle,
+    };
+
+    // Resolve everything before posting, so mistakes don't leave half-made menu behind
+    let mut role_ids = Vec::with_capacity(menu.roles.len());
+    let mut reactions = Vec::with_capacity(menu.roles.len());
+    for entry in &menu.roles {
+        role_ids.push(role_from_name_or_mention(ctx, &guild_id, entry.role.clone()).await?);
+        reactions.push(
+            ReactionType::try_from(entry.emoji.clone())
+                .with_context(|| format!("Invalid emoji: {:?}", entry.emoji))?,
+        );
+    }
+    let rich_message = CreateMessage::try_from(menu.to_message(&role_ids))?;
+
+    let menu_message = channel
+        .send_message(ctx, |m| {
+            m.0 = rich_message.0;
+            m
+        })
+        .await?;
+    for reaction in &reactions {
+        if let Err(e) = menu_message.react(ctx, reaction.clone()).await {
+            let _ = menu_message.delete(ctx).await;
+            return Err(e).context(format!("Unable to react with {}. Is the emoji valid?", reaction))?;
+        }
+    }
+    let roles = role_ids
+        .into_iter()
+        .zip(reactions.iter().map(ToString::to_string))
+        .collect::<Vec<(RoleId, String)>>();
+    if let Err(e) = reaction_roles
+        .set_react_roles(guild_id, channel, menu_message.id, roles, mode)
+        .await
+    {
+        // Menu that doesn't give any roles would only confuse people
+        let _ = menu_message.delete(ctx).await;
+        return Err(e).context("Unable to save reaction roles")?;
+    }
+    log_reaction_role_change(
+        ctx,
+        msg,
+        guild_id,
+        format!(
+            "Posted role menu with {} roles: {}",
+            reactions.len(),
+            get_message_url(guild_id, channel, menu_message.id)
+        ),
+    )
+    .await;
+
+    msg.channel_id
+        .say(
+            ctx,
+            format!(
+                "Role menu posted: {}",
+                get_message_url(guild_id, channel, menu_message.id)
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Manage exclusive groups of reaction roles - member can only have one role from a group,
+/// getting a role from it removes the others
+/// Usage to add message's reaction roles to group: `reaction_role group add <group> #channel <message_id> <emoji...>`
+/// Usage to take them out of groups: `reaction_role group remove #channel <message_id> <emoji...>`
+/// Usage to delete the group: `reaction_role group delete <group>`
+/// Usage to list groups: `reaction_role group`
+/// Example: `reaction_role group add colours #roles 12345678 :red_circle: :blue_circle:`
+#[command("group")]
+#[required_permissions(Administrator)]
+#[aliases("groups")]
+async fn reaction_role_group(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let reaction_roles = {
+        let data = ctx.data.read().await;
+        let reaction_roles = data
+            .get::<ReactionRoles>()
+            .context("Can't get reaction roles")?
+            .clone();
+        reaction_roles
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+
+    if args.is_empty() {
+        let groups = reaction_roles.get_groups(guild_id).await?;
+        let description = if groups.is_empty() {
+            "No groups yet, create one with `reaction_role group add`".to_string()
+        } else {
+            groups
+                .iter()
+                .map(|(name, role_ids)| {
+                    let roles = role_ids
+                        .iter()
+                        .map(|role_id| role_id.mention().to_string())
+                        .collect::<Vec<String>>();
+                    format!("**{}**: {}", name, roles.join(", "))
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+        msg.channel_id
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    e.title("Reaction role groups");
+                    e.description(description)
+                });
+
+                m
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let action = args.single::<String>().context("Unable to get first argument")?;
+    if action == "delete" {
+        let name = args.single::<String>().context("Unable to get second argument")?;
+        let deleted = reaction_roles.delete_group(guild_id, name.clone()).await?;
+        let response = if deleted == 0 {
+            format!("Group `{}` not found", name)
+        } else {
+            log_reaction_role_change(ctx, msg, guild_id, format!("Deleted group `{}`", name)).await;
+            format!(
+                "Group `{}` deleted, its reaction roles are no longer exclusive",
+                name
+            )
+        };
+        msg.channel_id.say(ctx, response).await?;
+        return Ok(());
+    }
+
+    let name = match action.as_str() {
+        "add" => Some(args.single::<String>().context("Unable to get second argument")?),
+        "remove" => None,
+        _ => {
+            return Err(CommandError::UserError(format!(
+                "Unknown action `{}` - use add, remove or delete",
+                action
+            ))
+            .into())
+        }
+    };
+    let channel_mention = args
+        .single::<String>()
+        .context("Unable to get channel argument")?;
+    let message_id_str = args
+        .single::<String>()
+        .context("Unable to get message ID argument")?;
+    let channel_number = parse_channel(channel_mention.clone())
+        .with_context(|| format!("Not a valid channel mention: {:?}", channel_mention))?;
+    let channel = ChannelId(channel_number);
+    let message_id = MessageId(message_id_str.parse::<u64>().context("Invalid message number")?);
+    let reactions = args
+        .iter::<String>()
+        .collect::<Result<Vec<String>, _>>()
+        .context("Unable to get emoji arguments")?;
+    if reactions.is_empty() {
+        return Err(CommandError::UserError("Please provide reaction emojis".to_string()).into());
+    }
+
+    let (updated, response) = match name {
+        Some(name) => (
+            reaction_roles
+                .set_group(guild_id, name.clone(), channel, message_id, reactions)
+                .await?,
+            format!("reaction roles added to group `{}`", name),
+        ),
+        None => (
+            reaction_roles
+                .unset_group(guild_id, channel, message_id, reactions)
+                .await?,
+            "reaction roles removed from their groups".to_string(),
+        ),
+    };
+    let response = if updated == 0 {
+        "No matching reaction roles found on that message".to_string()
+    } else {
+        let response = format!("{} {}", updated, response);
+        log_reaction_role_change(
+            ctx,
+            msg,
+            guild_id,
+            format!(
+                "{} on {}",
+                response,
+                get_message_url(guild_id, channel, message_id)
+            ),
+        )
+        .await;
+        response
+    };
+    msg.channel_id.say(ctx, response).await?;
+
+    Ok(())
+}
+
+async fn log_reaction_role_change(ctx: &Context, msg: &Message, guild_id: GuildId, change: String) {
+    let event = ModLogEvent::ReactionRoleChanged {
+        moderator: msg.author.id,
+        change,
+    };
+    log_event(ctx, guild_id, event).await;
+}
+
+/// Handles both adding and removing reactions, what happens depends on reaction role's mode
+pub async fn reaction_role_handler(ctx: &Context, reaction: &Reaction, added: bool) {
     // TODO: Replace error logging here with some timed-out messages and lower priority logs
     let (reaction_roles, bot_id) = {
         let data = ctx.data.read().await;
@@ -247,97 +700,570 @@ pub async fn reaction_role_handler(ctx: &Context, reaction: &Reaction) {
             reaction.emoji.to_string(),
         )
         .await;
-    let maybe_role_id = unwrap_or_return!(
+    let maybe_react_role = unwrap_or_return!(
         role_result,
         |e: &dyn Debug| {
             error!("Error getting ReactRole: {:?}", e);
         },
         {}
     );
-    let role_id = unwrap_or_return!(maybe_role_id);
-    let user = unwrap_or_return!(
-        reaction.user(&ctx).await,
+    let react_role = unwrap_or_return!(maybe_react_role);
+    let ReactRole { role_id, mode, .. } = react_role;
+    if !added && mode != ReactionRoleMode::KeepReaction {
+        // Includes removals of reactions we deleted ourselves
+        return;
+    }
+    let user_id = unwrap_or_return!(reaction.user_id);
+    let mut member = unwrap_or_return!(
+        guild_id.member(&ctx, user_id).await,
         |e: &dyn Debug| {
-            error!("Error getting ReactRole user: {:?}", e);
+            error!("Error getting ReactRole member: {:?}", e);
         },
         {}
     );
-    let mut member = unwrap_or_return!(
-        guild_id.member(&ctx, user.id).await,
+    match mode.role_change(added, member.roles.contains(&role_id)) {
+        Some(RoleChange::Add) => match member.add_role(&ctx, role_id).await {
+            Ok(_) => {
+                if let Some(react_role_group_id) = react_role.react_role_group_id {
+                    remove_other_group_roles(
+                        ctx,
+                        &reaction_roles,
+                        &mut member,
+                        &react_role,
+                        react_role_group_id,
+                    )
+                    .await;
+                }
+            }
+            Err(e) => error!("Error assigning role to user: {:?}", e),
+        },
+        Some(RoleChange::Remove) => {
+            if let Err(e) = member.remove_role(&ctx, role_id).await {
+                error!("Error removing role from user: {:?}", e);
+            };
+        }
+        None => {}
+    }
+
+    if added && mode.removes_reaction() {
+        if let Err(e) = reaction.delete(&ctx).await {
+            error!("Error deleting ReactRole reaction: {:?}", e);
+        }
+    }
+}
+
+/// Keeps exclusive group exclusive, by taking away member's other roles from it.
+/// Reactions that would stay on the messages get removed too, so they don't suggest member still has the role.
+async fn remove_other_group_roles(
+    ctx: &Context,
+    reaction_roles: &ReactionRoles,
+    member: &mut Member,
+    react_role: &ReactRole,
+    react_role_group_id: i64,
+) {
+    let group_react_roles = unwrap_or_return!(
+        reaction_roles.get_group_react_roles(react_role_group_id).await,
         |e: &dyn Debug| {
-            error!("Error getting ReactRole member: {:?}", e);
+            error!("Error getting ReactRole group: {:?}", e);
         },
         {}
     );
-    if member.roles.contains(&role_id) {
-        if let Err(e) = member.remove_role(&ctx, role_id).await {
-            error!("Error removing role from user: {:?}", e);
-        };
-    } else {
-        if let Err(e) = member.add_role(&ctx, role_id).await {
-            error!("Error assigning role to user: {:?}", e);
-        };
+    let others = group_react_roles
+        .iter()
+        .filter(|other| {
+            other.react_role_id != react_role.react_role_id && other.role_id != react_role.role_id
+        })
+        .collect::<Vec<_>>();
+    let mut roles_to_remove = others
+        .iter()
+        .map(|other| other.role_id)
+        .filter(|role_id| member.roles.contains(role_id))
+        .collect::<Vec<RoleId>>();
+    roles_to_remove.sort();
+    roles_to_remove.dedup();
+    if !roles_to_remove.is_empty() {
+        if let Err(e) = member.remove_roles(&ctx, &roles_to_remove).await {
+            error!("Error removing group roles from user: {:?}", e);
+        }
     }
-
-    if let Err(e) = reaction.delete(&ctx).await {
-        error!("Error deleting ReactRole reaction: {:?}", e);
+    for other in others.iter().filter(|other| !other.mode.removes_reaction()) {
+        let reaction_type = unwrap_or_return!(
+            ReactionType::try_from(other.reaction.clone()),
+            |e: &dyn Debug| {
+                error!("Invalid ReactRole emoji {:?}: {:?}", other.reaction, e);
+            },
+            {}
+        );
+        if let Err(e) = other
+            .channel_id
+            .delete_reaction(&ctx, other.message_id, Some(member.user.id), reaction_type)
+            .await
+        {
+            debug!("Unable to delete group reaction {:?}: {:?}", other.reaction, e);
+        }
     }
 }
 
-/// Give role in bulk to people with another role
-/// Usage: .bulk_role NewSuperRole OldRole
+/// Add or remove role in bulk, for members matching filters
+/// Changes are made in background, follow them with `job list`
+/// Usage: `bulk_role <role> [required roles...] [options]`
+/// Options:
+/// `--remove` - remove the role instead of adding it
+/// `--any <roles...>` - members need at least one of these roles
+/// `--none <roles...>` - members can't have any of these roles
+/// `--all <roles...>` - members need all of these roles, that's the default for listed roles
+/// `--humans` or `--bots` - only change humans or bots
+/// `--joined-before <date>`, `--joined-after <date>` - date is either `YYYY-MM-DD` or how long ago, like `30d`
+/// `--dry-run` - only count members who would change
+/// Quote role names with spaces
+/// Example: `bulk_role NewSuperRole OldRole`
+/// Example: `bulk_role Member --any Red Blue --none Muted --humans --joined-before 7d --dry-run`
 #[command]
 #[only_in("guilds")]
 #[required_permissions(Manage_Roles)]
-#[num_args(2)]
+#[min_args(1)]
 async fn bulk_role(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let guild_id = msg.guild_id.context("Not in a guild")?;
+    let raw_args = args
+        .quoted()
+        .iter::<String>()
+        .collect::<Result<Vec<String>, _>>()
+        .context("Unable to iterate over arguments!")?;
+    let bulk_role_args = BulkRoleArgs::parse(raw_args, Utc::now())?;
     // _typing is stopped when it's dropped
     let _typing = msg
         .channel_id
         .start_typing(&ctx.http)
         .context("Unable to start typing")?;
-    let mut role_ids = Vec::new();
-    for role_name_result in args.iter::<String>() {
-        let role_name = role_name_result.context("Unable to iterate over arguments!")?;
-        let role_id = role_from_name_or_mention(&ctx, &guild_id, role_name.clone())
+    let role = role_from_name_or_mention(ctx, &guild_id, bulk_role_args.role.clone())
+        .await
+        .context(format!("Unable to parse role: `{}`", bulk_role_args.role))?;
+    let filter = MemberFilter {
+        all: resolve_roles(ctx, guild_id, &bulk_role_args.filter.all).await?,
+        any: resolve_roles(ctx, guild_id, &bulk_role_args.filter.any).await?,
+        none: resolve_roles(ctx, guild_id, &bulk_role_args.filter.none).await?,
+        target: bulk_role_args.filter.target,
+        joined_before: bulk_role_args.filter.joined_before,
+        joined_after: bulk_role_args.filter.joined_after,
+    };
+    let mode = bulk_role_args.mode;
+
+    if bulk_role_args.dry_run {
+        let mut changed_counter = 0;
+        let mut members_stream = guild_id.members_iter(&ctx).boxed();
+        while let Some(member_result) = members_stream.next().await {
+            let member = member_result.context("Error getting member information")?;
+            if mode.needs_change(member.roles.contains(&role))
+                && filter.matches(&member.roles, member.user.bot, member.joined_at)
+            {
+                changed_counter += 1;
+            }
+        }
+        let response = match mode {
+            BulkRoleMode::Add => format!("Role would be applied to {} members.", changed_counter),
+            BulkRoleMode::Remove => format!("Role would be removed from {} members.", changed_counter),
+        };
+        msg.channel_id.send_message(ctx, |m| m.content(response)).await?;
+        return Ok(());
+    }
+
+    let bulk_role_jobs = {
+        let data = ctx.data.read().await;
+        let bulk_role_jobs = data
+            .get::<BulkRoleJobs>()
+            .context("Can't get bulk role jobs")?
+            .clone();
+        bulk_role_jobs
+    };
+    let mut job = bulk_role_jobs
+        .create_job(guild_id, msg.channel_id, msg.author.id, role, mode, &filter)
+        .await?;
+    let progress_message = msg
+        .channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title(format!("Bulk role job #{}", job.bulk_role_job_id));
+                e.description(describe_job(&job))
+            })
+        })
+        .await?;
+    bulk_role_jobs
+        .set_progress_message(job.bulk_role_job_id, progress_message.id)
+        .await?;
+    job.progress_message_id = Some(progress_message.id);
+    tokio::spawn(run_bulk_role_job(ctx.http.clone(), bulk_role_jobs, job));
+
+    Ok(())
+}
+
+/// Describes what job does and how far it got
+fn describe_job(job: &BulkRoleJob) -> String {
+    let action = match job.mode {
+        BulkRoleMode::Add => "Adding",
+        BulkRoleMode::Remove => "Removing",
+    };
+    let status = match job.status {
+        JobStatus::Running => "running".to_string(),
+        JobStatus::Cancelled => "cancelled".to_string(),
+        JobStatus::Finished => "finished".to_string(),
+        JobStatus::Failed => format!("failed: {}", job.error.as_deref().unwrap_or("unknown error")),
+    };
+    format!(
+        "{} {} - **{}**\nStarted by {} on {}\nChecked {} members, changed {}, failed {}",
+        action,
+        job.role_id.mention(),
+        status,
+        job.created_by.mention(),
+        job.created_at.format("%Y-%m-%d %H:%M UTC"),
+        job.progress.processed,
+        job.progress.changed,
+        job.progress.failed
+    )
+}
+
+async fn update_progress_message(http: &Http, job: &BulkRoleJob) {
+    let message_id = match job.progress_message_id {
+        Some(message_id) => message_id,
+        None => return,
+    };
+    let result = job
+        .channel_id
+        .edit_message(http, message_id, |m| {
+            m.embed(|e| {
+                e.title(format!("Bulk role job #{}", job.bulk_role_job_id));
+                e.description(describe_job(job))
+            })
+        })
+        .await;
+    if let Err(e) = result {
+        debug!(
+            "Unable to update progress message of job {}: {:?}",
+            job.bulk_role_job_id, e
+        );
+    }
+}
+
+/// Processes bulk role job until it's finished or cancelled, progress is saved periodically
+pub async fn run_bulk_role_job(http: Arc<Http>, bulk_role_jobs: Arc<BulkRoleJobs>, mut job: BulkRoleJob) {
+    let job_id = job.bulk_role_job_id;
+    if !bulk_role_jobs.claim(job_id).await {
+        debug!("Job {} is already being processed", job_id);
+        return;
+    }
+    info!("Processing bulk role job {}", job_id);
+    job.status = match process_bulk_role_job(&http, &bulk_role_jobs, &mut job).await {
+        Ok(status) => status,
+        Err(e) => {
+            error!("Bulk role job {} failed: {:?}", job_id, e);
+            let error = e.to_string();
+            if let Err(e) = bulk_role_jobs
+                .set_status(
+                    job_id,
+                    JobStatus::Failed,
+                    &[JobStatus::Running],
+                    Some(error.clone()),
+                )
+                .await
+            {
+                error!("Error marking job {} as failed: {:?}", job_id, e);
+            }
+            job.error = Some(error);
+            JobStatus::Failed
+        }
+    };
+    update_progress_message(&http, &job).await;
+    bulk_role_jobs.release(job_id).await;
+}
+
+async fn process_bulk_role_job(
+    http: &Arc<Http>,
+    bulk_role_jobs: &BulkRoleJobs,
+    job: &mut BulkRoleJob,
+) -> Result<JobStatus, anyhow::Error> {
+    let job_id = job.bulk_role_job_id;
+    let mut last_update = Instant::now();
+    // Members are listed by id, so interrupted job continues right after the last processed one
+    loop {
+        let members = http
+            .get_guild_members(
+                job.guild_id.0,
+                Some(MEMBERS_PAGE_SIZE),
+                Some(job.progress.last_user_id.0),
+            )
             .await
-            .context(format!("Unable to parse role: `{}`", role_name))?;
-        if u64::from(role_id) != u64::from(guild_id) {
-            // Special case for @everyone
-            role_ids.push(role_id);
+            .context("Error getting member information")?;
+        let page_size = members.len() as u64;
+        for member in members {
+            if job.mode.needs_change(member.roles.contains(&job.role_id))
+                && job
+                    .filter
+                    .matches(&member.roles, member.user.bot, member.joined_at)
+            {
+                let (guild_id, user_id, role_id) = (job.guild_id.0, member.user.id.0, job.role_id.0);
+                let result = match job.mode {
+                    BulkRoleMode::Add => http.add_member_role(guild_id, user_id, role_id).await,
+                    BulkRoleMode::Remove => http.remove_member_role(guild_id, user_id, role_id).await,
+                };
+                match result {
+                    Ok(_) => job.progress.changed += 1,
+                    Err(e) => {
+                        job.progress.failed += 1;
+                        if let Err(e) = bulk_role_jobs
+                            .add_job_error(job_id, member.user.id, e.to_string())
+                            .await
+                        {
+                            error!("Error saving error of job {}: {:?}", job_id, e);
+                        }
+                    }
+                }
+                // Serenity waits out rate limits, this just leaves room for other requests
+                tokio::time::sleep(BULK_ROLE_CHANGE_INTERVAL).await;
+            }
+            job.progress.processed += 1;
+            job.progress.last_user_id = member.user.id;
+
+            if last_update.elapsed() >= JOB_PROGRESS_UPDATE_INTERVAL {
+                job.status = bulk_role_jobs.save_progress(job_id, job.progress).await?;
+                if job.status != JobStatus::Running {
+                    return Ok(job.status);
+                }
+                update_progress_message(http, job).await;
+                last_update = Instant::now();
+            }
+        }
+        if page_size < MEMBERS_PAGE_SIZE {
+            break;
         }
     }
-    let role_to_add = role_ids.swap_remove(0);
-
-    let mut role_add_counter = 0;
-    let mut members_stream = guild_id.members_iter(&ctx).boxed();
-    while let Some(member_result) = members_stream.next().await {
-        let mut member = member_result
-            .context("Error getting member information - role application might be in partial state")?;
-        if !member.roles.contains(&role_to_add)
-            && role_ids.iter().all(|role_id| member.roles.contains(role_id))
-        {
-            member.add_role(&ctx, role_to_add).await.context(format!(
-                "Error giving {} a role.",
-                member.nick.unwrap_or(member.user.name)
-            ))?;
-            role_add_counter += 1;
+
+    job.status = bulk_role_jobs.save_progress(job_id, job.progress).await?;
+    if job.status != JobStatus::Running {
+        return Ok(job.status);
+    }
+    bulk_role_jobs
+        .set_status(job_id, JobStatus::Finished, &[JobStatus::Running], None)
+        .await?;
+    info!("Bulk role job {} finished", job_id);
+    Ok(JobStatus::Finished)
+}
+
+/// Continues jobs interrupted by restart
+pub async fn resume_bulk_role_jobs(http: Arc<Http>, bulk_role_jobs: Arc<BulkRoleJobs>) {
+    let jobs = match bulk_role_jobs.get_running_jobs().await {
+        Ok(jobs) => jobs,
+        Err(e) => {
+            error!("Error getting running bulk role jobs: {:?}", e);
+            return;
         }
+    };
+    for job in jobs {
+        tokio::spawn(run_bulk_role_job(http.clone(), bulk_role_jobs.clone(), job));
+    }
+}
+
+/// Background bulk role changes
+/// Usage to see recent ones: `job list`
+/// Usage to stop one: `job cancel <id>`
+/// Usage to continue cancelled or failed one: `job resume <id>`
+/// Usage to see members whose roles couldn't be changed: `job errors <id>`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Roles)]
+#[aliases("jobs")]
+#[sub_commands(job_list, job_cancel, job_resume, job_errors)]
+async fn job(ctx: &Context, msg: &Message) -> CommandResult {
+    msg.channel_id
+        .say(
+            ctx,
+            "Please use one of the subcommands! (list, cancel, resume, errors)",
+        )
+        .await?;
+    Ok(())
+}
+
+#[command("list")]
+async fn job_list(ctx: &Context, msg: &Message) -> CommandResult {
+    const LISTED_JOBS: i64 = 10;
+    let bulk_role_jobs = {
+        let data = ctx.data.read().await;
+        let bulk_role_jobs = data
+            .get::<BulkRoleJobs>()
+            .context("Can't get bulk role jobs")?
+            .clone();
+        bulk_role_jobs
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let jobs = bulk_role_jobs.get_jobs(guild_id, LISTED_JOBS).await?;
+    if jobs.is_empty() {
+        msg.channel_id.say(ctx, "There are no jobs yet").await?;
+        return Ok(());
     }
 
     msg.channel_id
         .send_message(ctx, |m| {
-            m.content(format!(
-                "Succesfully applied role to {} members.",
-                role_add_counter
-            ))
+            m.embed(|e| {
+                e.title("Recent jobs");
+                for job in &jobs {
+                    e.field(format!("#{}", job.bulk_role_job_id), describe_job(job), false);
+                }
+                e
+            })
         })
         .await?;
+    Ok(())
+}
+
+#[command("cancel")]
+#[aliases("stop")]
+#[num_args(1)]
+async fn job_cancel(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let job_id = args.single::<i64>().context("Unable to parse job id")?;
+    let bulk_role_jobs = {
+        let data = ctx.data.read().await;
+        let bulk_role_jobs = data
+            .get::<BulkRoleJobs>()
+            .context("Can't get bulk role jobs")?
+            .clone();
+        bulk_role_jobs
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let job = bulk_role_jobs
+        .get_job(guild_id, job_id)
+        .await?
+        .ok_or_else(|| CommandError::UserError(format!("Job #{} doesn't exist", job_id)))?;
 
+    let cancelled = bulk_role_jobs
+        .set_status(
+            job.bulk_role_job_id,
+            JobStatus::Cancelled,
+            &[JobStatus::Running],
+            None,
+        )
+        .await?;
+    if !cancelled {
+        return Err(CommandError::UserError(format!("Job #{} isn't running", job_id)).into());
+    }
+    msg.channel_id
+        .say(
+            ctx,
+            format!(
+                "Job #{} cancelled, members already changed keep their roles. Continue it with `job resume {}`",
+                job_id, job_id
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command("resume")]
+#[aliases("continue")]
+#[num_args(1)]
+async fn job_resume(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let job_id = args.single::<i64>().context("Unable to parse job id")?;
+    let bulk_role_jobs = {
+        let data = ctx.data.read().await;
+        let bulk_role_jobs = data
+            .get::<BulkRoleJobs>()
+            .context("Can't get bulk role jobs")?
+            .clone();
+        bulk_role_jobs
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let mut job = bulk_role_jobs
+        .get_job(guild_id, job_id)
+        .await?
+        .ok_or_else(|| CommandError::UserError(format!("Job #{} doesn't exist", job_id)))?;
+    if bulk_role_jobs.is_active(job_id).await {
+        return Err(CommandError::UserError(format!(
+            "Job #{} is still running or stopping, try again in a moment",
+            job_id
+        ))
+        .into());
+    }
+
+    let resumed = bulk_role_jobs
+        .set_status(
+            job_id,
+            JobStatus::Running,
+            &[JobStatus::Cancelled, JobStatus::Failed],
+            None,
+        )
+        .await?;
+    if !resumed {
+        return Err(CommandError::UserError(format!("Job #{} can't be resumed", job_id)).into());
+    }
+    job.status = JobStatus::Running;
+    job.error = None;
+    msg.channel_id
+        .say(ctx, format!("Job #{} resumed!", job_id))
+        .await?;
+    tokio::spawn(run_bulk_role_job(ctx.http.clone(), bulk_role_jobs, job));
     Ok(())
 }
 
+#[command("errors")]
+#[num_args(1)]
+async fn job_errors(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    const LISTED_ERRORS: i64 = 50;
+    let job_id = args.single::<i64>().context("Unable to parse job id")?;
+    let bulk_role_jobs = {
+        let data = ctx.data.read().await;
+        let bulk_role_jobs = data
+            .get::<BulkRoleJobs>()
+            .context("Can't get bulk role jobs")?
+            .clone();
+        bulk_role_jobs
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let job = bulk_role_jobs
+        .get_job(guild_id, job_id)
+        .await?
+        .ok_or_else(|| CommandError::UserError(format!("Job #{} doesn't exist", job_id)))?;
+    let errors = bulk_role_jobs
+        .get_job_errors(job.bulk_role_job_id, LISTED_ERRORS)
+        .await?;
+    if errors.is_empty() {
+        msg.channel_id
+            .say(ctx, format!("Job #{} has no errors", job_id))
+            .await?;
+        return Ok(());
+    }
+
+    let lines = errors
+        .iter()
+        .map(|(user_id, error)| format!("{} - {}", user_id.mention(), error))
+        .collect::<Vec<String>>();
+    for chunk in split_into_chunks(&lines, MAX_EMBED_DESCRIPTION_LENGTH) {
+        msg.channel_id
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    e.title(format!("Errors of job #{}", job_id));
+                    e.description(chunk)
+                })
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Resolves role names or mentions, skipping @everyone which every member has
+async fn resolve_roles(
+    ctx: &Context,
+    guild_id: GuildId,
+    role_names: &[String],
+) -> Result<Vec<RoleId>, anyhow::Error> {
+    let mut role_ids = Vec::new();
+    for role_name in role_names {
+        let role_id = role_from_name_or_mention(ctx, &guild_id, role_name.clone())
+            .await
+            .context(format!("Unable to parse role: `{}`", role_name))?;
+        if u64::from(role_id) != u64::from(guild_id) {
+            role_ids.push(role_id);
+        }
+    }
+    Ok(role_ids)
+}
+
 /// React to a given message with emoji(s)
 #[command]
 #[min_args(3)]
@@ -1,19 +1,57 @@
 use crate::{
-    database::queries::{CustomCommands, GuildInfoTable, JoinRoles},
-    structures::{context::PublicData, errors::*},
+    database::queries::{
+        Autoresponders,
+        CustomCommands,
+        GuildInfoTable,
+        JoinRole,
+        JoinRoles,
+        PendingJoinRole,
+        RestrictionKind,
+        StickyRoles,
+        WelcomeMessage,
+        WelcomeMessageKind,
+        WelcomeMessages,
+    },
+    parsers::{
+        duration::{format_duration, parse_duration},
+        message::Message as MessageBuilder,
+        template::{render, render_rich, starts_with_placeholder},
+    },
+    structures::{
+        autoresponders::{Trigger, TriggerKind},
+        context::PublicData,
+        cooldowns::CooldownScope,
+        errors::*,
+        member_kind::MemberKind,
+        mod_log::{log_event, ModLogEvent},
+    },
     unwrap_or_return,
     utils::{
-        misc::{role_from_name_or_mention, send_rich_serialized_message},
+        misc::{
+            is_not_found,
+            parse_user_id,
+            role_from_name_or_mention,
+            send_rendered,
+            send_rich_serialized_message,
+            template_variables,
+        },
         permissions,
     },
 };
 use anyhow::{anyhow, Context as AnyContext};
+use chrono::{Duration as ChronoDuration, Utc};
 use serenity::{
     framework::standard::{macros::command, Args, CommandResult},
+    http::Http,
     model::prelude::*,
     prelude::*,
+    utils::parse_channel,
 };
-use tracing::error;
+use std::{str::FromStr, sync::Arc, time::Duration};
+use tracing::{debug, error};
+
+/// How often delayed join roles are checked, so they are given at most this late
+const DELAYED_JOIN_ROLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Changes prefix in current guild.
 #[command]
@@ -34,6 +72,15 @@ async fn prefix(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let new_prefix = args.single::<String>().unwrap();
 
     guild_info.set_prefix(guild_id, new_prefix.clone()).await?;
+    log_event(
+        ctx,
+        guild_id,
+        ModLogEvent::PrefixChanged {
+            moderator: msg.author.id,
+            prefix: new_prefix.clone(),
+        },
+    )
+    .await;
 
     msg.channel_id
         .say(
@@ -46,20 +93,41 @@ async fn prefix(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
 
 /// Custom commands for your server that output a message
 /// Usage to set: `command set <name> <content to be said>`
+/// Usage to add random response variants: `command addresponse <name> [weight=<n>] <content>`
 /// Usage to remove: `command remove <name>`
+/// Usage to add another name: `command alias <alias> <name>`
+/// Usage to limit who can use it and where: `command restrict <name> allow|deny|remove <role or #channel>`
+/// Usage to limit how often it can be used: `command cooldown <name> guild|user|channel <seconds>`
 #[command]
 #[only_in("guilds")]
-#[sub_commands(command_set, command_remove, command_list)]
+#[sub_commands(
+    command_set,
+    command_remove,
+    command_list,
+    command_alias,
+    command_unalias,
+    command_restrict,
+    command_cooldown,
+    command_addresponse,
+    command_responses,
+    command_removeresponse
+)]
 async fn command(ctx: &Context, msg: &Message) -> CommandResult {
     msg.channel_id
-        .say(ctx, "Please use one of the subcommands! (set, remove, list)")
+        .say(
+            ctx,
+            "Please use one of the subcommands! (set, remove, list, alias, unalias, restrict, cooldown, addresponse, responses, removeresponse)",
+        )
         .await?;
 
     Ok(())
 }
 
-/// Set or update a custom command
+/// Set or update a custom command, replacing all of its responses
 /// Example: `command set website https://www.example.com`
+/// Content can use placeholders, which are filled in when the command is used:
+/// `{user}`, `{user.mention}`, `{guild.name}`, `{guild.member_count}`, `{channel}`, `{args}`, `{arg1}`, `{date}`
+/// Use `{{` and `}}` to get literal braces
 /// You can also define more complex messages using json5
 /// Example:
 /// ```
@@ -101,21 +169,23 @@ async fn command_set(ctx: &Context, msg: &Message, mut args: Args) -> CommandRes
 
     let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
 
-    let content = args.rest();
-
-    if content.starts_with("{") {
-        // Assume this is special content, which needs to be parsed
-        // So check if it can be deserialized
-        send_rich_serialized_message(ctx, msg.channel_id, content).await?;
-    } else {
+    if custom_commands.is_alias(guild_id, command_name.clone()).await? {
         msg.channel_id
-            .send_message(ctx, |msg| msg.content(content))
+            .say(
+                ctx,
+                "This name is already used as an alias! Remove it with `command unalias` first.",
+            )
             .await?;
+        Err(anyhow!("Command {} is already an alias.", command_name))?;
     }
 
+    let content = args.rest();
+    preview_response(ctx, msg, guild_id, content).await?;
+
     custom_commands
         .set_command(guild_id, command_name.clone(), content.to_string())
         .await?;
+    log_command_change(ctx, msg, guild_id, &command_name, "Set".to_string()).await;
 
     msg.channel_id
         .say(ctx, format!("Command `{}` successfully set!", command_name))
@@ -124,13 +194,38 @@ async fn command_set(ctx: &Context, msg: &Message, mut args: Args) -> CommandRes
     Ok(())
 }
 
-/// Remove custom command, by its name
-#[command("remove")]
+/// Sends response as if the command was invoked by the author, which also validates placeholders
+async fn preview_response(ctx: &Context, msg: &Message, guild_id: GuildId, content: &str) -> CommandResult {
+    let variables =
+        template_variables(ctx, &msg.author, Some(guild_id), Some(msg.channel_id), Vec::new()).await;
+    if content.starts_with("{") && !starts_with_placeholder(content) {
+        // Assume this is special content, which needs to be parsed
+        // So check if it can be deserialized
+        let rendered = render_rich(content, &variables)?;
+        send_rich_serialized_message(ctx, msg.channel_id, &rendered).await?;
+    } else {
+        let rendered = render(content, &variables)?;
+        MessageBuilder {
+            content: Some(rendered.clone()),
+            embed: None,
+        }
+        .validate()?;
+        msg.channel_id
+            .send_message(ctx, |msg| msg.content(rendered))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Add another possible response to existing custom command, one of them is picked randomly
+/// Optional `weight=<n>` makes the response more likely, default weight is 1
+/// Example: `command addresponse 8ball weight=3 Ask again later`
+#[command("addresponse")]
 #[required_permissions(Administrator)]
-#[aliases("delete", "del")]
-#[num_args(1)]
-async fn command_remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let command_name = args.single::<String>().unwrap();
+#[aliases("addvariant")]
+#[min_args(2)]
+async fn command_addresponse(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let command_name = args.single::<String>().context("Unable to get first argument")?;
     let custom_commands = {
         let data = ctx.data.read().await;
         let custom_commands = data
@@ -141,20 +236,61 @@ async fn command_remove(ctx: &Context, msg: &Message, mut args: Args) -> Command
     };
     let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
 
+    let weight = match args.current().and_then(|arg| arg.strip_prefix("weight=")) {
+        Some(weight) => {
+            let weight = weight
+                .parse::<i32>()
+                .ok()
+                .filter(|weight| *weight > 0)
+                .ok_or_else(|| CommandError::UserError("Weight must be a positive number".to_string()))?;
+            args.advance();
+            weight
+        }
+        None => 1,
+    };
+    let content = args.rest();
+    if content.is_empty() {
+        return Err(CommandError::UserError("Please provide content of the response".to_string()).into());
+    }
+    if custom_commands
+        .get_command(guild_id, command_name.clone())
+        .await?
+        .is_none()
+    {
+        return Err(CommandError::UserError(format!(
+            "Command `{}` doesn't exist, create it with `command set` first",
+            command_name
+        ))
+        .into());
+    }
+    preview_response(ctx, msg, guild_id, content).await?;
+
     custom_commands
-        .delete_command(guild_id, command_name.to_string())
+        .add_response(guild_id, command_name.clone(), content.to_string(), weight)
         .await?;
+    log_command_change(
+        ctx,
+        msg,
+        guild_id,
+        &command_name,
+        format!("Added response with weight {}", weight),
+    )
+    .await;
 
     msg.channel_id
-        .say(ctx, format!("Command {} successfully deleted!", command_name))
+        .say(ctx, format!("Response added to command `{}`!", command_name))
         .await?;
 
     Ok(())
 }
 
-/// List custom commands
-#[command("list")]
-async fn command_list(ctx: &Context, msg: &Message) -> CommandResult {
+/// List responses of a custom command, with their weights
+/// Example: `command responses 8ball`
+#[command("responses")]
+#[aliases("variants")]
+#[num_args(1)]
+async fn command_responses(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let command_name = args.single::<String>().context("Unable to get first argument")?;
     let custom_commands = {
         let data = ctx.data.read().await;
         let custom_commands = data
@@ -164,14 +300,34 @@ async fn command_list(ctx: &Context, msg: &Message) -> CommandResult {
         custom_commands
     };
     let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
-    let commands = custom_commands.get_command_names(guild_id).await?;
+    let command = custom_commands
+        .get_command(guild_id, command_name.clone())
+        .await?
+        .ok_or_else(|| CommandError::UserError(format!("Command `{}` doesn't exist", command_name)))?;
+    let responses = custom_commands.get_responses(command.command_id).await?;
+    let total_weight = responses.iter().map(|r| r.weight as f64).sum::<f64>();
+    let lines = responses
+        .iter()
+        .enumerate()
+        .map(|(idx, response)| {
+            format!(
+                "{}. (weight {}, {:.0}%) {}",
+                idx + 1,
+                response.weight,
+                response.weight as f64 * 100.0 / total_weight,
+                preview_text(&response.content)
+            )
+        })
+        .collect::<Vec<String>>();
 
     msg.channel_id
         .send_message(ctx, |m| {
             m.embed(|e| {
-                e.title("Custom commands");
-                // Using `fix` to color it light yellow
-                e.description(format!("```fix\n{}\n```", commands.join("\n")))
+                e.title(format!("Responses of `{}`", command.name));
+                e.description(format!(
+                    "```\n{}\n```",
+                    lines.join("\n").replace("```", "`\u{200b}``")
+                ))
             });
 
             m
@@ -181,129 +337,1458 @@ async fn command_list(ctx: &Context, msg: &Message) -> CommandResult {
     Ok(())
 }
 
-/// Manage join roles
-/// Every time a new member joins the guild, they receive given roles
-#[command]
-#[only_in("guilds")]
-#[required_permissions(Manage_Roles)]
-#[sub_commands(join_role_add, join_role_remove, join_role_list)]
-async fn join_role(ctx: &Context, msg: &Message) -> CommandResult {
-    msg.channel_id
-        .say(ctx, "Please use one of the subcommands! (add, remove, list)")
-        .await?;
-    Ok(())
+/// Shortens response content, so that list of them fits in an embed
+fn preview_text(content: &str) -> String {
+    const MAX_PREVIEW_LENGTH: usize = 80;
+    let single_line = content.replace('\n', " ");
+    if single_line.chars().count() > MAX_PREVIEW_LENGTH {
+        format!(
+            "{}…",
+            single_line.chars().take(MAX_PREVIEW_LENGTH).collect::<String>()
+        )
+    } else {
+        single_line
+    }
 }
 
-/// Add new role that should be given to everyone that joins
-/// Requires one argument - either role name or mention
-#[command("add")]
-#[num_args(1)]
-async fn join_role_add(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let role_str = args.single::<String>().context("Unable to get first argument")?;
-    let join_roles = {
+/// Remove response from a custom command, by its number from `command responses`
+/// Last response can't be removed, use `command remove` instead
+/// Example: `command removeresponse 8ball 2`
+#[command("removeresponse")]
+#[required_permissions(Administrator)]
+#[aliases("delresponse", "removevariant")]
+#[num_args(2)]
+async fn command_removeresponse(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let command_name = args.single::<String>().context("Unable to get first argument")?;
+    let position = args
+        .single::<usize>()
+        .map_err(|_| CommandError::UserError("Response number must be a positive number".to_string()))?;
+    let custom_commands = {
         let data = ctx.data.read().await;
-        let join_roles = data.get::<JoinRoles>().context("Can't get join roles")?.clone();
-        join_roles
+        let custom_commands = data
+            .get::<CustomCommands>()
+            .context("Can't get custom commands")?
+            .clone();
+        custom_commands
     };
-    let guild = msg
-        .guild(&ctx)
-        .await
-        .with_context(|| format!("Not in guild: {:?}", msg))?;
-    let guild_id = guild.id;
-    let role_id = role_from_name_or_mention(&ctx, &guild_id, role_str).await?;
-    let role = guild
-        .roles
-        .get(&role_id)
-        .with_context(|| format!("Unable to find role with id {}", role_id))?;
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+    let command = custom_commands
+        .get_command(guild_id, command_name.clone())
+        .await?
+        .ok_or_else(|| CommandError::UserError(format!("Command `{}` doesn't exist", command_name)))?;
+    let responses = custom_commands.get_responses(command.command_id).await?;
+    if responses.len() <= 1 {
+        return Err(CommandError::UserError(
+            "Can't remove the only response, use `command remove` to delete the command".to_string(),
+        )
+        .into());
+    }
+    let response = position
+        .checked_sub(1)
+        .and_then(|idx| responses.get(idx))
+        .ok_or_else(|| {
+            CommandError::UserError(format!(
+                "Command `{}` has only {} responses",
+                command.name,
+                responses.len()
+            ))
+        })?;
 
-    join_roles.add_join_role(guild_id, role_id).await?;
+    custom_commands
+        .delete_response(response.command_response_id)
+        .await?;
+    log_command_change(
+        ctx,
+        msg,
+        guild_id,
+        &command.name,
+        format!("Removed response {}", position),
+    )
+    .await;
 
     msg.channel_id
-        .say(ctx, format!("Will add {} role on join!", role.name))
+        .say(
+            ctx,
+            format!("Response {} removed from command `{}`!", position, command.name),
+        )
         .await?;
+
     Ok(())
 }
 
+async fn log_command_change(ctx: &Context, msg: &Message, guild_id: GuildId, name: &str, change: String) {
+    let event = ModLogEvent::CustomCommandChanged {
+        moderator: msg.author.id,
+        name: name.to_string(),
+        change,
+    };
+    log_event(ctx, guild_id, event).await;
+}
+
+/// Remove custom command, by its name
 #[command("remove")]
+#[required_permissions(Administrator)]
 #[aliases("delete", "del")]
 #[num_args(1)]
-async fn join_role_remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let role_str = args.single::<String>().context("Unable to get first argument")?;
-    let join_roles = {
+async fn command_remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let command_name = args.single::<String>().unwrap();
+    let custom_commands = {
         let data = ctx.data.read().await;
-        let join_roles = data.get::<JoinRoles>().context("Can't get join roles")?.clone();
-        join_roles
+        let custom_commands = data
+            .get::<CustomCommands>()
+            .context("Can't get custom commands")?
+            .clone();
+        custom_commands
     };
-    let guild = msg
-        .guild(&ctx)
-        .await
-        .with_context(|| format!("Not in guild: {:?}", msg))?;
-    let guild_id = guild.id;
-    let role_id = role_from_name_or_mention(&ctx, &guild_id, role_str).await?;
-    let role = guild
-        .roles
-        .get(&role_id)
-        .with_context(|| format!("Unable to find role with id {}", role_id))?;
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
 
-    join_roles.delete_join_role(guild_id, role_id).await?;
+    custom_commands
+        .delete_command(guild_id, command_name.to_string())
+        .await?;
+    log_command_change(ctx, msg, guild_id, &command_name, "Removed".to_string()).await;
 
     msg.channel_id
-        .say(ctx, format!("Will no longer add {} role on join!", role.name))
+        .say(ctx, format!("Command {} successfully deleted!", command_name))
         .await?;
+
     Ok(())
 }
 
+/// List custom commands
 #[command("list")]
-async fn join_role_list(ctx: &Context, msg: &Message) -> CommandResult {
-    let join_roles = {
+async fn command_list(ctx: &Context, msg: &Message) -> CommandResult {
+    let custom_commands = {
         let data = ctx.data.read().await;
-        let join_roles = data.get::<JoinRoles>().context("Can't get join roles")?.clone();
-        join_roles
+        let custom_commands = data
+            .get::<CustomCommands>()
+            .context("Can't get custom commands")?
+            .clone();
+        custom_commands
     };
-    let guild = msg
-        .guild(&ctx)
-        .await
-        .with_context(|| format!("Not in guild: {:?}", msg))?;
-    let guild_id = guild.id;
-    let role_ids = join_roles.get_join_roles(guild_id).await?;
-
-    let role_names = role_ids
-        .iter()
-        .filter_map(|rid| guild.roles.get(&rid).map(|role| &*role.name))
-        .collect::<Vec<&str>>();
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+    let aliases = custom_commands.get_aliases(guild_id).await?;
+    let commands = custom_commands
+        .get_command_names(guild_id)
+        .await?
+        .into_iter()
+        .map(|name| match aliases.get(&name) {
+            Some(command_aliases) => format!("{} ({})", name, command_aliases.join(", ")),
+            None => name,
+        })
+        .collect::<Vec<String>>();
 
     msg.channel_id
         .send_message(ctx, |m| {
             m.embed(|e| {
-                e.title("Join roles");
+                e.title("Custom commands");
                 // Using `fix` to color it light yellow
-                e.description(format!("```fix\n{}\n```", role_names.join("\n")))
+                e.description(format!("```fix\n{}\n```", commands.join("\n")))
             });
 
             m
         })
         .await?;
+
     Ok(())
 }
 
-pub async fn join_role_handler(ctx: &Context, guild_id: &GuildId, new_member: &mut Member) {
-    // TODO: Replace error logging here with some timed-out messages and lower priority logs
-    let join_roles = {
+/// Give existing custom command another name
+/// Example: `command alias site website`
+#[command("alias")]
+#[required_permissions(Administrator)]
+#[num_args(2)]
+async fn command_alias(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let alias = args.single::<String>().context("Unable to get first argument")?;
+    let target = args.single::<String>().context("Unable to get second argument")?;
+    let (command_names, custom_commands) = {
         let data = ctx.data.read().await;
-        match data.get::<JoinRoles>() {
-            Some(rr) => rr.clone(),
-            None => return,
-        }
+        let command_names = data
+            .get::<PublicData>()
+            .context("Can't get public data")?
+            .hardcoded_commands
+            .clone();
+        let custom_commands = data
+            .get::<CustomCommands>()
+            .context("Can't get custom commands")?
+            .clone();
+        (command_names, custom_commands)
     };
 
-    let roles = unwrap_or_return!(
-        join_roles.get_join_roles(guild_id.clone()).await,
-        |e| { error!("Error retrieving list of join roles: {:?}", e) },
-        {}
-    );
-    for role_id in roles {
-        if let Err(e) = new_member.add_role(&ctx, role_id).await {
-            error!("Error assigning role to user: {:?}", e);
+    if command_names.contains(&alias) {
+        msg.channel_id
+            .say(
+                ctx,
+                "This command is already hardcoded! Please choose a different name!",
+            )
+            .await?;
+        Err(anyhow!("Command {} is already hardcoded.", alias))?;
+    }
+
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    if custom_commands
+        .get_command_names(guild_id)
+        .await?
+        .contains(&alias)
+    {
+        msg.channel_id
+            .say(
+                ctx,
+                "Custom command with this name already exists! Please choose a different name!",
+            )
+            .await?;
+        Err(anyhow!("Command {} already exists.", alias))?;
+    }
+
+    let updated = custom_commands
+        .set_alias(guild_id, alias.clone(), target.clone())
+        .await?;
+    let response = if updated == 0 {
+        format!("Command `{}` not found", target)
+    } else {
+        log_command_change(ctx, msg, guild_id, &target, format!("Added alias `{}`", alias)).await;
+        format!("`{}` is now an alias for `{}`!", alias, target)
+    };
+    msg.channel_id.say(ctx, response).await?;
+
+    Ok(())
+}
+
+/// Remove custom command alias, leaving the command itself untouched
+#[command("unalias")]
+#[required_permissions(Administrator)]
+#[num_args(1)]
+async fn command_unalias(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let alias = args.single::<String>().context("Unable to get first argument")?;
+    let custom_commands = {
+        let data = ctx.data.read().await;
+        let custom_commands = data
+            .get::<CustomCommands>()
+            .context("Can't get custom commands")?
+            .clone();
+        custom_commands
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let deleted = custom_commands.delete_alias(guild_id, alias.clone()).await?;
+    let response = if deleted == 0 {
+        format!("Alias `{}` not found", alias)
+    } else {
+        log_command_change(ctx, msg, guild_id, &alias, "Removed alias".to_string()).await;
+        format!("Alias `{}` successfully deleted!", alias)
+    };
+    msg.channel_id.say(ctx, response).await?;
+
+    Ok(())
+}
+
+/// Restrict who can use a custom command and where
+/// Usage: `command restrict <name> allow|deny <role or #channel>`
+/// If any role or channel is allowed, the command only works for those
+/// Denied roles and channels always block the command
+/// Use `command restrict <name> remove <role or #channel>` or `command restrict <name> clear` to undo
+/// Use `command restrict <name>` to see current restrictions
+/// Example: `command restrict rules allow Staff`
+#[command("restrict")]
+#[required_permissions(Administrator)]
+#[min_args(1)]
+#[max_args(3)]
+async fn command_restrict(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let command_name = args.single::<String>().context("Unable to get first argument")?;
+    let custom_commands = {
+        let data = ctx.data.read().await;
+        let custom_commands = data
+            .get::<CustomCommands>()
+            .context("Can't get custom commands")?
+            .clone();
+        custom_commands
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    if args.is_empty() {
+        let command = custom_commands
+            .get_command(guild_id, command_name.clone())
+            .await?
+            .ok_or_else(|| CommandError::UserError(format!("Command `{}` not found", command_name)))?;
+        let restrictions = custom_commands.get_restrictions(command.command_id).await?;
+        let describe = |kind: RestrictionKind| {
+            let targets = restrictions
+                .iter()
+                .filter(|r| r.kind == kind)
+                .map(|r| match kind {
+                    RestrictionKind::AllowRole | RestrictionKind::DenyRole => {
+                        RoleId(r.target_id).mention().to_string()
+                    }
+                    RestrictionKind::AllowChannel | RestrictionKind::DenyChannel => {
+                        ChannelId(r.target_id).mention().to_string()
+                    }
+                })
+                .collect::<Vec<String>>();
+            if targets.is_empty() {
+                "-".to_string()
+            } else {
+                targets.join(", ")
+            }
         };
+        msg.channel_id
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    e.title(format!("Restrictions of `{}`", command.name));
+                    e.field("Allowed roles", describe(RestrictionKind::AllowRole), false);
+                    e.field("Denied roles", describe(RestrictionKind::DenyRole), false);
+                    e.field("Allowed channels", describe(RestrictionKind::AllowChannel), false);
+                    e.field("Denied channels", describe(RestrictionKind::DenyChannel), false)
+                });
+
+                m
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let action = args.single::<String>().context("Unable to get second argument")?;
+    if action == "clear" {
+        custom_commands
+            .delete_restrictions(guild_id, command_name.clone(), None)
+            .await?;
+        log_command_change(
+            ctx,
+            msg,
+            guild_id,
+            &command_name,
+            "Cleared restrictions".to_string(),
+        )
+        .await;
+        msg.channel_id
+            .say(
+                ctx,
+                format!("Command `{}` is no longer restricted!", command_name),
+            )
+            .await?;
+        return Ok(());
+    }
+
+    let target = args.single::<String>().context("Unable to get third argument")?;
+    // Channels have to be mentioned, anything else is assumed to be a role
+    let (target_id, is_channel) = match parse_channel(&target) {
+        Some(channel_id) => (channel_id, true),
+        None => (
+            role_from_name_or_mention(ctx, &guild_id, target.clone()).await?.0,
+            false,
+        ),
+    };
+
+    let affected = match (action.as_str(), is_channel) {
+        ("allow", false) => RestrictionKind::AllowRole,
+        ("allow", true) => RestrictionKind::AllowChannel,
+        ("deny", false) => RestrictionKind::DenyRole,
+        ("deny", true) => RestrictionKind::DenyChannel,
+        ("remove", _) => {
+            let deleted = custom_commands
+                .delete_restrictions(guild_id, command_name.clone(), Some(target_id))
+                .await?;
+            let response = if deleted == 0 {
+                format!("Command `{}` has no restriction for {}", command_name, target)
+            } else {
+                log_command_change(
+                    ctx,
+                    msg,
+                    guild_id,
+                    &command_name,
+                    format!("Removed restriction for {}", target),
+                )
+                .await;
+                format!("Removed restriction for {} from `{}`!", target, command_name)
+            };
+            msg.channel_id.say(ctx, response).await?;
+            return Ok(());
+        }
+        _ => {
+            return Err(CommandError::UserError(format!(
+                "Unknown action `{}` - use allow, deny, remove or clear",
+                action
+            ))
+            .into())
+        }
+    };
+
+    let updated = custom_commands
+        .set_restriction(guild_id, command_name.clone(), affected, target_id)
+        .await?;
+    let response = if updated == 0 {
+        format!("Command `{}` not found", command_name)
+    } else {
+        log_command_change(
+            ctx,
+            msg,
+            guild_id,
+            &command_name,
+            format!("Will now {} {}", action, target),
+        )
+        .await;
+        format!("Command `{}` will now {} {}!", command_name, action, target)
+    };
+    msg.channel_id.say(ctx, response).await?;
+
+    Ok(())
+}
+
+/// Limit how often a custom command can be used
+/// Usage: `command cooldown <name> guild|user|channel <seconds>`, 0 removes the cooldown
+/// Use `command cooldown <name> message on|off` to tell users when they're on cooldown
+/// Example: `command cooldown website channel 60`
+#[command("cooldown")]
+#[required_permissions(Administrator)]
+#[num_args(3)]
+async fn command_cooldown(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let command_name = args.single::<String>().context("Unable to get first argument")?;
+    let setting = args.single::<String>().context("Unable to get second argument")?;
+    let value = args.single::<String>().context("Unable to get third argument")?;
+    let custom_commands = {
+        let data = ctx.data.read().await;
+        let custom_commands = data
+            .get::<CustomCommands>()
+            .context("Can't get custom commands")?
+            .clone();
+        custom_commands
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let (updated, response) = if setting == "message" {
+        let enabled = match value.as_str() {
+            "on" => true,
+            "off" => false,
+            _ => return Err(CommandError::UserError("Expected `on` or `off`".to_string()).into()),
+        };
+        let updated = custom_commands
+            .set_cooldown_message(guild_id, command_name.clone(), enabled)
+            .await?;
+        (
+            updated,
+            format!("Cooldown message for `{}` turned {}!", command_name, value),
+        )
+    } else {
+        let scope = CooldownScope::from_str(&setting).map_err(|_| {
+            CommandError::UserError(format!(
+                "Unknown cooldown `{}` - use guild, user, channel or message",
+                setting
+            ))
+        })?;
+        let seconds = value
+            .parse::<i32>()
+            .ok()
+            .filter(|s| *s >= 0)
+            .ok_or_else(|| CommandError::UserError(format!("Invalid number of seconds: `{}`", value)))?;
+        let updated = custom_commands
+            .set_cooldown(guild_id, command_name.clone(), scope, seconds)
+            .await?;
+        (
+            updated,
+            format!(
+                "Command `{}` now has {} cooldown of {}s!",
+                command_name, setting, seconds
+            ),
+        )
+    };
+
+    if updated == 0 {
+        msg.channel_id
+            .say(ctx, format!("Command `{}` not found", command_name))
+            .await?;
+    } else {
+        log_command_change(ctx, msg, guild_id, &command_name, response.clone()).await;
+        msg.channel_id.say(ctx, response).await?;
+    }
+
+    Ok(())
+}
+
+/// Responses sent automatically when a message matches a trigger, no prefix needed
+/// Usage to set: `autoresponder set <name> contains|exact|regex <pattern> <content>`
+/// Usage to remove: `autoresponder remove <name>`
+/// Usage to limit to channels: `autoresponder channels <name> [#channel ...]`
+/// Usage to limit how often it responds in a channel: `autoresponder cooldown <name> <seconds>`
+#[command]
+#[only_in("guilds")]
+#[aliases("ar", "autoresponse")]
+#[sub_commands(
+    autoresponder_set,
+    autoresponder_remove,
+    autoresponder_list,
+    autoresponder_channels,
+    autoresponder_cooldown
+)]
+async fn autoresponder(ctx: &Context, msg: &Message) -> CommandResult {
+    msg.channel_id
+        .say(
+            ctx,
+            "Please use one of the subcommands! (set, remove, list, channels, cooldown)",
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Set or update an autoresponder
+/// `contains` responds when the keyword is anywhere in a message, `exact` when the whole message is the phrase,
+/// both ignore letter case. `regex` responds when the regular expression matches.
+/// Quote patterns with spaces. Content works the same as in `command set`, including json5 messages.
+/// Example: `autoresponder set greeting exact "good morning" Good morning {user}!`
+#[command("set")]
+#[required_permissions(Administrator)]
+#[aliases("add")]
+#[min_args(4)]
+async fn autoresponder_set(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>().context("Unable to get first argument")?;
+    let kind = args.single::<String>().context("Unable to get second argument")?;
+    let pattern = args
+        .single_quoted::<String>()
+        .context("Unable to get third argument")?;
+    let content = args.rest();
+    let autoresponders = {
+        let data = ctx.data.read().await;
+        let autoresponders = data
+            .get::<Autoresponders>()
+            .context("Can't get autoresponders")?
+            .clone();
+        autoresponders
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let kind = TriggerKind::from_str(&kind).map_err(|_| {
+        CommandError::UserError(format!(
+            "Unknown trigger `{}` - use contains, exact or regex",
+            kind
+        ))
+    })?;
+    if pattern.trim().is_empty() {
+        return Err(CommandError::UserError("Pattern can't be empty".to_string()).into());
+    }
+    // Make sure the pattern is valid before saving it
+    Trigger::new(kind, &pattern)?;
+    preview_response(ctx, msg, guild_id, content).await?;
+
+    autoresponders
+        .set_autoresponder(guild_id, name.clone(), kind, pattern, content.to_string())
+        .await?;
+
+    msg.channel_id
+        .say(ctx, format!("Autoresponder `{}` successfully set!", name))
+        .await?;
+
+    Ok(())
+}
+
+/// Remove autoresponder, by its name
+#[command("remove")]
+#[required_permissions(Administrator)]
+#[aliases("delete", "del")]
+#[num_args(1)]
+async fn autoresponder_remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>().context("Unable to get first argument")?;
+    let autoresponders = {
+        let data = ctx.data.read().await;
+        let autoresponders = data
+            .get::<Autoresponders>()
+            .context("Can't get autoresponders")?
+            .clone();
+        autoresponders
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let deleted = autoresponders
+        .delete_autoresponder(guild_id, name.clone())
+        .await?;
+    let response = if deleted == 0 {
+        format!("Autoresponder `{}` not found", name)
+    } else {
+        format!("Autoresponder `{}` successfully deleted!", name)
+    };
+    msg.channel_id.say(ctx, response).await?;
+
+    Ok(())
+}
+
+/// List autoresponders, with their triggers
+#[command("list")]
+async fn autoresponder_list(ctx: &Context, msg: &Message) -> CommandResult {
+    let autoresponders = {
+        let data = ctx.data.read().await;
+        let autoresponders = data
+            .get::<Autoresponders>()
+            .context("Can't get autoresponders")?
+            .clone();
+        autoresponders
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+    let lines = autoresponders
+        .get_autoresponders(guild_id)
+        .await?
+        .iter()
+        .map(|a| {
+            let channels = if a.channel_ids.is_empty() {
+                "all channels".to_string()
+            } else {
+                a.channel_ids
+                    .iter()
+                    .map(|c| c.mention().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            format!(
+                "**{}** - {} `{}` in {}, cooldown {}s",
+                a.name,
+                a.trigger.kind().as_ref(),
+                preview_text(a.trigger.pattern()).replace('`', "'"),
+                channels,
+                a.cooldown
+            )
+        })
+        .collect::<Vec<String>>();
+
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title("Autoresponders");
+                if lines.is_empty() {
+                    e.description("No autoresponders yet, add one with `autoresponder set`")
+                } else {
+                    e.description(lines.join("\n"))
+                }
+            });
+
+            m
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// Limit autoresponder to given channels, without channels it responds everywhere
+/// Example: `autoresponder channels greeting #general #welcome`
+#[command("channels")]
+#[required_permissions(Administrator)]
+#[min_args(1)]
+async fn autoresponder_channels(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>().context("Unable to get first argument")?;
+    let channel_ids = args
+        .iter::<String>()
+        .map(|arg| {
+            let arg = arg.context("Unable to get channel argument")?;
+            parse_channel(&arg)
+                .map(ChannelId)
+                .ok_or_else(|| CommandError::UserError(format!("Not a channel mention: `{}`", arg)).into())
+        })
+        .collect::<Result<Vec<ChannelId>, anyhow::Error>>()?;
+    let autoresponders = {
+        let data = ctx.data.read().await;
+        let autoresponders = data
+            .get::<Autoresponders>()
+            .context("Can't get autoresponders")?
+            .clone();
+        autoresponders
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let everywhere = channel_ids.is_empty();
+    let updated = autoresponders
+        .set_channels(guild_id, name.clone(), channel_ids)
+        .await?;
+    let response = if updated == 0 {
+        format!("Autoresponder `{}` not found", name)
+    } else if everywhere {
+        format!("Autoresponder `{}` now responds in all channels!", name)
+    } else {
+        format!("Autoresponder `{}` now responds only in given channels!", name)
+    };
+    msg.channel_id.say(ctx, response).await?;
+
+    Ok(())
+}
+
+/// Limit how often autoresponder responds in each channel, 0 removes the cooldown
+/// Example: `autoresponder cooldown greeting 300`
+#[command("cooldown")]
+#[required_permissions(Administrator)]
+#[num_args(2)]
+async fn autoresponder_cooldown(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>().context("Unable to get first argument")?;
+    let value = args.single::<String>().context("Unable to get second argument")?;
+    let seconds = value
+        .parse::<i32>()
+        .ok()
+        .filter(|s| *s >= 0)
+        .ok_or_else(|| CommandError::UserError(format!("Invalid number of seconds: `{}`", value)))?;
+    let autoresponders = {
+        let data = ctx.data.read().await;
+        let autoresponders = data
+            .get::<Autoresponders>()
+            .context("Can't get autoresponders")?
+            .clone();
+        autoresponders
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let updated = autoresponders
+        .set_cooldown(guild_id, name.clone(), seconds)
+        .await?;
+    let response = if updated == 0 {
+        format!("Autoresponder `{}` not found", name)
+    } else {
+        format!("Autoresponder `{}` now has cooldown of {}s!", name, seconds)
+    };
+    msg.channel_id.say(ctx, response).await?;
+
+    Ok(())
+}
+
+/// Manage join roles
+/// Every time a new member joins the guild, they receive given roles
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Roles)]
+#[sub_commands(join_role_add, join_role_remove, join_role_list)]
+async fn join_role(ctx: &Context, msg: &Message) -> CommandResult {
+    msg.channel_id
+        .say(ctx, "Please use one of the subcommands! (add, remove, list)")
+        .await?;
+    Ok(())
+}
+
+/// Add new role that should be given to everyone that joins, or change options of existing one
+/// Requires role name or mention, optionally followed by:
+/// `humans`, `bots` or `both` (default) - who gets the role
+/// delay, like `30s` or `10m` - how long after joining the role is given
+/// `skip-pending` - wait until member passes membership screening
+/// Example: `join_role add Member humans 10m skip-pending`
+#[command("add")]
+#[min_args(1)]
+#[max_args(4)]
+async fn join_role_add(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let role_str = args.single::<String>().context("Unable to get first argument")?;
+    let mut join_role_options = (MemberKind::Both, Duration::from_secs(0), false);
+    for option in args.iter::<String>() {
+        let option = option.context("Unable to get option argument")?;
+        if let Ok(target) = MemberKind::from_str(&option) {
+            join_role_options.0 = target;
+        } else if option == "skip-pending" {
+            join_role_options.2 = true;
+        } else {
+            let delay = parse_duration(&option).map_err(|_| {
+                CommandError::UserError(format!(
+                    "Unknown option `{}` - use humans, bots, both, skip-pending or delay like `10m`",
+                    option
+                ))
+            })?;
+            if delay.as_secs() > i32::MAX as u64 {
+                return Err(CommandError::UserError(format!("Delay `{}` is too long", option)).into());
+            }
+            join_role_options.1 = delay;
+        }
+    }
+    let (target, delay, skip_pending) = join_role_options;
+    let join_roles = {
+        let data = ctx.data.read().await;
+        let join_roles = data.get::<JoinRoles>().context("Can't get join roles")?.clone();
+        join_roles
+    };
+    let guild = msg
+        .guild(&ctx)
+        .await
+        .with_context(|| format!("Not in guild: {:?}", msg))?;
+    let guild_id = guild.id;
+    let role_id = role_from_name_or_mention(&ctx, &guild_id, role_str).await?;
+    let role = guild
+        .roles
+        .get(&role_id)
+        .with_context(|| format!("Unable to find role with id {}", role_id))?;
+
+    let join_role = JoinRole {
+        role_id,
+        target,
+        delay,
+        skip_pending,
+    };
+    join_roles.add_join_role(guild_id, join_role).await?;
+    log_event(
+        ctx,
+        guild_id,
+        ModLogEvent::JoinRoleChanged {
+            moderator: msg.author.id,
+            change: format!("Added {} ({})", role_id.mention(), describe_join_role(&join_role)),
+        },
+    )
+    .await;
+
+    msg.channel_id
+        .say(
+            ctx,
+            format!(
+                "Will add {} role on join! ({})",
+                role.name,
+                describe_join_role(&join_role)
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+#[command("remove")]
+#[aliases("delete", "del")]
+#[num_args(1)]
+async fn join_role_remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let role_str = args.single::<String>().context("Unable to get first argument")?;
+    let join_roles = {
+        let data = ctx.data.read().await;
+        let join_roles = data.get::<JoinRoles>().context("Can't get join roles")?.clone();
+        join_roles
+    };
+    let guild = msg
+        .guild(&ctx)
+        .await
+        .with_context(|| format!("Not in guild: {:?}", msg))?;
+    let guild_id = guild.id;
+    let role_id = role_from_name_or_mention(&ctx, &guild_id, role_str).await?;
+    let role = guild
+        .roles
+        .get(&role_id)
+        .with_context(|| format!("Unable to find role with id {}", role_id))?;
+
+    join_roles.delete_join_role(guild_id, role_id).await?;
+    log_event(
+        ctx,
+        guild_id,
+        ModLogEvent::JoinRoleChanged {
+            moderator: msg.author.id,
+            change: format!("Removed {}", role_id.mention()),
+        },
+    )
+    .await;
+
+    msg.channel_id
+        .say(ctx, format!("Will no longer add {} role on join!", role.name))
+        .await?;
+    Ok(())
+}
+
+#[command("list")]
+async fn join_role_list(ctx: &Context, msg: &Message) -> CommandResult {
+    let join_roles = {
+        let data = ctx.data.read().await;
+        let join_roles = data.get::<JoinRoles>().context("Can't get join roles")?.clone();
+        join_roles
+    };
+    let guild = msg
+        .guild(&ctx)
+        .await
+        .with_context(|| format!("Not in guild: {:?}", msg))?;
+    let guild_id = guild.id;
+    let guild_join_roles = join_roles.get_join_roles(guild_id).await?;
+
+    let role_names = guild_join_roles
+        .iter()
+        .filter_map(|join_role| {
+            guild
+                .roles
+                .get(&join_role.role_id)
+                .map(|role| format!("{} ({})", role.name, describe_join_role(join_role)))
+        })
+        .collect::<Vec<String>>();
+
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title("Join roles");
+                // Using `fix` to color it light yellow
+                e.description(format!("```fix\n{}\n```", role_names.join("\n")))
+            });
+
+            m
+        })
+        .await?;
+    Ok(())
+}
+
+fn describe_join_role(join_role: &JoinRole) -> String {
+    let mut description = match join_role.target {
+        MemberKind::Humans => "humans only".to_string(),
+        MemberKind::Bots => "bots only".to_string(),
+        MemberKind::Both => "everyone".to_string(),
+    };
+    if join_role.delay.as_secs() > 0 {
+        description.push_str(&format!(", after {}", format_duration(join_role.delay)));
+    }
+    if join_role.skip_pending {
+        description.push_str(", after membership screening");
+    }
+    description
+}
+
+pub async fn join_role_handler(ctx: &Context, guild_id: &GuildId, new_member: &mut Member) {
+    // TODO: Replace error logging here with some timed-out messages and lower priority logs
+    let join_roles = {
+        let data = ctx.data.read().await;
+        match data.get::<JoinRoles>() {
+            Some(rr) => rr.clone(),
+            None => return,
+        }
+    };
+
+    let roles = unwrap_or_return!(
+        join_roles.get_join_roles(*guild_id).await,
+        |e| { error!("Error retrieving list of join roles: {:?}", e) },
+        {}
+    );
+    let is_bot = new_member.user.bot;
+    let pending = new_member.pending;
+    let (held_back, roles): (Vec<JoinRole>, Vec<JoinRole>) = roles
+        .into_iter()
+        .filter(|join_role| join_role.target.applies_to(is_bot))
+        .partition(|join_role| join_role.skip_pending && pending);
+    if !held_back.is_empty() {
+        if let Err(e) = join_roles
+            .hold_back_join_roles(*guild_id, new_member.user.id)
+            .await
+        {
+            error!("Error holding back join roles: {:?}", e);
+        }
+    }
+    apply_join_roles(ctx, &join_roles, *guild_id, new_member, roles).await;
+}
+
+/// Gives roles which were held back until member passes membership screening.
+/// Member missing from cache before the update only gets them if they were held back when they joined.
+pub async fn join_role_screening_handler(ctx: &Context, old: Option<&Member>, new: &mut Member) {
+    if new.pending || matches!(old, Some(old) if !old.pending) {
+        return;
+    }
+    let join_roles = {
+        let data = ctx.data.read().await;
+        match data.get::<JoinRoles>() {
+            Some(rr) => rr.clone(),
+            None => return,
+        }
+    };
+    let held_back = unwrap_or_return!(
+        join_roles.release_join_roles(new.guild_id, new.user.id).await,
+        |e| { error!("Error releasing held back join roles: {:?}", e) },
+        {}
+    );
+    // Without cached member, the update could be anything, like a nickname change
+    if old.is_none() && !held_back {
+        return;
+    }
+
+    let roles = unwrap_or_return!(
+        join_roles.get_join_roles(new.guild_id).await,
+        |e| { error!("Error retrieving list of join roles: {:?}", e) },
+        {}
+    );
+    let is_bot = new.user.bot;
+    let roles = roles
+        .into_iter()
+        .filter(|join_role| {
+            join_role.target.applies_to(is_bot)
+                && join_role.skip_pending
+                && !new.roles.contains(&join_role.role_id)
+        })
+        .collect::<Vec<JoinRole>>();
+    let guild_id = new.guild_id;
+    apply_join_roles(ctx, &join_roles, guild_id, new, roles).await;
+}
+
+/// Forgets held back join roles of member who left before passing membership screening
+pub async fn join_role_removal_handler(ctx: &Context, guild_id: GuildId, user_id: UserId) {
+    let join_roles = {
+        let data = ctx.data.read().await;
+        match data.get::<JoinRoles>() {
+            Some(rr) => rr.clone(),
+            None => return,
+        }
+    };
+    if let Err(e) = join_roles.release_join_roles(guild_id, user_id).await {
+        error!("Error forgetting held back join roles: {:?}", e);
+    }
+}
+
+/// Gives roles right away, or schedules them if they're delayed
+async fn apply_join_roles(
+    ctx: &Context,
+    join_roles: &JoinRoles,
+    guild_id: GuildId,
+    member: &mut Member,
+    roles: Vec<JoinRole>,
+) {
+    for join_role in roles {
+        if join_role.delay.as_secs() == 0 {
+            if let Err(e) = member.add_role(&ctx, join_role.role_id).await {
+                error!("Error assigning role to user: {:?}", e);
+            };
+            continue;
+        }
+        let pending = PendingJoinRole {
+            guild_id,
+            user_id: member.user.id,
+            role_id: join_role.role_id,
+        };
+        let grant_at = Utc::now() + ChronoDuration::seconds(join_role.delay.as_secs() as i64);
+        if let Err(e) = join_roles.add_pending_join_role(pending, grant_at).await {
+            error!("Error scheduling delayed join role: {:?}", e);
+        }
+    }
+}
+
+/// Gives delayed join roles once they're due. Runs for the whole lifetime of the bot.
+pub async fn delayed_join_role_scheduler(http: Arc<Http>, join_roles: Arc<JoinRoles>) {
+    let mut interval = tokio::time::interval(DELAYED_JOIN_ROLE_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let due = match join_roles.get_due_join_roles(Utc::now()).await {
+            Ok(due) => due,
+            Err(e) => {
+                error!("Error getting delayed join roles: {:?}", e);
+                continue;
+            }
+        };
+        for (pending_join_role_id, pending) in due {
+            let result = http
+                .add_member_role(pending.guild_id.0, pending.user_id.0, pending.role_id.0)
+                .await;
+            let result = match result {
+                Ok(_) => join_roles.delete_pending_join_role(pending_join_role_id).await,
+                // Member left, or the role got deleted in the meantime
+                Err(e) if is_not_found(&e) => {
+                    debug!("Unable to give delayed join role: {:?}", e);
+                    join_roles.delete_pending_join_role(pending_join_role_id).await
+                }
+                // Postponed, so a failing one doesn't block the rest
+                Err(e) => {
+                    error!("Error giving delayed join role: {:?}", e);
+                    join_roles.postpone_pending_join_role(pending_join_role_id).await
+                }
+            };
+            if let Err(e) = result {
+                error!("Error updating delayed join role: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Manage sticky roles
+/// Members who leave and rejoin get sticky roles they had back, so they can't dodge e.g. mute role
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Roles)]
+#[sub_commands(sticky_role_add, sticky_role_remove, sticky_role_list, sticky_role_forget)]
+async fn sticky_role(ctx: &Context, msg: &Message) -> CommandResult {
+    msg.channel_id
+        .say(
+            ctx,
+            "Please use one of the subcommands! (add, remove, list, forget)",
+        )
+        .await?;
+    Ok(())
+}
+
+/// Make role sticky
+/// Requires one argument - either role name or mention
+#[command("add")]
+#[num_args(1)]
+async fn sticky_role_add(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let role_str = args.single::<String>().context("Unable to get first argument")?;
+    let sticky_roles = {
+        let data = ctx.data.read().await;
+        let sticky_roles = data
+            .get::<StickyRoles>()
+            .context("Can't get sticky roles")?
+            .clone();
+        sticky_roles
+    };
+    let guild = msg
+        .guild(&ctx)
+        .await
+        .with_context(|| format!("Not in guild: {:?}", msg))?;
+    let role_id = role_from_name_or_mention(ctx, &guild.id, role_str).await?;
+    let role = guild
+        .roles
+        .get(&role_id)
+        .with_context(|| format!("Unable to find role with id {}", role_id))?;
+
+    sticky_roles.add_sticky_role(guild.id, role_id).await?;
+
+    msg.channel_id
+        .say(ctx, format!("Role {} is now sticky!", role.name))
+        .await?;
+    Ok(())
+}
+
+#[command("remove")]
+#[aliases("delete", "del")]
+#[num_args(1)]
+async fn sticky_role_remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let role_str = args.single::<String>().context("Unable to get first argument")?;
+    let sticky_roles = {
+        let data = ctx.data.read().await;
+        let sticky_roles = data
+            .get::<StickyRoles>()
+            .context("Can't get sticky roles")?
+            .clone();
+        sticky_roles
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+    let role_id = role_from_name_or_mention(ctx, &guild_id, role_str.clone()).await?;
+
+    let deleted = sticky_roles.delete_sticky_role(guild_id, role_id).await?;
+    let response = if deleted == 0 {
+        format!("Role {} isn't sticky", role_str)
+    } else {
+        format!("Role {} is no longer sticky!", role_str)
+    };
+    msg.channel_id.say(ctx, response).await?;
+    Ok(())
+}
+
+#[command("list")]
+async fn sticky_role_list(ctx: &Context, msg: &Message) -> CommandResult {
+    let sticky_roles = {
+        let data = ctx.data.read().await;
+        let sticky_roles = data
+            .get::<StickyRoles>()
+            .context("Can't get sticky roles")?
+            .clone();
+        sticky_roles
+    };
+    let guild = msg
+        .guild(&ctx)
+        .await
+        .with_context(|| format!("Not in guild: {:?}", msg))?;
+    let role_ids = sticky_roles.get_sticky_roles(guild.id).await?;
+
+    let role_names = role_ids
+        .iter()
+        .filter_map(|role_id| guild.roles.get(role_id).map(|role| &*role.name))
+        .collect::<Vec<&str>>();
+
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.title("Sticky roles");
+                // Using `fix` to color it light yellow
+                e.description(format!("```fix\n{}\n```", role_names.join("\n")))
+            });
+
+            m
+        })
+        .await?;
+    Ok(())
+}
+
+/// Forget roles stored for user who left, so they don't get them back on rejoin
+/// Requires user mention or ID
+#[command("forget")]
+#[num_args(1)]
+async fn sticky_role_forget(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_str = args.single::<String>().context("Unable to get first argument")?;
+    let user_id = parse_user_id(&user_str)
+        .ok_or_else(|| CommandError::UserError(format!("Not a user mention or ID: `{}`", user_str)))?;
+    let sticky_roles = {
+        let data = ctx.data.read().await;
+        let sticky_roles = data
+            .get::<StickyRoles>()
+            .context("Can't get sticky roles")?
+            .clone();
+        sticky_roles
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let deleted = sticky_roles.forget_member_roles(guild_id, user_id).await?;
+    let response = if deleted == 0 {
+        format!("No roles stored for {}", user_id.mention())
+    } else {
+        format!("Forgot stored roles of {}!", user_id.mention())
+    };
+    msg.channel_id.say(ctx, response).await?;
+    Ok(())
+}
+
+/// Remembers roles of member who's leaving
+pub async fn sticky_role_removal_handler(ctx: &Context, guild_id: GuildId, member: Option<&Member>) {
+    let sticky_roles = {
+        let data = ctx.data.read().await;
+        match data.get::<StickyRoles>() {
+            Some(sticky_roles) => sticky_roles.clone(),
+            None => return,
+        }
+    };
+    let member = match member {
+        Some(member) => member,
+        None => {
+            debug!("Roles of member who left aren't cached, unable to store them");
+            return;
+        }
+    };
+    if member.roles.is_empty() {
+        return;
+    }
+    if let Err(e) = sticky_roles
+        .save_member_roles(guild_id, member.user.id, member.roles.clone())
+        .await
+    {
+        error!("Error storing roles of member who left: {:?}", e);
+    }
+}
+
+/// Gives rejoining member back their sticky roles
+pub async fn sticky_role_handler(ctx: &Context, guild_id: GuildId, new_member: &mut Member) {
+    let sticky_roles = {
+        let data = ctx.data.read().await;
+        match data.get::<StickyRoles>() {
+            Some(sticky_roles) => sticky_roles.clone(),
+            None => return,
+        }
+    };
+    let user_id = new_member.user.id;
+    let roles = unwrap_or_return!(
+        sticky_roles.get_member_sticky_roles(guild_id, user_id).await,
+        |e| { error!("Error retrieving stored sticky roles: {:?}", e) },
+        {}
+    );
+    // Stored roles are kept if restoring them fails, so they're restored on the next rejoin at least
+    if !roles.is_empty() {
+        if let Err(e) = new_member.add_roles(&ctx, &roles).await {
+            error!("Error restoring sticky roles: {:?}", e);
+            return;
+        }
+    }
+    if let Err(e) = sticky_roles.forget_member_roles(guild_id, user_id).await {
+        error!("Error forgetting restored sticky roles: {:?}", e);
+    }
+}
+
+/// Messages sent when members join or leave the server
+/// Usage to set: `welcome set [goodbye] <#channel or dm> <content>`
+/// Usage to see how it looks: `welcome preview [goodbye]`
+/// Usage to stop sending it: `welcome disable [goodbye]`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Guild)]
+#[aliases("greeting")]
+#[sub_commands(welcome_set, welcome_preview, welcome_disable)]
+async fn welcome(ctx: &Context, msg: &Message) -> CommandResult {
+    msg.channel_id
+        .say(ctx, "Please use one of the subcommands! (set, preview, disable)")
+        .await?;
+    Ok(())
+}
+
+/// Takes optional `welcome` or `goodbye` argument, welcome is the default
+fn welcome_message_kind(args: &mut Args) -> WelcomeMessageKind {
+    match args.current().map(WelcomeMessageKind::from_str) {
+        Some(Ok(kind)) => {
+            args.advance();
+            kind
+        }
+        _ => WelcomeMessageKind::Welcome,
+    }
+}
+
+/// Set message sent when member joins, or leaves with `goodbye`
+/// It's sent either to a channel, or directly to the member with `dm` - members who left can't get DMs
+/// Content works the same as in `command set`, including json5 messages and placeholders like
+/// `{user}`, `{user.mention}`, `{user.tag}`, `{guild.name}` and `{guild.member_count}`
+/// Example: `welcome set #lobby Welcome to {guild.name}, {user.mention}!`
+/// Example: `welcome set goodbye #lobby {user.tag} has left us`
+#[command("set")]
+#[min_args(2)]
+async fn welcome_set(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let kind = welcome_message_kind(&mut args);
+    let target = args
+        .single::<String>()
+        .context("Unable to get channel argument")?;
+    let content = args.rest();
+    if content.is_empty() {
+        return Err(CommandError::UserError("Please provide content of the message".to_string()).into());
+    }
+    let welcome_messages = {
+        let data = ctx.data.read().await;
+        let welcome_messages = data
+            .get::<WelcomeMessages>()
+            .context("Can't get welcome messages")?
+            .clone();
+        welcome_messages
+    };
+    let guild = msg
+        .guild(&ctx)
+        .await
+        .with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let channel_id = if target.eq_ignore_ascii_case("dm") {
+        if kind == WelcomeMessageKind::Goodbye {
+            return Err(CommandError::UserError(
+                "Members who left can't be messaged directly, please choose a channel".to_string(),
+            )
+            .into());
+        }
+        None
+    } else {
+        let channel_id = parse_channel(&target)
+            .map(ChannelId)
+            .filter(|channel_id| guild.channels.contains_key(channel_id))
+            .ok_or_else(|| {
+                CommandError::UserError(format!("`{}` isn't a channel in this server or `dm`", target))
+            })?;
+        Some(channel_id)
+    };
+    preview_response(ctx, msg, guild.id, content).await?;
+
+    welcome_messages
+        .set_welcome_message(
+            guild.id,
+            WelcomeMessage {
+                kind,
+                channel_id,
+                content: content.to_string(),
+            },
+        )
+        .await?;
+
+    msg.channel_id
+        .say(
+            ctx,
+            format!(
+                "{} message set, it will be sent {}!",
+                describe_welcome_message_kind(kind),
+                describe_welcome_message_target(channel_id)
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Show welcome message, or goodbye message with `goodbye`, as if you have just joined or left
+#[command("preview")]
+#[max_args(1)]
+async fn welcome_preview(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let kind = welcome_message_kind(&mut args);
+    let welcome_messages = {
+        let data = ctx.data.read().await;
+        let welcome_messages = data
+            .get::<WelcomeMessages>()
+            .context("Can't get welcome messages")?
+            .clone();
+        welcome_messages
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let welcome_message = welcome_messages
+        .get_welcome_message(guild_id, kind)
+        .await?
+        .ok_or_else(|| {
+            CommandError::UserError(format!(
+                "{} message isn't set",
+                describe_welcome_message_kind(kind)
+            ))
+        })?;
+    msg.channel_id
+        .say(
+            ctx,
+            format!(
+                "{} message, sent {}:",
+                describe_welcome_message_kind(kind),
+                describe_welcome_message_target(welcome_message.channel_id)
+            ),
+        )
+        .await?;
+    preview_response(ctx, msg, guild_id, &welcome_message.content).await?;
+    Ok(())
+}
+
+/// Stop sending welcome message, or goodbye message with `goodbye`
+#[command("disable")]
+#[aliases("remove", "delete", "del")]
+#[max_args(1)]
+async fn welcome_disable(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let kind = welcome_message_kind(&mut args);
+    let welcome_messages = {
+        let data = ctx.data.read().await;
+        let welcome_messages = data
+            .get::<WelcomeMessages>()
+            .context("Can't get welcome messages")?
+            .clone();
+        welcome_messages
+    };
+    let guild_id = msg.guild_id.with_context(|| format!("Not in guild: {:?}", msg))?;
+
+    let deleted = welcome_messages.delete_welcome_message(guild_id, kind).await?;
+    let response = if deleted == 0 {
+        format!("{} message isn't set", describe_welcome_message_kind(kind))
+    } else {
+        format!("{} message disabled!", describe_welcome_message_kind(kind))
+    };
+    msg.channel_id.say(ctx, response).await?;
+    Ok(())
+}
+
+fn describe_welcome_message_kind(kind: WelcomeMessageKind) -> &'static str {
+    match kind {
+        WelcomeMessageKind::Welcome => "Welcome",
+        WelcomeMessageKind::Goodbye => "Goodbye",
+    }
+}
+
+fn describe_welcome_message_target(channel_id: Option<ChannelId>) -> String {
+    match channel_id {
+        Some(channel_id) => format!("in {}", channel_id.mention()),
+        None => "in direct message".to_string(),
+    }
+}
+
+/// Sends welcome or goodbye message of the guild, if it's set
+pub async fn welcome_message_handler(
+    ctx: &Context,
+    guild_id: GuildId,
+    user: &User,
+    kind: WelcomeMessageKind,
+) {
+    let welcome_messages = {
+        let data = ctx.data.read().await;
+        match data.get::<WelcomeMessages>() {
+            Some(welcome_messages) => welcome_messages.clone(),
+            None => return,
+        }
+    };
+    let welcome_message = unwrap_or_return!(
+        welcome_messages.get_welcome_message(guild_id, kind).await,
+        |e| { error!("Error getting {:?} message: {:?}", kind, e) },
+        {}
+    );
+    let welcome_message = match welcome_message {
+        Some(welcome_message) => welcome_message,
+        None => return,
+    };
+    let channel_id = match welcome_message.channel_id {
+        Some(channel_id) => channel_id,
+        None => {
+            unwrap_or_return!(
+                user.create_dm_channel(ctx).await,
+                |e| { debug!("Unable to open DM with {}: {:?}", user.id, e) },
+                {}
+            )
+            .id
+        }
+    };
+    let variables =
+        template_variables(ctx, user, Some(guild_id), welcome_message.channel_id, Vec::new()).await;
+    if let Err(e) = send_rendered(ctx, channel_id, &welcome_message.content, &variables).await {
+        match e.downcast_ref::<SerenityError>() {
+            // Members can have DMs closed, that's expected
+            Some(_) if welcome_message.channel_id.is_none() => {
+                debug!("Unable to DM {:?} message to {}: {:?}", kind, user.id, e)
+            }
+            _ => error!(
+                "Unable to send {:?} message to channel {}: {:?}",
+                kind, channel_id, e
+            ),
+        }
     }
 }
@@ -0,0 +1,375 @@
+use crate::{
+    database::queries::MessageLogIgnoreKind,
+    structures::{
+        errors::CommandError,
+        message_log::{
+            bulk_delete_embed,
+            delete_embed,
+            edit_embed,
+            log_channel,
+            transcript,
+            CachedMessage,
+            MessageLog,
+        },
+    },
+    unwrap_or_return,
+    utils::{
+        defaults::DEFAULT_HELP_EMBED_COLOUR,
+        misc::{is_not_found, role_from_name_or_mention},
+    },
+};
+use anyhow::Context as AnyContext;
+use chrono::Utc;
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    http::AttachmentType,
+    model::{event::MessageUpdateEvent, prelude::*},
+    prelude::*,
+    utils::parse_channel,
+    Result as SerenityResult,
+};
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Log edited and deleted messages to a channel
+/// Usage to set the channel: `message_log channel <#channel|disable>`
+/// Usage to skip channels or members with a role: `message_log ignore <#channel|role>`
+/// Usage to log them again: `message_log unignore <#channel|role>`
+/// Usage to see the settings: `message_log show`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Guild)]
+#[sub_commands(
+    message_log_channel,
+    message_log_ignore,
+    message_log_unignore,
+    message_log_show
+)]
+async fn message_log(ctx: &Context, msg: &Message) -> CommandResult {
+    msg.channel_id
+        .say(
+            ctx,
+            "Please use one of the subcommands! (channel, ignore, unignore, show)",
+        )
+        .await?;
+    Ok(())
+}
+
+#[command("channel")]
+#[num_args(1)]
+async fn message_log_channel(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let message_log = get_message_log(ctx).await?;
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+
+    let content = if args.current().map(|arg| arg.eq_ignore_ascii_case("disable")) == Some(true) {
+        message_log.configs().set_channel(guild_id, None).await?;
+        "Message log disabled".to_string()
+    } else {
+        let channel_id = args
+            .single::<ChannelId>()
+            .map_err(|_| CommandError::UserError("Please mention a channel, or use `disable`".to_string()))?;
+        let in_guild = ctx.cache.guild_channel_field(channel_id, |c| c.guild_id).await == Some(guild_id);
+        if !in_guild {
+            return Err(CommandError::UserError("Channel isn't in this server".to_string()).into());
+        }
+        message_log
+            .configs()
+            .set_channel(guild_id, Some(channel_id))
+            .await?;
+        format!(
+            "Edited and deleted messages will be logged in {}",
+            channel_id.mention()
+        )
+    };
+    msg.channel_id.say(ctx, content).await?;
+    Ok(())
+}
+
+#[command("ignore")]
+#[num_args(1)]
+async fn message_log_ignore(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let target = args.single::<String>().context("Unable to get first argument")?;
+    let message_log = get_message_log(ctx).await?;
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+
+    let (kind, target_id) = message_log_ignore_target(ctx, guild_id, &target).await?;
+    let added = message_log
+        .configs()
+        .add_ignore(guild_id, kind, target_id)
+        .await?;
+    let response = if added == 0 {
+        format!("{} is already ignored", target)
+    } else {
+        format!("Messages from {} will no longer be logged", target)
+    };
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.content(response).allowed_mentions(|am| am.empty_parse())
+        })
+        .await?;
+    Ok(())
+}
+
+#[command("unignore")]
+#[num_args(1)]
+async fn message_log_unignore(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let target = args.single::<String>().context("Unable to get first argument")?;
+    let message_log = get_message_log(ctx).await?;
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+
+    let (_, target_id) = message_log_ignore_target(ctx, guild_id, &target).await?;
+    let deleted = message_log.configs().delete_ignore(guild_id, target_id).await?;
+    let response = if deleted == 0 {
+        format!("{} isn't ignored", target)
+    } else {
+        format!("Messages from {} will be logged again", target)
+    };
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.content(response).allowed_mentions(|am| am.empty_parse())
+        })
+        .await?;
+    Ok(())
+}
+
+#[command("show")]
+#[aliases("settings")]
+#[num_args(0)]
+async fn message_log_show(ctx: &Context, msg: &Message) -> CommandResult {
+    let message_log = get_message_log(ctx).await?;
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+
+    let config = message_log.get_config(guild_id).await?;
+    let list = |mentions: Vec<String>| {
+        if mentions.is_empty() {
+            "-".to_string()
+        } else {
+            mentions.join(", ")
+        }
+    };
+    let channel = config
+        .channel_id
+        .map(|channel_id| channel_id.mention().to_string())
+        .unwrap_or_else(|| "Disabled".to_string());
+    let ignored_channels = list(
+        config
+            .ignored_channels
+            .iter()
+            .map(|channel_id| channel_id.mention().to_string())
+            .collect(),
+    );
+    let ignored_roles = list(
+        config
+            .ignored_roles
+            .iter()
+            .map(|role_id| role_id.mention().to_string())
+            .collect(),
+    );
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| {
+                e.colour(DEFAULT_HELP_EMBED_COLOUR);
+                e.title("Message log");
+                e.field("Channel", channel, false);
+                e.field("Ignored channels", ignored_channels, false);
+                e.field("Ignored roles", ignored_roles, false)
+            })
+        })
+        .await?;
+    Ok(())
+}
+
+/// Channels have to be mentioned, anything else is assumed to be a role
+async fn message_log_ignore_target(
+    ctx: &Context,
+    guild_id: GuildId,
+    target: &str,
+) -> Result<(MessageLogIgnoreKind, u64), anyhow::Error> {
+    match parse_channel(target) {
+        Some(channel_id) => Ok((MessageLogIgnoreKind::Channel, channel_id)),
+        None => {
+            let role_id = role_from_name_or_mention(ctx, &guild_id, target.to_string()).await?;
+            Ok((MessageLogIgnoreKind::Role, role_id.0))
+        }
+    }
+}
+
+async fn get_message_log(ctx: &Context) -> Result<Arc<MessageLog>, anyhow::Error> {
+    let data = ctx.data.read().await;
+    let message_log = data.get::<MessageLog>().context("Can't get message log")?.clone();
+    Ok(message_log)
+}
+
+/// Remembers messages of guilds with message log, in case serenity's cache drops them before they change
+pub async fn message_log_cache_handler(ctx: &Context, msg: &Message) {
+    let guild_id = unwrap_or_return!(msg.guild_id);
+    let message_log = unwrap_or_return!(
+        get_message_log(ctx).await,
+        |e| { error!("Unable to get message log: {:?}", e) },
+        {}
+    );
+    if let Err(e) = message_log.remember(guild_id, msg).await {
+        error!("Error caching message: {:?}", e);
+    }
+}
+
+pub async fn message_update_handler(
+    ctx: &Context,
+    old: Option<Message>,
+    new: Option<Message>,
+    event: &MessageUpdateEvent,
+) {
+    let guild_id = unwrap_or_return!(event.guild_id);
+    // Embeds getting resolved and pins also update messages, without editing them
+    let content = unwrap_or_return!(event.content.clone());
+    if event.edited_timestamp.is_none() {
+        return;
+    }
+    let message_log = unwrap_or_return!(
+        get_message_log(ctx).await,
+        |e| { error!("Unable to get message log: {:?}", e) },
+        {}
+    );
+
+    let cached_before = message_log.update(event.id, content.clone()).await;
+    let before = old.as_ref().map(CachedMessage::from).or(cached_before);
+    if before.as_ref().map(|before| before.content == content) == Some(true) {
+        return;
+    }
+    let after = match (new.as_ref(), &before, &event.author) {
+        (Some(new), _, _) => CachedMessage::from(new),
+        (None, Some(before), _) => CachedMessage {
+            content,
+            ..before.clone()
+        },
+        (None, None, Some(author)) => CachedMessage {
+            id: event.id,
+            channel_id: event.channel_id,
+            author_id: author.id,
+            author_tag: author.tag(),
+            author_is_bot: author.bot,
+            content,
+            attachments: Vec::new(),
+            timestamp: event.timestamp.unwrap_or_else(Utc::now),
+        },
+        (None, None, None) => return,
+    };
+    if after.author_is_bot {
+        return;
+    }
+
+    let config = unwrap_or_return!(
+        message_log.get_config(guild_id).await,
+        |e| { error!("Error getting message log config: {:?}", e) },
+        {}
+    );
+    let roles = member_roles(ctx, guild_id, after.author_id).await;
+    let log_channel_id = unwrap_or_return!(log_channel(&config, after.channel_id, &roles));
+    let result = log_channel_id
+        .send_message(ctx, |m| {
+            m.embed(|e| edit_embed(e, guild_id, before.as_ref(), &after))
+        })
+        .await;
+    handle_message_log_result(&message_log, guild_id, log_channel_id, result).await;
+}
+
+/// Logs single deleted message as an embed, bulk deletions as an attached transcript
+pub async fn message_delete_handler(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_ids: &[MessageId],
+) {
+    let message_log = unwrap_or_return!(
+        get_message_log(ctx).await,
+        |e| { error!("Unable to get message log: {:?}", e) },
+        {}
+    );
+    let mut messages = Vec::with_capacity(message_ids.len());
+    for message_id in message_ids {
+        // Forgotten either way, so they don't take space in the cache
+        let cached = message_log.take(*message_id).await;
+        let message = match ctx.cache.message(channel_id, *message_id).await {
+            Some(message) => Some(CachedMessage::from(&message)),
+            None => cached,
+        };
+        messages.push((*message_id, message));
+    }
+
+    let config = unwrap_or_return!(
+        message_log.get_config(guild_id).await,
+        |e| { error!("Error getting message log config: {:?}", e) },
+        {}
+    );
+    let log_channel_id = unwrap_or_return!(log_channel(&config, channel_id, &[]));
+    let mut logged = Vec::with_capacity(messages.len());
+    for (message_id, message) in messages {
+        if let Some(message) = &message {
+            if message.author_is_bot {
+                continue;
+            }
+            let roles = member_roles(ctx, guild_id, message.author_id).await;
+            if log_channel(&config, channel_id, &roles).is_none() {
+                continue;
+            }
+        }
+        logged.push((message_id, message));
+    }
+
+    let result = match logged.as_slice() {
+        [] => return,
+        [(message_id, message)] if message_ids.len() == 1 => {
+            log_channel_id
+                .send_message(ctx, |m| {
+                    m.embed(|e| delete_embed(e, channel_id, *message_id, message.as_ref()))
+                })
+                .await
+        }
+        _ => {
+            log_channel_id
+                .send_message(ctx, |m| {
+                    m.embed(|e| bulk_delete_embed(e, channel_id, logged.len()));
+                    m.add_file(AttachmentType::Bytes {
+                        data: transcript(&logged).into_bytes().into(),
+                        filename: format!("deleted-messages-{}.txt", channel_id),
+                    })
+                })
+                .await
+        }
+    };
+    handle_message_log_result(&message_log, guild_id, log_channel_id, result).await;
+}
+
+/// Disables message log once its channel is gone
+async fn handle_message_log_result(
+    message_log: &MessageLog,
+    guild_id: GuildId,
+    log_channel_id: ChannelId,
+    result: SerenityResult<Message>,
+) {
+    match result {
+        Ok(_) => {}
+        Err(e) if is_not_found(&e) => {
+            info!(
+                "Message log channel {} of {} is gone, disabling it",
+                log_channel_id, guild_id
+            );
+            if let Err(e) = message_log.configs().set_channel(guild_id, None).await {
+                error!("Error disabling message log: {:?}", e);
+            }
+        }
+        Err(e) => error!(
+            "Unable to post to message log channel {}: {:?}",
+            log_channel_id, e
+        ),
+    }
+}
+
+/// Roles of member, or none if they aren't cached, e.g. because they already left
+async fn member_roles(ctx: &Context, guild_id: GuildId, user_id: UserId) -> Vec<RoleId> {
+    ctx.cache
+        .member(guild_id, user_id)
+        .await
+        .map(|member| member.roles)
+        .unwrap_or_default()
+}
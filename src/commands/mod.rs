@@ -1,5 +1,7 @@
 pub mod admin;
 pub mod config;
+pub mod message_log;
 pub mod meta;
+pub mod moderation;
 pub mod owner;
 pub mod support;
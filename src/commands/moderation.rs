@@ -0,0 +1,984 @@
+use crate::{
+    database::queries::{InfractionKind, Infractions, PunishmentKind, StickyRoles, TimedPunishment},
+    parsers::{
+        duration::{format_duration, parse_duration},
+        message::MAX_EMBED_DESCRIPTION_LENGTH,
+        purge::{is_bulk_deletable, PurgeArgs},
+    },
+    structures::{
+        errors::CommandError,
+        mod_log::{infraction_embed, infraction_kind_name, log_event, ModLog, ModLogEvent},
+    },
+    utils::{
+        defaults::DEFAULT_HELP_EMBED_COLOUR,
+        misc::{is_not_found, parse_user_id, role_from_name_or_mention, split_into_chunks, truncate_bytes},
+    },
+};
+use anyhow::Context as AnyContext;
+use chrono::{Duration as ChronoDuration, Utc};
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    http::Http,
+    model::prelude::*,
+    prelude::*,
+};
+use std::{sync::Arc, time::Duration};
+use tracing::{debug, error, info};
+
+/// Discord doesn't accept longer audit log reasons, in bytes
+const MAX_AUDIT_LOG_REASON_LENGTH: usize = 512;
+/// How many days of messages softban deletes
+const SOFTBAN_DELETE_MESSAGE_DAYS: u8 = 1;
+/// How often expired punishments are checked, so they are lifted at most this late
+const PUNISHMENT_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// How many latest messages purge looks through at most
+const PURGE_SEARCH_LIMIT: usize = 1000;
+/// Discord limits of a single request
+const MAX_FETCHED_MESSAGES: usize = 100;
+const MAX_BULK_DELETE_MESSAGES: usize = 100;
+/// Messages too old to be bulk deleted take a request each, so purge deletes only this many of them
+const MAX_OLD_MESSAGES_DELETED: usize = 50;
+
+/// Warn member, they get a direct message with the reason
+/// Usage: `warn <user> [reason]`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Messages)]
+#[min_args(1)]
+async fn warn(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let target = target_member(ctx, msg, &mut args).await?;
+    let reason = reason_from_args(&args);
+
+    notify_target(
+        ctx,
+        msg,
+        &target.user,
+        InfractionKind::Warn,
+        reason.as_deref(),
+        None,
+    )
+    .await;
+    record_infraction(ctx, msg, InfractionKind::Warn, target.user.id, reason, None).await
+}
+
+/// Kick member from the server
+/// Usage: `kick <user> [reason]`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Kick_Members)]
+#[min_args(1)]
+async fn kick(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let target = target_member(ctx, msg, &mut args).await?;
+    let reason = reason_from_args(&args);
+
+    notify_target(
+        ctx,
+        msg,
+        &target.user,
+        InfractionKind::Kick,
+        reason.as_deref(),
+        None,
+    )
+    .await;
+    target
+        .guild_id
+        .kick_with_reason(ctx, target.user.id, &audit_log_reason(msg, reason.as_deref()))
+        .await
+        .map_err(|e| CommandError::UserDiscordError("Unable to kick the member".to_string(), e))?;
+    record_infraction(ctx, msg, InfractionKind::Kick, target.user.id, reason, None).await
+}
+
+/// Ban user from the server, they don't have to be a member
+/// Optional duration like `30m`, `2d` or `1w` makes the ban temporary
+/// Usage: `ban <user> [duration] [reason]`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Ban_Members)]
+#[min_args(1)]
+async fn ban(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let user_id = target_user_id(&mut args)?;
+    let duration = duration_from_args(&mut args)?;
+    let reason = reason_from_args(&args);
+    // Users who aren't members can be banned too, so they can't join
+    match guild_id.member(ctx, user_id).await {
+        Ok(target) => {
+            check_can_moderate(ctx, msg, &target).await?;
+            notify_target(
+                ctx,
+                msg,
+                &target.user,
+                InfractionKind::Ban,
+                reason.as_deref(),
+                duration,
+            )
+            .await;
+        }
+        Err(e) if is_not_found(&e) => {}
+        Err(e) => {
+            return Err(
+                CommandError::UserDiscordError(format!("Unable to check member {}", user_id), e).into(),
+            )
+        }
+    }
+
+    guild_id
+        .ban_with_reason(ctx, user_id, 0, audit_log_reason(msg, reason.as_deref()))
+        .await
+        .map_err(|e| CommandError::UserDiscordError("Unable to ban the user".to_string(), e))?;
+    schedule_expiry(ctx, guild_id, user_id, PunishmentKind::Ban, None, duration).await?;
+    record_infraction(ctx, msg, InfractionKind::Ban, user_id, reason, duration).await
+}
+
+/// Ban and immediately unban member, to remove them and their recent messages
+/// Usage: `softban <user> [reason]`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Ban_Members)]
+#[min_args(1)]
+async fn softban(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let target = target_member(ctx, msg, &mut args).await?;
+    let reason = reason_from_args(&args);
+
+    notify_target(
+        ctx,
+        msg,
+        &target.user,
+        InfractionKind::Softban,
+        reason.as_deref(),
+        None,
+    )
+    .await;
+    target
+        .guild_id
+        .ban_with_reason(
+            ctx,
+            target.user.id,
+            SOFTBAN_DELETE_MESSAGE_DAYS,
+            audit_log_reason(msg, reason.as_deref()),
+        )
+        .await
+        .map_err(|e| CommandError::UserDiscordError("Unable to ban the member".to_string(), e))?;
+    target.guild_id.unban(ctx, target.user.id).await.map_err(|e| {
+        CommandError::UserDiscordError("Member was banned, but unable to unban them".to_string(), e)
+    })?;
+    record_infraction(ctx, msg, InfractionKind::Softban, target.user.id, reason, None).await
+}
+
+/// Lift ban of user
+/// Usage: `unban <user ID> [reason]`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Ban_Members)]
+#[min_args(1)]
+async fn unban(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let user_id = target_user_id(&mut args)?;
+    let reason = reason_from_args(&args);
+
+    guild_id
+        .unban(ctx, user_id)
+        .await
+        .map_err(|e| CommandError::UserDiscordError("Unable to unban the user".to_string(), e))?;
+    schedule_expiry(ctx, guild_id, user_id, PunishmentKind::Ban, None, None).await?;
+    record_infraction(ctx, msg, InfractionKind::Unban, user_id, reason, None).await
+}
+
+/// Mute member by giving them the mute role, set it up with `mute_role` first
+/// Optional duration like `30m`, `2d` or `1w` makes the mute temporary
+/// Usage: `mute <user> [duration] [reason]`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Roles)]
+#[aliases("timeout")]
+#[min_args(1)]
+async fn mute(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let mut target = target_member(ctx, msg, &mut args).await?;
+    let duration = duration_from_args(&mut args)?;
+    let reason = reason_from_args(&args);
+    let mute_role = get_mute_role(ctx, target.guild_id).await?;
+    if target.roles.contains(&mute_role) {
+        return Err(CommandError::UserError(format!("{} is already muted", target.user.tag())).into());
+    }
+
+    target
+        .add_role(ctx, mute_role)
+        .await
+        .map_err(|e| CommandError::UserDiscordError("Unable to give the mute role".to_string(), e))?;
+    schedule_expiry(
+        ctx,
+        target.guild_id,
+        target.user.id,
+        PunishmentKind::Mute,
+        Some(mute_role),
+        duration,
+    )
+    .await?;
+    notify_target(
+        ctx,
+        msg,
+        &target.user,
+        InfractionKind::Mute,
+        reason.as_deref(),
+        duration,
+    )
+    .await;
+    record_infraction(ctx, msg, InfractionKind::Mute, target.user.id, reason, duration).await
+}
+
+/// Unmute member, by taking the mute role away
+/// Usage: `unmute <user> [reason]`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Roles)]
+#[aliases("untimeout")]
+#[min_args(1)]
+async fn unmute(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let mut target = target_member(ctx, msg, &mut args).await?;
+    let reason = reason_from_args(&args);
+    let mute_role = get_mute_role(ctx, target.guild_id).await?;
+    if !target.roles.contains(&mute_role) {
+        return Err(CommandError::UserError(format!("{} isn't muted", target.user.tag())).into());
+    }
+
+    target
+        .remove_role(ctx, mute_role)
+        .await
+        .map_err(|e| CommandError::UserDiscordError("Unable to remove the mute role".to_string(), e))?;
+    schedule_expiry(
+        ctx,
+        target.guild_id,
+        target.user.id,
+        PunishmentKind::Mute,
+        None,
+        None,
+    )
+    .await?;
+    record_infraction(ctx, msg, InfractionKind::Unmute, target.user.id, reason, None).await
+}
+
+/// Give member a role for limited time, it's taken away once the duration like `30m`, `2d` or `1w` passes
+/// Usage: `temprole <user> <role> <duration>`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Roles)]
+#[num_args(3)]
+async fn temprole(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = target_user_id(&mut args)?;
+    let role_str = args
+        .single_quoted::<String>()
+        .context("Unable to get second argument")?;
+    let duration_str = args.single::<String>().context("Unable to get third argument")?;
+    let duration = check_duration(&duration_str, parse_duration(&duration_str)?)?;
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let role_id = role_from_name_or_mention(ctx, &guild_id, role_str).await?;
+    check_can_grant(ctx, msg, role_id).await?;
+    let mut target = guild_id
+        .member(ctx, user_id)
+        .await
+        .map_err(|e| CommandError::UserDiscordError(format!("{} isn't a member", user_id), e))?;
+
+    target
+        .add_role(ctx, role_id)
+        .await
+        .map_err(|e| CommandError::UserDiscordError("Unable to give the role".to_string(), e))?;
+    schedule_expiry(
+        ctx,
+        guild_id,
+        user_id,
+        PunishmentKind::Role,
+        Some(role_id),
+        Some(duration),
+    )
+    .await?;
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.content(format!(
+                "Gave {} to {} for {}",
+                role_id.mention(),
+                user_id.mention(),
+                format_duration(duration)
+            ))
+            .allowed_mentions(|am| am.empty_parse())
+        })
+        .await?;
+    let event = ModLogEvent::TemporaryRoleGiven {
+        moderator: msg.author.id,
+        user_id,
+        role_id,
+        duration,
+    };
+    log_event(ctx, guild_id, event).await;
+    Ok(())
+}
+
+/// Set role given to muted members
+/// The role should deny sending messages in channels, consider making it sticky with `sticky_role add`
+/// Usage: `mute_role <role>`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Roles)]
+#[num_args(1)]
+async fn mute_role(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let role_str = args.single::<String>().context("Unable to get first argument")?;
+    let infractions = {
+        let data = ctx.data.read().await;
+        let infractions = data
+            .get::<Infractions>()
+            .context("Can't get infractions")?
+            .clone();
+        infractions
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let role_id = role_from_name_or_mention(ctx, &guild_id, role_str).await?;
+
+    infractions.set_mute_role(guild_id, role_id).await?;
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.content(format!("Muted members will get {}", role_id.mention()))
+                .allowed_mentions(|am| am.empty_parse())
+        })
+        .await?;
+    Ok(())
+}
+
+/// Set channel which records moderation actions, configuration changes and members joining and leaving
+/// Usage: `mod_log [#channel|disable]`, without arguments shows the current channel
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Guild)]
+#[max_args(1)]
+async fn mod_log(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let mod_log = {
+        let data = ctx.data.read().await;
+        let mod_log = data.get::<ModLog>().context("Can't get mod-log")?.clone();
+        mod_log
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+
+    let content = if args.is_empty() {
+        match mod_log.get_channel(guild_id).await? {
+            Some(channel_id) => format!("Mod-log is posted in {}", channel_id.mention()),
+            None => "Mod-log is disabled".to_string(),
+        }
+    } else if args.current().map(|arg| arg.eq_ignore_ascii_case("disable")) == Some(true) {
+        mod_log.set_channel(guild_id, None).await?;
+        "Mod-log disabled".to_string()
+    } else {
+        let channel_id = args
+            .single::<ChannelId>()
+            .map_err(|_| CommandError::UserError("Please mention a channel, or use `disable`".to_string()))?;
+        let in_guild = ctx.cache.guild_channel_field(channel_id, |c| c.guild_id).await == Some(guild_id);
+        if !in_guild {
+            return Err(CommandError::UserError("Channel isn't in this server".to_string()).into());
+        }
+        mod_log.set_channel(guild_id, Some(channel_id)).await?;
+        format!("Mod-log will be posted in {}", channel_id.mention())
+    };
+    msg.channel_id.say(ctx, content).await?;
+    Ok(())
+}
+
+/// Delete recent messages, optionally only ones matching all given filters
+/// Usage: `purge <number> [--user <user>] [--bots] [--contains <text>] [--regex <pattern>] [--attachments] [--links] [--before <message_id>] [--after <message_id>]`
+/// Looks through at most 1000 latest messages, ones older than 14 days can only be deleted slowly one by one,
+/// so at most 50 of those are deleted
+/// Example: `purge 20 --user @Spammer --links`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Messages)]
+#[aliases("clean", "prune")]
+#[min_args(1)]
+async fn purge(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let raw_args = args
+        .quoted()
+        .iter::<String>()
+        .collect::<Result<Vec<String>, _>>()
+        .context("Unable to iterate over arguments!")?;
+    let purge_args = PurgeArgs::parse(raw_args)?;
+    if purge_args.count == 0 || purge_args.count as usize > PURGE_SEARCH_LIMIT {
+        return Err(CommandError::UserError(format!(
+            "Number of messages has to be between 1 and {}",
+            PURGE_SEARCH_LIMIT
+        ))
+        .into());
+    }
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    // _typing is stopped when it's dropped
+    let _typing = msg
+        .channel_id
+        .start_typing(&ctx.http)
+        .context("Unable to start typing")?;
+
+    let message_ids = find_purged_messages(ctx, msg, &purge_args).await?;
+    let now = Utc::now();
+    let (recent, old): (Vec<MessageId>, Vec<MessageId>) = message_ids
+        .into_iter()
+        .partition(|message_id| is_bulk_deletable(*message_id, now));
+    for chunk in recent.chunks(MAX_BULK_DELETE_MESSAGES) {
+        match chunk {
+            [message_id] => msg.channel_id.delete_message(ctx, message_id).await?,
+            _ => msg.channel_id.delete_messages(ctx, chunk).await?,
+        }
+    }
+    let (old, skipped) = old.split_at(old.len().min(MAX_OLD_MESSAGES_DELETED));
+    let mut deleted = recent.len();
+    for message_id in old {
+        match msg.channel_id.delete_message(ctx, message_id).await {
+            Ok(_) => deleted += 1,
+            // Someone else deleted it in the meantime
+            Err(e) if is_not_found(&e) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let filters = purge_args.filter.describe();
+    let mut summary = format!("Deleted {} messages", deleted);
+    if !filters.is_empty() {
+        summary.push_str(&format!(" {}", filters));
+    }
+    if !old.is_empty() {
+        summary.push_str(&format!(
+            " ({} were older than 14 days and had to be deleted one by one)",
+            old.len()
+        ));
+    }
+    if !skipped.is_empty() {
+        summary.push_str(&format!(", {} more old messages were left alone", skipped.len()));
+    }
+    msg.channel_id
+        .send_message(ctx, |m| {
+            m.content(summary).allowed_mentions(|am| am.empty_parse())
+        })
+        .await?;
+    let event = ModLogEvent::MessagesPurged {
+        moderator: msg.author.id,
+        channel_id: msg.channel_id,
+        count: deleted,
+        filters,
+    };
+    log_event(ctx, guild_id, event).await;
+    Ok(())
+}
+
+/// Goes back from `--before` message or the command, until enough matching messages are found
+async fn find_purged_messages(
+    ctx: &Context,
+    msg: &Message,
+    purge_args: &PurgeArgs,
+) -> Result<Vec<MessageId>, anyhow::Error> {
+    let count = purge_args.count as usize;
+    let mut message_ids = Vec::with_capacity(count);
+    let mut before = purge_args.before.unwrap_or(msg.id);
+    let mut searched = 0;
+    while searched < PURGE_SEARCH_LIMIT {
+        let limit = (PURGE_SEARCH_LIMIT - searched).min(MAX_FETCHED_MESSAGES);
+        let messages = msg
+            .channel_id
+            .messages(ctx, |r| r.before(before).limit(limit as u64))
+            .await?;
+        for message in &messages {
+            if purge_args.after.map(|after| message.id <= after) == Some(true) {
+                return Ok(message_ids);
+            }
+            let matches = purge_args.filter.matches(
+                message.author.id,
+                message.author.bot,
+                &message.content,
+                !message.attachments.is_empty(),
+            );
+            if matches {
+                message_ids.push(message.id);
+                if message_ids.len() == count {
+                    return Ok(message_ids);
+                }
+            }
+        }
+        searched += messages.len();
+        match messages.last() {
+            Some(oldest) if messages.len() == limit => before = oldest.id,
+            // Reached the beginning of the channel
+            _ => break,
+        }
+    }
+    Ok(message_ids)
+}
+
+/// Show details of a case
+/// Usage: `case <number>`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Messages)]
+#[num_args(1)]
+async fn case(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let case_number = args.single::<i32>().context("Unable to parse case number")?;
+    let infractions = {
+        let data = ctx.data.read().await;
+        let infractions = data
+            .get::<Infractions>()
+            .context("Can't get infractions")?
+            .clone();
+        infractions
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let infraction = infractions
+        .get_infraction(guild_id, case_number)
+        .await?
+        .ok_or_else(|| CommandError::UserError(format!("Case #{} doesn't exist", case_number)))?;
+
+    msg.channel_id
+        .send_message(ctx, |m| m.embed(|e| infraction_embed(e, &infraction)))
+        .await?;
+    Ok(())
+}
+
+/// List infractions of user
+/// Usage: `infractions <user>`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Messages)]
+#[aliases("warnings", "modlogs")]
+#[num_args(1)]
+async fn infractions(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let user_id = target_user_id(&mut args)?;
+    let infractions = {
+        let data = ctx.data.read().await;
+        let infractions = data
+            .get::<Infractions>()
+            .context("Can't get infractions")?
+            .clone();
+        infractions
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let user_infractions = infractions.get_user_infractions(guild_id, user_id).await?;
+    if user_infractions.is_empty() {
+        msg.channel_id
+            .send_message(ctx, |m| {
+                m.content(format!("{} has no infractions", user_id.mention()))
+                    .allowed_mentions(|am| am.empty_parse())
+            })
+            .await?;
+        return Ok(());
+    }
+
+    let lines = user_infractions
+        .iter()
+        .map(|infraction| {
+            format!(
+                "**#{}** {} - {} ({})",
+                infraction.case_number,
+                infraction_kind_name(infraction.kind),
+                infraction.reason.as_deref().unwrap_or("no reason"),
+                infraction.created_at.format("%Y-%m-%d")
+            )
+        })
+        .collect::<Vec<String>>();
+    for chunk in split_into_chunks(&lines, MAX_EMBED_DESCRIPTION_LENGTH) {
+        msg.channel_id
+            .send_message(ctx, |m| {
+                m.embed(|e| {
+                    e.colour(DEFAULT_HELP_EMBED_COLOUR);
+                    e.title(format!("Infractions of {}", user_id));
+                    e.description(chunk)
+                })
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+/// Change reason of a case
+/// Usage: `reason <case number> <reason>`
+#[command]
+#[only_in("guilds")]
+#[required_permissions(Manage_Messages)]
+#[min_args(2)]
+async fn reason(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let case_number = args.single::<i32>().context("Unable to parse case number")?;
+    let reason = args.rest().trim().to_string();
+    let infractions = {
+        let data = ctx.data.read().await;
+        let infractions = data
+            .get::<Infractions>()
+            .context("Can't get infractions")?
+            .clone();
+        infractions
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+
+    let infraction = infractions
+        .set_reason(guild_id, case_number, reason)
+        .await?
+        .ok_or_else(|| CommandError::UserError(format!("Case #{} doesn't exist", case_number)))?;
+    msg.channel_id
+        .send_message(ctx, |m| m.embed(|e| infraction_embed(e, &infraction)))
+        .await?;
+    log_event(ctx, guild_id, ModLogEvent::Infraction(infraction)).await;
+    Ok(())
+}
+
+fn target_user_id(args: &mut Args) -> Result<UserId, CommandError> {
+    let user_str = args
+        .single::<String>()
+        .map_err(|_| CommandError::UserError("Please provide user mention or ID".to_string()))?;
+    parse_user_id(&user_str)
+        .ok_or_else(|| CommandError::UserError(format!("Not a user mention or ID: `{}`", user_str)))
+}
+
+/// Gets member from the first argument, who the author is allowed to moderate
+async fn target_member(ctx: &Context, msg: &Message, args: &mut Args) -> Result<Member, anyhow::Error> {
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let user_id = target_user_id(args)?;
+    let target = guild_id
+        .member(ctx, user_id)
+        .await
+        .map_err(|e| CommandError::UserDiscordError(format!("{} isn't a member", user_id), e))?;
+    check_can_moderate(ctx, msg, &target).await?;
+    Ok(target)
+}
+
+/// Moderators can only act on members below them, and never on the owner
+async fn check_can_moderate(ctx: &Context, msg: &Message, target: &Member) -> Result<(), anyhow::Error> {
+    if target.user.id == msg.author.id {
+        return Err(CommandError::UserError("You can't moderate yourself".to_string()).into());
+    }
+    let owner_id = ctx
+        .cache
+        .guild_field(target.guild_id, |g| g.owner_id)
+        .await
+        .context("Guild isn't cached")?;
+    if target.user.id == owner_id {
+        return Err(CommandError::UserError("Server owner can't be moderated".to_string()).into());
+    }
+    if msg.author.id == owner_id {
+        return Ok(());
+    }
+    let moderator = msg.member(ctx).await.context("Unable to get author's member")?;
+    let target_position = target
+        .highest_role_info(&ctx.cache)
+        .await
+        .map(|(_, position)| position)
+        .unwrap_or(0);
+    let moderator_position = moderator
+        .highest_role_info(&ctx.cache)
+        .await
+        .map(|(_, position)| position)
+        .unwrap_or(0);
+    if target_position >= moderator_position {
+        return Err(CommandError::UserError(format!(
+            "{} has the same or higher role than you",
+            target.user.tag()
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Moderators can only give roles below their highest role, like in Discord
+async fn check_can_grant(ctx: &Context, msg: &Message, role_id: RoleId) -> Result<(), anyhow::Error> {
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+    let (owner_id, role_position) = ctx
+        .cache
+        .guild_field(guild_id, |g| {
+            (g.owner_id, g.roles.get(&role_id).map(|role| role.position))
+        })
+        .await
+        .context("Guild isn't cached")?;
+    let role_position = role_position.with_context(|| format!("Unable to find role with id {}", role_id))?;
+    if msg.author.id == owner_id {
+        return Ok(());
+    }
+    let moderator = msg.member(ctx).await.context("Unable to get author's member")?;
+    let moderator_position = moderator
+        .highest_role_info(&ctx.cache)
+        .await
+        .map(|(_, position)| position)
+        .unwrap_or(0);
+    if role_position >= moderator_position {
+        return Err(
+            CommandError::UserError("You can only give roles below your highest role".to_string()).into(),
+        );
+    }
+    Ok(())
+}
+
+/// Takes optional duration, like `2d` - plain numbers are left for the reason
+fn duration_from_args(args: &mut Args) -> Result<Option<Duration>, CommandError> {
+    let duration = match args
+        .current()
+        .filter(|arg| !arg.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|arg| parse_duration(arg).ok().map(|duration| (arg, duration)))
+        .filter(|(_, duration)| duration.as_secs() > 0)
+    {
+        Some((arg, duration)) => check_duration(arg, duration)?,
+        None => return Ok(None),
+    };
+    args.advance();
+    Ok(Some(duration))
+}
+
+/// Durations are stored in seconds as `integer`, longer ones wouldn't fit
+fn check_duration(input: &str, duration: Duration) -> Result<Duration, CommandError> {
+    if duration.as_secs() > i32::MAX as u64 {
+        return Err(CommandError::UserError(format!(
+            "Duration `{}` is too long",
+            input
+        )));
+    }
+    Ok(duration)
+}
+
+/// Schedules lifting of punishment after `duration`, or cancels scheduled lifting for permanent punishments
+async fn schedule_expiry(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    kind: PunishmentKind,
+    role_id: Option<RoleId>,
+    duration: Option<Duration>,
+) -> Result<(), anyhow::Error> {
+    let infractions = {
+        let data = ctx.data.read().await;
+        let infractions = data
+            .get::<Infractions>()
+            .context("Can't get infractions")?
+            .clone();
+        infractions
+    };
+    match duration {
+        Some(duration) => {
+            let expires_at = ChronoDuration::from_std(duration)
+                .ok()
+                .and_then(|duration| Utc::now().checked_add_signed(duration))
+                .context("Duration is too long")?;
+            infractions
+                .set_expiry(TimedPunishment {
+                    guild_id,
+                    user_id,
+                    kind,
+                    role_id,
+                    expires_at,
+                })
+                .await?;
+        }
+        None => {
+            infractions
+                .delete_expiry(guild_id, user_id, kind, role_id)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Lifts expired punishments, they are stored in database so ones which expired during downtime are lifted too
+pub async fn punishment_expiry_scheduler(
+    http: Arc<Http>,
+    infractions: Arc<Infractions>,
+    sticky_roles: Arc<StickyRoles>,
+    mod_log: Arc<ModLog>,
+    bot_id: UserId,
+) {
+    let mut interval = tokio::time::interval(PUNISHMENT_EXPIRY_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let expired = match infractions.get_expired(Utc::now()).await {
+            Ok(expired) => expired,
+            Err(e) => {
+                error!("Error getting expired punishments: {:?}", e);
+                continue;
+            }
+        };
+        for (timed_punishment_id, punishment) in expired {
+            let result =
+                match lift_punishment(&http, &infractions, &sticky_roles, &mod_log, bot_id, punishment).await
+                {
+                    LiftOutcome::Lifted => infractions.delete_expiry_by_id(timed_punishment_id).await,
+                    // Postponed, so a failing one doesn't block the rest
+                    LiftOutcome::Failed => infractions.postpone_expiry(timed_punishment_id).await,
+                };
+            if let Err(e) = result {
+                error!("Error updating expired punishment: {:?}", e);
+            }
+        }
+    }
+}
+
+enum LiftOutcome {
+    /// Punishment is gone, including when it was lifted by hand
+    Lifted,
+    /// Should be retried later
+    Failed,
+}
+
+async fn lift_punishment(
+    http: &Http,
+    infractions: &Infractions,
+    sticky_roles: &StickyRoles,
+    mod_log: &ModLog,
+    bot_id: UserId,
+    punishment: TimedPunishment,
+) -> LiftOutcome {
+    let TimedPunishment {
+        guild_id,
+        user_id,
+        kind,
+        role_id,
+        ..
+    } = punishment;
+    let (result, infraction_kind) = match (kind, role_id) {
+        (PunishmentKind::Ban, _) => (
+            http.remove_ban(guild_id.0, user_id.0).await,
+            Some(InfractionKind::Unban),
+        ),
+        (PunishmentKind::Mute, Some(role_id)) => (
+            http.remove_member_role(guild_id.0, user_id.0, role_id.0).await,
+            Some(InfractionKind::Unmute),
+        ),
+        (PunishmentKind::Role, Some(role_id)) => (
+            http.remove_member_role(guild_id.0, user_id.0, role_id.0).await,
+            None,
+        ),
+        (_, None) => {
+            error!("Timed punishment without role: {:?}", punishment);
+            return LiftOutcome::Lifted;
+        }
+    };
+    match result {
+        Ok(_) => info!("Lifted expired {:?} of {} in {}", kind, user_id, guild_id),
+        // Unbanned by hand, the role got deleted, or the member left
+        Err(e) if is_not_found(&e) => {
+            debug!("Expired {:?} of {} was already lifted: {:?}", kind, user_id, e);
+            // Member who left would get the role back from sticky roles on rejoin
+            if let Some(role_id) = role_id {
+                if let Err(e) = sticky_roles.forget_member_role(guild_id, user_id, role_id).await {
+                    error!("Error lifting expired {:?} of member who left: {:?}", kind, e);
+                    return LiftOutcome::Failed;
+                }
+            }
+            return LiftOutcome::Lifted;
+        }
+        Err(e) => {
+            error!("Error lifting expired {:?} of {}: {:?}", kind, user_id, e);
+            return LiftOutcome::Failed;
+        }
+    }
+    match (infraction_kind, role_id) {
+        (Some(infraction_kind), _) => {
+            let reason = Some(format!("Temporary {} expired", kind.as_ref()));
+            match infractions
+                .add_infraction(guild_id, infraction_kind, bot_id, user_id, reason, None)
+                .await
+            {
+                Ok(infraction) => {
+                    mod_log
+                        .log(http, guild_id, ModLogEvent::Infraction(infraction))
+                        .await
+                }
+                Err(e) => error!("Error recording lifted punishment: {:?}", e),
+            }
+        }
+        (None, Some(role_id)) => {
+            mod_log
+                .log(
+                    http,
+                    guild_id,
+                    ModLogEvent::TemporaryRoleExpired { user_id, role_id },
+                )
+                .await
+        }
+        (None, None) => {}
+    }
+    LiftOutcome::Lifted
+}
+
+fn reason_from_args(args: &Args) -> Option<String> {
+    Some(args.rest().trim().to_string()).filter(|reason| !reason.is_empty())
+}
+
+/// Reason shown in audit log, where the bot is the one doing the action
+fn audit_log_reason(msg: &Message, reason: Option<&str>) -> String {
+    let reason = format!("{}: {}", msg.author.tag(), reason.unwrap_or("no reason"));
+    truncate_bytes(&reason, MAX_AUDIT_LOG_REASON_LENGTH)
+}
+
+async fn get_mute_role(ctx: &Context, guild_id: GuildId) -> Result<RoleId, anyhow::Error> {
+    let infractions = {
+        let data = ctx.data.read().await;
+        let infractions = data
+            .get::<Infractions>()
+            .context("Can't get infractions")?
+            .clone();
+        infractions
+    };
+    let mute_role = infractions.get_mute_role(guild_id).await?.ok_or_else(|| {
+        CommandError::UserError("Mute role isn't set, please set it with `mute_role` first".to_string())
+    })?;
+    Ok(mute_role)
+}
+
+/// Lets target know about the infraction, they might have direct messages closed though
+async fn notify_target(
+    ctx: &Context,
+    msg: &Message,
+    target: &User,
+    kind: InfractionKind,
+    reason: Option<&str>,
+    duration: Option<Duration>,
+) {
+    let guild_name = match msg.guild_field(ctx, |g| g.name.clone()).await {
+        Some(guild_name) => guild_name,
+        None => return,
+    };
+    let content = format!(
+        "You were {} in {}{}. Reason: {}",
+        kind_past_tense(kind),
+        guild_name,
+        duration
+            .map(|duration| format!(" for {}", format_duration(duration)))
+            .unwrap_or_default(),
+        reason.unwrap_or("no reason given")
+    );
+    if let Err(e) = target.direct_message(ctx, |m| m.content(content)).await {
+        debug!("Unable to notify {} about infraction: {:?}", target.id, e);
+    }
+}
+
+async fn record_infraction(
+    ctx: &Context,
+    msg: &Message,
+    kind: InfractionKind,
+    target_id: UserId,
+    reason: Option<String>,
+    duration: Option<Duration>,
+) -> CommandResult {
+    let infractions = {
+        let data = ctx.data.read().await;
+        let infractions = data
+            .get::<Infractions>()
+            .context("Can't get infractions")?
+            .clone();
+        infractions
+    };
+    let guild_id = msg.guild_id.context("Not in a guild")?;
+
+    let infraction = infractions
+        .add_infraction(guild_id, kind, msg.author.id, target_id, reason, duration)
+        .await?;
+    msg.channel_id
+        .send_message(ctx, |m| m.embed(|e| infraction_embed(e, &infraction)))
+        .await?;
+    log_event(ctx, guild_id, ModLogEvent::Infraction(infraction)).await;
+    Ok(())
+}
+
+fn kind_past_tense(kind: InfractionKind) -> &'static str {
+    match kind {
+        InfractionKind::Warn => "warned",
+        InfractionKind::Kick => "kicked",
+        InfractionKind::Ban => "banned",
+        InfractionKind::Unban => "unbanned",
+        InfractionKind::Softban => "softbanned",
+        InfractionKind::Mute => "muted",
+        InfractionKind::Unmute => "unmuted",
+    }
+}
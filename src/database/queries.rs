@@ -1,14 +1,26 @@
-use crate::structures::errors::DatabaseError;
+use crate::{
+    parsers::bulk_role::{BulkRoleMode, MemberFilter},
+    structures::{
+        autoresponders::{Trigger, TriggerKind},
+        cooldowns::{Cooldown, CooldownScope},
+        errors::DatabaseError,
+        member_kind::MemberKind,
+    },
+};
+use chrono::{DateTime, Utc};
 use serenity::{
-    model::id::{ChannelId, GuildId, MessageId, RoleId},
-    prelude::{RwLock, TypeMapKey},
+    model::id::{ChannelId, GuildId, MessageId, RoleId, UserId},
+    prelude::{Mutex, RwLock, TypeMapKey},
 };
 use sqlx::PgPool;
 use std::{
     collections::{HashMap, HashSet},
     fmt,
+    str::FromStr,
     sync::Arc,
+    time::Duration,
 };
+use strum_macros::{AsRefStr, EnumString};
 use tracing::instrument;
 
 #[derive(Clone, Debug)]
@@ -137,6 +149,59 @@ impl TypeMapKey for GuildInfoTable {
     type Value = Arc<Self>;
 }
 
+#[derive(Clone, Debug)]
+pub struct CustomCommand {
+    pub command_id: i64,
+    pub name: String,
+    pub guild_cooldown: i32,
+    pub user_cooldown: i32,
+    pub channel_cooldown: i32,
+    pub cooldown_message: bool,
+}
+
+impl CustomCommand {
+    /// Cooldowns that apply when the command is used by `user_id` in `channel_id`
+    pub fn cooldowns(&self, guild_id: GuildId, channel_id: ChannelId, user_id: UserId) -> Vec<Cooldown> {
+        vec![
+            (CooldownScope::Guild, guild_id.0, self.guild_cooldown),
+            (CooldownScope::User, user_id.0, self.user_cooldown),
+            (CooldownScope::Channel, channel_id.0, self.channel_cooldown),
+        ]
+        .into_iter()
+        .map(|(scope, id, seconds)| Cooldown {
+            scope,
+            id,
+            duration: Duration::from_secs(seconds.max(0) as u64),
+        })
+        .collect()
+    }
+}
+
+/// One of possible responses to a custom command, picked randomly with chance proportional to `weight`
+#[derive(Clone, Debug)]
+pub struct CommandResponse {
+    pub command_response_id: i64,
+    pub content: String,
+    pub weight: i32,
+    /// Responses saved before templates were supported are sent as they are
+    pub templated: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum RestrictionKind {
+    AllowRole,
+    DenyRole,
+    AllowChannel,
+    DenyChannel,
+}
+
+#[derive(Clone, Debug)]
+pub struct CommandRestriction {
+    pub kind: RestrictionKind,
+    pub target_id: u64,
+}
+
 #[derive(Debug)]
 pub struct CustomCommands {
     pool: PgPool,
@@ -147,6 +212,7 @@ impl CustomCommands {
         Self { pool }
     }
 
+    /// Creates command or replaces all of its responses with `content`
     #[instrument]
     pub async fn set_command(
         &self,
@@ -154,17 +220,90 @@ impl CustomCommands {
         name: String,
         content: String,
     ) -> Result<(), DatabaseError> {
+        let mut transaction = self.pool.begin().await?;
+        let command = sqlx::query!(
+            "INSERT INTO commands (guild_id, name) VALUES ($1, $2)
+            ON CONFLICT (guild_id, name) DO UPDATE SET name = EXCLUDED.name
+            RETURNING command_id",
+            i64::from(guild_id),
+            name
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM command_responses WHERE command_id = $1",
+            command.command_id
+        )
+        .execute(&mut transaction)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO command_responses (command_id, content) VALUES ($1, $2)",
+            command.command_id,
+            content
+        )
+        .execute(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Adds another response variant to existing command, `name` can be an alias.
+    /// Returns number of rows affected - 0 if command doesn't exist.
+    #[instrument]
+    pub async fn add_response(
+        &self,
+        guild_id: GuildId,
+        name: String,
+        content: String,
+        weight: i32,
+    ) -> Result<u64, DatabaseError> {
         sqlx::query!(
-            "INSERT INTO commands (guild_id, name, content) VALUES ($1, $2, $3)
-            ON CONFLICT (guild_id, name) DO UPDATE SET content = EXCLUDED.content",
+            "INSERT INTO command_responses (command_id, content, weight)
+            SELECT command_id, $3, $4
+            FROM commands
+            WHERE guild_id = $1
+            AND (
+                name = $2
+                OR command_id IN (SELECT command_id FROM command_aliases WHERE guild_id = $1 AND alias = $2)
+            )",
             i64::from(guild_id),
             name,
-            content
+            content,
+            weight
         )
         .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    /// Responses of a command, in order they were added
+    #[instrument]
+    pub async fn get_responses(&self, command_id: i64) -> Result<Vec<CommandResponse>, DatabaseError> {
+        let responses = sqlx::query_as!(
+            CommandResponse,
+            "SELECT command_response_id, content, weight, templated
+            FROM command_responses
+            WHERE command_id = $1
+            ORDER BY command_response_id",
+            command_id
+        )
+        .fetch_all(&self.pool)
         .await?;
+        Ok(responses)
+    }
 
-        Ok(())
+    #[instrument]
+    pub async fn delete_response(&self, command_response_id: i64) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM command_responses WHERE command_response_id = $1",
+            command_response_id
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
     }
 
     #[instrument]
@@ -172,126 +311,168 @@ impl CustomCommands {
         &self,
         guild_id: GuildId,
         name: String,
-    ) -> Result<Option<String>, DatabaseError> {
-        let returned = sqlx::query!(
-            "SELECT content FROM commands WHERE guild_id = $1 AND name = $2",
+    ) -> Result<Option<CustomCommand>, DatabaseError> {
+        let returned = sqlx::query_as!(
+            CustomCommand,
+            "SELECT command_id, name, guild_cooldown, user_cooldown, channel_cooldown, cooldown_message
+            FROM commands
+            WHERE guild_id = $1
+            AND (
+                name = $2
+                OR command_id IN (SELECT command_id FROM command_aliases WHERE guild_id = $1 AND alias = $2)
+            )",
             i64::from(guild_id),
             name
         )
         .fetch_optional(&self.pool)
         .await?;
-        Ok(returned.map(|value| value.content))
+        Ok(returned)
     }
 
+    /// Points `alias` at `target`, which can be either command name or another alias.
+    /// Returns number of rows affected - 0 if `target` doesn't exist.
     #[instrument]
-    pub async fn get_command_names(&self, guild_id: GuildId) -> Result<Vec<String>, DatabaseError> {
-        let names = sqlx::query!(
-            "SELECT name FROM commands WHERE guild_id = $1",
-            i64::from(guild_id)
+    pub async fn set_alias(
+        &self,
+        guild_id: GuildId,
+        alias: String,
+        target: String,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO command_aliases (guild_id, command_id, alias)
+            SELECT guild_id, command_id, $3 FROM commands
+            WHERE guild_id = $1
+            AND (
+                name = $2
+                OR command_id IN (SELECT command_id FROM command_aliases WHERE guild_id = $1 AND alias = $2)
+            )
+            ON CONFLICT (guild_id, alias) DO UPDATE SET command_id = EXCLUDED.command_id",
+            i64::from(guild_id),
+            target,
+            alias
         )
-        .fetch_all(&self.pool)
-        .await?
-        .into_iter()
-        .map(|value| value.name)
-        .collect::<Vec<String>>();
-        Ok(names)
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
     }
 
     #[instrument]
-    pub async fn delete_command(&self, guild_id: GuildId, name: String) -> Result<(), DatabaseError> {
+    pub async fn delete_alias(&self, guild_id: GuildId, alias: String) -> Result<u64, DatabaseError> {
         sqlx::query!(
-            "DELETE FROM commands WHERE guild_id = $1 AND name = $2",
+            "DELETE FROM command_aliases WHERE guild_id = $1 AND alias = $2",
             i64::from(guild_id),
-            name
+            alias
         )
         .execute(&self.pool)
-        .await?;
-        Ok(())
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
     }
-}
-
-impl TypeMapKey for CustomCommands {
-    type Value = Arc<Self>;
-}
-
-#[derive(Debug)]
-pub struct ReactionRoles {
-    pool: PgPool,
-}
 
-impl ReactionRoles {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    /// Returns aliases for each command name in the guild
+    #[instrument]
+    pub async fn get_aliases(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<HashMap<String, Vec<String>>, DatabaseError> {
+        let rows = sqlx::query!(
+            "SELECT c.name, a.alias FROM command_aliases a
+            JOIN commands c ON c.command_id = a.command_id
+            WHERE a.guild_id = $1
+            ORDER BY a.alias",
+            i64::from(guild_id)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut aliases: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            aliases.entry(row.name).or_default().push(row.alias);
+        }
+        Ok(aliases)
     }
 
+    /// Sets cooldown of command `name` in given scope, 0 disables it.
+    /// Returns number of rows affected - 0 if command doesn't exist.
     #[instrument]
-    pub async fn set_react_role(
+    pub async fn set_cooldown(
         &self,
         guild_id: GuildId,
-        channel_id: ChannelId,
-        message_id: MessageId,
-        role_id: RoleId,
-        reaction: String,
-    ) -> Result<(), DatabaseError> {
+        name: String,
+        scope: CooldownScope,
+        seconds: i32,
+    ) -> Result<u64, DatabaseError> {
+        let for_scope = |s: CooldownScope| Some(seconds).filter(|_| s == scope);
         sqlx::query!(
-            "INSERT INTO react_roles (guild_id, channel_id, message_id, role_id, reaction_emoji)
-            VALUES ($1, $2, $3, $4, $5)
-            ON CONFLICT (guild_id, channel_id, message_id, reaction_emoji)
-            DO UPDATE SET role_id = EXCLUDED.role_id",
+            "UPDATE commands SET
+            guild_cooldown = COALESCE($3, guild_cooldown),
+            user_cooldown = COALESCE($4, user_cooldown),
+            channel_cooldown = COALESCE($5, channel_cooldown)
+            WHERE guild_id = $1
+            AND (
+                name = $2
+                OR command_id IN (SELECT command_id FROM command_aliases WHERE guild_id = $1 AND alias = $2)
+            )",
             i64::from(guild_id),
-            i64::from(channel_id),
-            i64::from(message_id),
-            i64::from(role_id),
-            reaction
+            name,
+            for_scope(CooldownScope::Guild),
+            for_scope(CooldownScope::User),
+            for_scope(CooldownScope::Channel)
         )
         .execute(&self.pool)
-        .await?;
-
-        Ok(())
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
     }
 
+    /// Sets whether users get told they're on cooldown
     #[instrument]
-    pub async fn get_react_role(
+    pub async fn set_cooldown_message(
         &self,
         guild_id: GuildId,
-        channel_id: ChannelId,
-        message_id: MessageId,
-        reaction: String,
-    ) -> Result<Option<RoleId>, DatabaseError> {
-        let returned = sqlx::query!(
-            "SELECT role_id FROM react_roles
+        name: String,
+        enabled: bool,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "UPDATE commands SET cooldown_message = $3
             WHERE guild_id = $1
-            AND channel_id = $2
-            AND message_id = $3
-            AND reaction_emoji = $4",
+            AND (
+                name = $2
+                OR command_id IN (SELECT command_id FROM command_aliases WHERE guild_id = $1 AND alias = $2)
+            )",
             i64::from(guild_id),
-            i64::from(channel_id),
-            i64::from(message_id),
-            reaction
+            name,
+            enabled
         )
-        .fetch_optional(&self.pool)
-        .await?;
-        Ok(returned.map(|value| RoleId::from(value.role_id as u64)))
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
     }
 
+    /// Allows or denies using command (or its alias) `name` for given role or channel.
+    /// Returns number of rows affected - 0 if command doesn't exist.
     #[instrument]
-    pub async fn delete_react_role(
+    pub async fn set_restriction(
         &self,
         guild_id: GuildId,
-        channel_id: ChannelId,
-        message_id: MessageId,
-        reaction: String,
+        name: String,
+        kind: RestrictionKind,
+        target_id: u64,
     ) -> Result<u64, DatabaseError> {
         sqlx::query!(
-            "DELETE FROM react_roles
+            "INSERT INTO command_restrictions (command_id, kind, target_id)
+            SELECT command_id, $3, $4 FROM commands
             WHERE guild_id = $1
-            AND channel_id = $2
-            AND message_id = $3
-            AND reaction_emoji = $4",
+            AND (
+                name = $2
+                OR command_id IN (SELECT command_id FROM command_aliases WHERE guild_id = $1 AND alias = $2)
+            )
+            ON CONFLICT (command_id, target_id) DO UPDATE SET kind = EXCLUDED.kind",
             i64::from(guild_id),
-            i64::from(channel_id),
-            i64::from(message_id),
-            reaction
+            name,
+            kind.as_ref(),
+            target_id as i64
         )
         .execute(&self.pool)
         .await
@@ -299,79 +480,88 @@ impl ReactionRoles {
         .map_err(|err| err.into())
     }
 
+    /// Deletes restrictions of command `name` - only the one for `target_id`, if given
     #[instrument]
-    pub async fn delete_react_roles(
+    pub async fn delete_restrictions(
         &self,
         guild_id: GuildId,
-        channel_id: ChannelId,
-        message_id: MessageId,
+        name: String,
+        target_id: Option<u64>,
     ) -> Result<u64, DatabaseError> {
         sqlx::query!(
-            "DELETE FROM react_roles
-            WHERE guild_id = $1
-            AND channel_id = $2
-            AND message_id = $3",
+            "DELETE FROM command_restrictions
+            WHERE ($3::bigint IS NULL OR target_id = $3)
+            AND command_id IN (
+                SELECT command_id FROM commands
+                WHERE guild_id = $1
+                AND (
+                    name = $2
+                    OR command_id IN (SELECT command_id FROM command_aliases WHERE guild_id = $1 AND alias = $2)
+                )
+            )",
             i64::from(guild_id),
-            i64::from(channel_id),
-            i64::from(message_id)
+            name,
+            target_id.map(|id| id as i64)
         )
         .execute(&self.pool)
         .await
         .map(|done| done.rows_affected())
         .map_err(|err| err.into())
     }
-}
-
-impl TypeMapKey for ReactionRoles {
-    type Value = Arc<Self>;
-}
-
-#[derive(Debug)]
-pub struct JoinRoles {
-    pool: PgPool,
-}
 
-impl JoinRoles {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    #[instrument]
+    pub async fn get_restrictions(&self, command_id: i64) -> Result<Vec<CommandRestriction>, DatabaseError> {
+        let rows = sqlx::query!(
+            "SELECT kind, target_id FROM command_restrictions WHERE command_id = $1",
+            command_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut restrictions = Vec::with_capacity(rows.len());
+        for row in rows {
+            // Shouldn't happen, thanks to the CHECK constraint
+            let kind = RestrictionKind::from_str(&row.kind)
+                .map_err(|_| DatabaseError::InvalidValue("kind".to_string(), row.kind.clone()))?;
+            restrictions.push(CommandRestriction {
+                kind,
+                target_id: row.target_id as u64,
+            });
+        }
+        Ok(restrictions)
     }
 
     #[instrument]
-    pub async fn add_join_role(&self, guild_id: GuildId, role_id: RoleId) -> Result<(), DatabaseError> {
-        sqlx::query!(
-            "INSERT INTO join_roles (guild_id, role_id) VALUES ($1, $2)
-            ON CONFLICT DO NOTHING",
+    pub async fn is_alias(&self, guild_id: GuildId, name: String) -> Result<bool, DatabaseError> {
+        let returned = sqlx::query!(
+            "SELECT alias FROM command_aliases WHERE guild_id = $1 AND alias = $2",
             i64::from(guild_id),
-            i64::from(role_id),
+            name
         )
-        .execute(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
-
-        Ok(())
+        Ok(returned.is_some())
     }
 
     #[instrument]
-    pub async fn get_join_roles(&self, guild_id: GuildId) -> Result<Vec<RoleId>, DatabaseError> {
-        let results = sqlx::query!(
-            "SELECT role_id FROM join_roles
-            WHERE guild_id = $1",
-            i64::from(guild_id),
+    pub async fn get_command_names(&self, guild_id: GuildId) -> Result<Vec<String>, DatabaseError> {
+        let names = sqlx::query!(
+            "SELECT name FROM commands WHERE guild_id = $1",
+            i64::from(guild_id)
         )
         .fetch_all(&self.pool)
         .await?
         .into_iter()
-        .map(|value| RoleId::from(value.role_id as u64))
-        .collect::<Vec<RoleId>>();
-
-        Ok(results)
+        .map(|value| value.name)
+        .collect::<Vec<String>>();
+        Ok(names)
     }
 
     #[instrument]
-    pub async fn delete_join_role(&self, guild_id: GuildId, role_id: RoleId) -> Result<(), DatabaseError> {
+    pub async fn delete_command(&self, guild_id: GuildId, name: String) -> Result<(), DatabaseError> {
         sqlx::query!(
-            "DELETE FROM join_roles WHERE guild_id = $1 AND role_id = $2",
+            "DELETE FROM commands WHERE guild_id = $1 AND name = $2",
             i64::from(guild_id),
-            i64::from(role_id),
+            name
         )
         .execute(&self.pool)
         .await?;
@@ -379,6 +569,1955 @@ impl JoinRoles {
     }
 }
 
-impl TypeMapKey for JoinRoles {
+impl TypeMapKey for CustomCommands {
     type Value = Arc<Self>;
 }
+
+#[derive(Clone, Debug)]
+pub struct Autoresponder {
+    pub autoresponder_id: i64,
+    pub name: String,
+    pub trigger: Trigger,
+    pub content: String,
+    pub channel_ids: Vec<ChannelId>,
+    pub cooldown: i32,
+}
+
+impl Autoresponder {
+    /// Whether it should respond to `content` sent in `channel_id`
+    pub fn matches(&self, channel_id: ChannelId, content: &str) -> bool {
+        (self.channel_ids.is_empty() || self.channel_ids.contains(&channel_id))
+            && self.trigger.matches(content)
+    }
+
+    /// Cooldown of the autoresponder is counted separately in each channel
+    pub fn cooldowns(&self, channel_id: ChannelId) -> Vec<Cooldown> {
+        vec![Cooldown {
+            scope: CooldownScope::Channel,
+            id: channel_id.0,
+            duration: Duration::from_secs(self.cooldown.max(0) as u64),
+        }]
+    }
+}
+
+/// Autoresponders are checked against every message, so they are cached per guild.
+/// Any change to guild's autoresponders drops its cache entry.
+#[derive(Debug)]
+pub struct Autoresponders {
+    pool: PgPool,
+    cache: RwLock<HashMap<GuildId, Arc<Vec<Autoresponder>>>>,
+}
+
+impl Autoresponders {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    #[instrument]
+    pub async fn get_autoresponders(
+        &self,
+        guild_id: GuildId,
+    ) -> Result<Arc<Vec<Autoresponder>>, DatabaseError> {
+        if let Some(autoresponders) = self.cache.read().await.get(&guild_id) {
+            return Ok(autoresponders.clone());
+        }
+        let rows = sqlx::query!(
+            "SELECT autoresponder_id, name, trigger_kind, pattern, content, channel_ids, cooldown
+            FROM autoresponders
+            WHERE guild_id = $1
+            ORDER BY autoresponder_id",
+            i64::from(guild_id)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut autoresponders = Vec::with_capacity(rows.len());
+        for row in rows {
+            // Shouldn't happen, thanks to the CHECK constraint and validation before saving
+            let kind = TriggerKind::from_str(&row.trigger_kind).map_err(|_| {
+                DatabaseError::InvalidValue("trigger_kind".to_string(), row.trigger_kind.clone())
+            })?;
+            let trigger = Trigger::new(kind, &row.pattern)
+                .map_err(|_| DatabaseError::InvalidValue("pattern".to_string(), row.pattern.clone()))?;
+            autoresponders.push(Autoresponder {
+                autoresponder_id: row.autoresponder_id,
+                name: row.name,
+                trigger,
+                content: row.content,
+                channel_ids: row
+                    .channel_ids
+                    .into_iter()
+                    .map(|id| ChannelId(id as u64))
+                    .collect(),
+                cooldown: row.cooldown,
+            });
+        }
+        let autoresponders = Arc::new(autoresponders);
+        self.cache.write().await.insert(guild_id, autoresponders.clone());
+        Ok(autoresponders)
+    }
+
+    /// Creates autoresponder or changes its trigger and content, keeping channels and cooldown
+    #[instrument]
+    pub async fn set_autoresponder(
+        &self,
+        guild_id: GuildId,
+        name: String,
+        kind: TriggerKind,
+        pattern: String,
+        content: String,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO autoresponders (guild_id, name, trigger_kind, pattern, content) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (guild_id, name) DO UPDATE
+            SET trigger_kind = EXCLUDED.trigger_kind, pattern = EXCLUDED.pattern, content = EXCLUDED.content",
+            i64::from(guild_id),
+            name,
+            kind.as_ref(),
+            pattern,
+            content
+        )
+        .execute(&self.pool)
+        .await?;
+        self.cache.write().await.remove(&guild_id);
+        Ok(())
+    }
+
+    /// Limits autoresponder to given channels, empty means all channels.
+    /// Returns number of rows affected - 0 if it doesn't exist.
+    #[instrument]
+    pub async fn set_channels(
+        &self,
+        guild_id: GuildId,
+        name: String,
+        channel_ids: Vec<ChannelId>,
+    ) -> Result<u64, DatabaseError> {
+        let channel_ids = channel_ids.into_iter().map(i64::from).collect::<Vec<i64>>();
+        let result = sqlx::query!(
+            "UPDATE autoresponders SET channel_ids = $3 WHERE guild_id = $1 AND name = $2",
+            i64::from(guild_id),
+            name,
+            &channel_ids
+        )
+        .execute(&self.pool)
+        .await?;
+        self.cache.write().await.remove(&guild_id);
+        Ok(result.rows_affected())
+    }
+
+    /// Returns number of rows affected - 0 if it doesn't exist.
+    #[instrument]
+    pub async fn set_cooldown(
+        &self,
+        guild_id: GuildId,
+        name: String,
+        seconds: i32,
+    ) -> Result<u64, DatabaseError> {
+        let result = sqlx::query!(
+            "UPDATE autoresponders SET cooldown = $3 WHERE guild_id = $1 AND name = $2",
+            i64::from(guild_id),
+            name,
+            seconds
+        )
+        .execute(&self.pool)
+        .await?;
+        self.cache.write().await.remove(&guild_id);
+        Ok(result.rows_affected())
+    }
+
+    #[instrument]
+    pub async fn delete_autoresponder(&self, guild_id: GuildId, name: String) -> Result<u64, DatabaseError> {
+        let result = sqlx::query!(
+            "DELETE FROM autoresponders WHERE guild_id = $1 AND name = $2",
+            i64::from(guild_id),
+            name
+        )
+        .execute(&self.pool)
+        .await?;
+        self.cache.write().await.remove(&guild_id);
+        Ok(result.rows_affected())
+    }
+}
+
+impl TypeMapKey for Autoresponders {
+    type Value = Arc<Self>;
+}
+
+/// How reacting to a reaction role message affects the role
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ReactionRoleMode {
+    /// React once to get the role, react again to lose it
+    Toggle,
+    AddOnly,
+    RemoveOnly,
+    /// Role is given and the reaction stays, so it can't be taken back by reacting
+    Verify,
+    /// Role is given while the reaction stays, removing the reaction removes the role
+    KeepReaction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoleChange {
+    Add,
+    Remove,
+}
+
+impl ReactionRoleMode {
+    /// What should happen to the role after reaction was added or removed by member, who may already have the role
+    pub fn role_change(self, reaction_added: bool, has_role: bool) -> Option<RoleChange> {
+        use ReactionRoleMode::*;
+        match (self, reaction_added, has_role) {
+            (Toggle, true, true) | (RemoveOnly, true, true) | (KeepReaction, false, true) => {
+                Some(RoleChange::Remove)
+            }
+            (Toggle, true, false)
+            | (AddOnly, true, false)
+            | (Verify, true, false)
+            | (KeepReaction, true, false) => Some(RoleChange::Add),
+            _ => None,
+        }
+    }
+
+    /// Whether member's reaction gets removed, so they can react again
+    pub fn removes_reaction(self) -> bool {
+        matches!(self, Self::Toggle | Self::AddOnly | Self::RemoveOnly)
+    }
+
+    fn from_column(value: &str) -> Result<Self, DatabaseError> {
+        // Shouldn't fail, thanks to the CHECK constraint
+        Self::from_str(value).map_err(|_| DatabaseError::InvalidValue("mode".to_string(), value.to_string()))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReactRole {
+    pub react_role_id: i64,
+    pub role_id: RoleId,
+    pub mode: ReactionRoleMode,
+    /// Exclusive group - member can only have one role from it
+    pub react_role_group_id: Option<i64>,
+}
+
+/// Reaction role together with where it is
+#[derive(Clone, Debug)]
+pub struct ReactRoleEntry {
+    pub react_role_id: i64,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub role_id: RoleId,
+    pub reaction: String,
+    pub mode: ReactionRoleMode,
+}
+
+#[derive(Debug)]
+pub struct ReactionRoles {
+    pool: PgPool,
+}
+
+impl ReactionRoles {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument]
+    pub async fn set_react_role(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        role_id: RoleId,
+        reaction: String,
+        mode: ReactionRoleMode,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO react_roles (guild_id, channel_id, message_id, role_id, reaction_emoji, mode)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (guild_id, channel_id, message_id, reaction_emoji)
+            DO UPDATE SET role_id = EXCLUDED.role_id, mode = EXCLUDED.mode",
+            i64::from(guild_id),
+            i64::from(channel_id),
+            i64::from(message_id),
+            i64::from(role_id),
+            reaction,
+            mode.as_ref()
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Saves all reaction roles of a message at once, so either all or none of them exist
+    #[instrument]
+    pub async fn set_react_roles(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        roles: Vec<(RoleId, String)>,
+        mode: ReactionRoleMode,
+    ) -> Result<(), DatabaseError> {
+        let mut transaction = self.pool.begin().await?;
+        for (role_id, reaction) in roles {
+            sqlx::query!(
+                "INSERT INTO react_roles (guild_id, channel_id, message_id, role_id, reaction_emoji, mode)
+                VALUES ($1, $2, $3, $4, $5, $6)",
+                i64::from(guild_id),
+                i64::from(channel_id),
+                i64::from(message_id),
+                i64::from(role_id),
+                reaction,
+                mode.as_ref()
+            )
+            .execute(&mut transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn get_react_role(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reaction: String,
+    ) -> Result<Option<ReactRole>, DatabaseError> {
+        let returned = sqlx::query!(
+            "SELECT react_role_id, role_id, mode, react_role_group_id FROM react_roles
+            WHERE guild_id = $1
+            AND channel_id = $2
+            AND message_id = $3
+            AND reaction_emoji = $4",
+            i64::from(guild_id),
+            i64::from(channel_id),
+            i64::from(message_id),
+            reaction
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        returned
+            .map(|value| {
+                Ok(ReactRole {
+                    react_role_id: value.react_role_id,
+                    role_id: RoleId::from(value.role_id as u64),
+                    mode: ReactionRoleMode::from_column(&value.mode)?,
+                    react_role_group_id: value.react_role_group_id,
+                })
+            })
+            .transpose()
+    }
+
+    #[instrument]
+    pub async fn delete_react_role(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reaction: String,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM react_roles
+            WHERE guild_id = $1
+            AND channel_id = $2
+            AND message_id = $3
+            AND reaction_emoji = $4",
+            i64::from(guild_id),
+            i64::from(channel_id),
+            i64::from(message_id),
+            reaction
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    #[instrument]
+    pub async fn delete_react_roles(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM react_roles
+            WHERE guild_id = $1
+            AND channel_id = $2
+            AND message_id = $3",
+            i64::from(guild_id),
+            i64::from(channel_id),
+            i64::from(message_id)
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    #[instrument]
+    pub async fn delete_channel_react_roles(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM react_roles WHERE guild_id = $1 AND channel_id = $2",
+            i64::from(guild_id),
+            i64::from(channel_id)
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    #[instrument]
+    pub async fn delete_role_react_roles(
+        &self,
+        guild_id: GuildId,
+        role_id: RoleId,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM react_roles WHERE guild_id = $1 AND role_id = $2",
+            i64::from(guild_id),
+            i64::from(role_id)
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    /// All reaction roles in the guild, or only in given channel
+    #[instrument]
+    pub async fn get_react_roles(
+        &self,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+    ) -> Result<Vec<ReactRoleEntry>, DatabaseError> {
+        let rows = sqlx::query!(
+            "SELECT react_role_id, channel_id, message_id, role_id, reaction_emoji, mode
+            FROM react_roles
+            WHERE guild_id = $1
+            AND ($2::bigint IS NULL OR channel_id = $2)
+            ORDER BY channel_id, message_id, react_role_id",
+            i64::from(guild_id),
+            channel_id.map(i64::from)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(ReactRoleEntry {
+                    react_role_id: row.react_role_id,
+                    channel_id: ChannelId(row.channel_id as u64),
+                    message_id: MessageId(row.message_id as u64),
+                    role_id: RoleId(row.role_id as u64),
+                    reaction: row.reaction_emoji,
+                    mode: ReactionRoleMode::from_column(&row.mode)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Puts reaction roles of a message into exclusive group, creating it if needed.
+    /// Returns number of reaction roles added to the group.
+    #[instrument]
+    pub async fn set_group(
+        &self,
+        guild_id: GuildId,
+        name: String,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reactions: Vec<String>,
+    ) -> Result<u64, DatabaseError> {
+        let mut transaction = self.pool.begin().await?;
+        let group = sqlx::query!(
+            "INSERT INTO react_role_groups (guild_id, name) VALUES ($1, $2)
+            ON CONFLICT (guild_id, name) DO UPDATE SET name = EXCLUDED.name
+            RETURNING react_role_group_id",
+            i64::from(guild_id),
+            name
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        let updated = sqlx::query!(
+            "UPDATE react_roles SET react_role_group_id = $5
+            WHERE guild_id = $1
+            AND channel_id = $2
+            AND message_id = $3
+            AND reaction_emoji = ANY($4)",
+            i64::from(guild_id),
+            i64::from(channel_id),
+            i64::from(message_id),
+            &reactions,
+            group.react_role_group_id
+        )
+        .execute(&mut transaction)
+        .await?
+        .rows_affected();
+        transaction.commit().await?;
+        Ok(updated)
+    }
+
+    /// Takes reaction roles of a message out of their groups.
+    /// Returns number of rows affected.
+    #[instrument]
+    pub async fn unset_group(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reactions: Vec<String>,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "UPDATE react_roles SET react_role_group_id = NULL
+            WHERE guild_id = $1
+            AND channel_id = $2
+            AND message_id = $3
+            AND reaction_emoji = ANY($4)",
+            i64::from(guild_id),
+            i64::from(channel_id),
+            i64::from(message_id),
+            &reactions
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    /// Deletes the group, its reaction roles stay but are no longer exclusive
+    #[instrument]
+    pub async fn delete_group(&self, guild_id: GuildId, name: String) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM react_role_groups WHERE guild_id = $1 AND name = $2",
+            i64::from(guild_id),
+            name
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    /// Names of guild's groups with roles in them
+    #[instrument]
+    pub async fn get_groups(&self, guild_id: GuildId) -> Result<Vec<(String, Vec<RoleId>)>, DatabaseError> {
+        let rows = sqlx::query!(
+            r#"SELECT g.name, r.role_id AS "role_id?"
+            FROM react_role_groups g
+            LEFT JOIN react_roles r ON r.react_role_group_id = g.react_role_group_id
+            WHERE g.guild_id = $1
+            ORDER BY g.name, r.react_role_id"#,
+            i64::from(guild_id)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut groups: Vec<(String, Vec<RoleId>)> = Vec::new();
+        for row in rows {
+            if groups.last().map(|(name, _)| name != &row.name).unwrap_or(true) {
+                groups.push((row.name.clone(), Vec::new()));
+            }
+            if let (Some((_, roles)), Some(role_id)) = (groups.last_mut(), row.role_id) {
+                roles.push(RoleId(role_id as u64));
+            }
+        }
+        Ok(groups)
+    }
+
+    /// All reaction roles in the group
+    #[instrument]
+    pub async fn get_group_react_roles(
+        &self,
+        react_role_group_id: i64,
+    ) -> Result<Vec<ReactRoleEntry>, DatabaseError> {
+        let rows = sqlx::query!(
+            "SELECT react_role_id, channel_id, message_id, role_id, reaction_emoji, mode
+            FROM react_roles
+            WHERE react_role_group_id = $1",
+            react_role_group_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter()
+            .map(|row| {
+                Ok(ReactRoleEntry {
+                    react_role_id: row.react_role_id,
+                    channel_id: ChannelId(row.channel_id as u64),
+                    message_id: MessageId(row.message_id as u64),
+                    role_id: RoleId(row.role_id as u64),
+                    reaction: row.reaction_emoji,
+                    mode: ReactionRoleMode::from_column(&row.mode)?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl TypeMapKey for ReactionRoles {
+    type Value = Arc<Self>;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct JoinRole {
+    pub role_id: RoleId,
+    pub target: MemberKind,
+    /// How long after joining the role is given
+    pub delay: Duration,
+    /// Don't give the role until member passes membership screening
+    pub skip_pending: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PendingJoinRole {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    pub role_id: RoleId,
+}
+
+#[derive(Debug)]
+pub struct JoinRoles {
+    pool: PgPool,
+}
+
+impl JoinRoles {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Adds join role, or updates its options if it already exists
+    #[instrument]
+    pub async fn add_join_role(&self, guild_id: GuildId, join_role: JoinRole) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO join_roles (guild_id, role_id, target, delay, skip_pending) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (guild_id, role_id) DO UPDATE
+            SET target = EXCLUDED.target, delay = EXCLUDED.delay, skip_pending = EXCLUDED.skip_pending",
+            i64::from(guild_id),
+            i64::from(join_role.role_id),
+            join_role.target.as_ref(),
+            join_role.delay.as_secs() as i32,
+            join_role.skip_pending
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn get_join_roles(&self, guild_id: GuildId) -> Result<Vec<JoinRole>, DatabaseError> {
+        let rows = sqlx::query!(
+            "SELECT role_id, target, delay, skip_pending FROM join_roles
+            WHERE guild_id = $1",
+            i64::from(guild_id),
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut join_roles = Vec::with_capacity(rows.len());
+        for row in rows {
+            // Shouldn't happen, thanks to the CHECK constraint
+            let target = MemberKind::from_str(&row.target)
+                .map_err(|_| DatabaseError::InvalidValue("target".to_string(), row.target.clone()))?;
+            join_roles.push(JoinRole {
+                role_id: RoleId::from(row.role_id as u64),
+                target,
+                delay: Duration::from_secs(row.delay.max(0) as u64),
+                skip_pending: row.skip_pending,
+            });
+        }
+
+        Ok(join_roles)
+    }
+
+    #[instrument]
+    pub async fn delete_join_role(&self, guild_id: GuildId, role_id: RoleId) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM join_roles WHERE guild_id = $1 AND role_id = $2",
+            i64::from(guild_id),
+            i64::from(role_id),
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Remembers that member's join roles wait for them to pass membership screening
+    #[instrument]
+    pub async fn hold_back_join_roles(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO screening_members (guild_id, user_id) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+            i64::from(guild_id),
+            i64::from(user_id)
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Forgets that member's join roles were held back, returns whether they were
+    #[instrument]
+    pub async fn release_join_roles(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<bool, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM screening_members WHERE guild_id = $1 AND user_id = $2",
+            i64::from(guild_id),
+            i64::from(user_id)
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected() > 0)
+        .map_err(|err| err.into())
+    }
+
+    /// Schedules delayed join role to be given at `grant_at`, already scheduled one isn't pushed back
+    #[instrument]
+    pub async fn add_pending_join_role(
+        &self,
+        pending: PendingJoinRole,
+        grant_at: DateTime<Utc>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO pending_join_roles (guild_id, user_id, role_id, grant_at) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (guild_id, user_id, role_id) DO NOTHING",
+            i64::from(pending.guild_id),
+            i64::from(pending.user_id),
+            i64::from(pending.role_id),
+            grant_at
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Delayed join roles which should be given by `now`, with ids to delete them by once they're given
+    #[instrument]
+    pub async fn get_due_join_roles(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(i64, PendingJoinRole)>, DatabaseError> {
+        let rows = sqlx::query!(
+            "SELECT pending_join_role_id, guild_id, user_id, role_id FROM pending_join_roles
+            WHERE grant_at <= $1
+            ORDER BY grant_at",
+            now
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.pending_join_role_id,
+                    PendingJoinRole {
+                        guild_id: GuildId(row.guild_id as u64),
+                        user_id: UserId(row.user_id as u64),
+                        role_id: RoleId(row.role_id as u64),
+                    },
+                )
+            })
+            .collect())
+    }
+
+    #[instrument]
+    pub async fn delete_pending_join_role(&self, pending_join_role_id: i64) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM pending_join_roles WHERE pending_join_role_id = $1",
+            pending_join_role_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Retries giving the role later, the delay doubles with each failed attempt up to an hour
+    #[instrument]
+    pub async fn postpone_pending_join_role(&self, pending_join_role_id: i64) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "UPDATE pending_join_roles
+            SET grant_at = now() + LEAST(interval '10 seconds' * power(2, failed_attempts), interval '1 hour'),
+                failed_attempts = failed_attempts + 1
+            WHERE pending_join_role_id = $1",
+            pending_join_role_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+impl TypeMapKey for JoinRoles {
+    type Value = Arc<Self>;
+}
+
+#[derive(Debug)]
+pub struct StickyRoles {
+    pool: PgPool,
+}
+
+impl StickyRoles {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[instrument]
+    pub async fn add_sticky_role(&self, guild_id: GuildId, role_id: RoleId) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO sticky_roles (guild_id, role_id) VALUES ($1, $2)
+            ON CONFLICT DO NOTHING",
+            i64::from(guild_id),
+            i64::from(role_id),
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn get_sticky_roles(&self, guild_id: GuildId) -> Result<Vec<RoleId>, DatabaseError> {
+        let results = sqlx::query!(
+            "SELECT role_id FROM sticky_roles WHERE guild_id = $1",
+            i64::from(guild_id),
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|value| RoleId::from(value.role_id as u64))
+        .collect::<Vec<RoleId>>();
+        Ok(results)
+    }
+
+    #[instrument]
+    pub async fn delete_sticky_role(&self, guild_id: GuildId, role_id: RoleId) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM sticky_roles WHERE guild_id = $1 AND role_id = $2",
+            i64::from(guild_id),
+            i64::from(role_id),
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    /// Remembers which of the roles of member who left are sticky, replacing previously stored ones.
+    /// Nothing is stored when none of them are sticky.
+    #[instrument]
+    pub async fn save_member_roles(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_ids: Vec<RoleId>,
+    ) -> Result<(), DatabaseError> {
+        let role_ids = role_ids.into_iter().map(i64::from).collect::<Vec<i64>>();
+        sqlx::query!(
+            "INSERT INTO departed_member_roles (guild_id, user_id, role_ids)
+            SELECT $1, $2, array_agg(role_id) FROM sticky_roles WHERE guild_id = $1 AND role_id = ANY($3)
+            HAVING count(*) > 0
+            ON CONFLICT (guild_id, user_id) DO UPDATE SET role_ids = EXCLUDED.role_ids, left_at = now()",
+            i64::from(guild_id),
+            i64::from(user_id),
+            &role_ids
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Stored roles of member which are still sticky, they're kept until `forget_member_roles`
+    #[instrument]
+    pub async fn get_member_sticky_roles(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<Vec<RoleId>, DatabaseError> {
+        let stored = sqlx::query!(
+            "SELECT role_ids FROM departed_member_roles WHERE guild_id = $1 AND user_id = $2",
+            i64::from(guild_id),
+            i64::from(user_id)
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let stored = match stored {
+            Some(row) => row.role_ids,
+            None => return Ok(Vec::new()),
+        };
+        let sticky = self.get_sticky_roles(guild_id).await?;
+        Ok(stored
+            .into_iter()
+            .map(|role_id| RoleId(role_id as u64))
+            .filter(|role_id| sticky.contains(role_id))
+            .collect())
+    }
+
+    /// Takes role out of stored roles of member who left, so they won't get it back on rejoin
+    #[instrument]
+    pub async fn forget_member_role(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        role_id: RoleId,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "UPDATE departed_member_roles SET role_ids = array_remove(role_ids, $3)
+            WHERE guild_id = $1 AND user_id = $2",
+            i64::from(guild_id),
+            i64::from(user_id),
+            i64::from(role_id)
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Forgets stored roles of member, so they won't get them back on rejoin
+    #[instrument]
+    pub async fn forget_member_roles(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM departed_member_roles WHERE guild_id = $1 AND user_id = $2",
+            i64::from(guild_id),
+            i64::from(user_id)
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+}
+
+impl TypeMapKey for StickyRoles {
+    type Value = Arc<Self>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum WelcomeMessageKind {
+    Welcome,
+    Goodbye,
+}
+
+#[derive(Clone, Debug)]
+pub struct WelcomeMessage {
+    pub kind: WelcomeMessageKind,
+    /// Channel the message is sent to, direct message to the member if empty
+    pub channel_id: Option<ChannelId>,
+    pub content: String,
+}
+
+#[derive(Debug)]
+pub struct WelcomeMessages {
+    pool: PgPool,
+}
+
+impl WelcomeMessages {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Sets welcome or goodbye message, replacing the previous one
+    #[instrument]
+    pub async fn set_welcome_message(
+        &self,
+        guild_id: GuildId,
+        welcome_message: WelcomeMessage,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO welcome_messages (guild_id, kind, channel_id, content) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (guild_id, kind) DO UPDATE SET channel_id = EXCLUDED.channel_id, content = EXCLUDED.content",
+            i64::from(guild_id),
+            welcome_message.kind.as_ref(),
+            welcome_message.channel_id.map(i64::from),
+            welcome_message.content
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn get_welcome_message(
+        &self,
+        guild_id: GuildId,
+        kind: WelcomeMessageKind,
+    ) -> Result<Option<WelcomeMessage>, DatabaseError> {
+        let row = sqlx::query!(
+            "SELECT channel_id, content FROM welcome_messages WHERE guild_id = $1 AND kind = $2",
+            i64::from(guild_id),
+            kind.as_ref()
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| WelcomeMessage {
+            kind,
+            channel_id: row.channel_id.map(|channel_id| ChannelId(channel_id as u64)),
+            content: row.content,
+        }))
+    }
+
+    #[instrument]
+    pub async fn delete_welcome_message(
+        &self,
+        guild_id: GuildId,
+        kind: WelcomeMessageKind,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM welcome_messages WHERE guild_id = $1 AND kind = $2",
+            i64::from(guild_id),
+            kind.as_ref()
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    /// Disables messages sent to deleted channel
+    #[instrument]
+    pub async fn delete_channel_welcome_messages(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM welcome_messages WHERE guild_id = $1 AND channel_id = $2",
+            i64::from(guild_id),
+            i64::from(channel_id)
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+}
+
+impl TypeMapKey for WelcomeMessages {
+    type Value = Arc<Self>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Cancelled,
+    Failed,
+    Finished,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct JobProgress {
+    /// Members are processed in order of their ids, so the job can continue after this one
+    pub last_user_id: UserId,
+    pub processed: i32,
+    pub changed: i32,
+    pub failed: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct BulkRoleJob {
+    pub bulk_role_job_id: i64,
+    pub guild_id: GuildId,
+    /// Channel with the progress message
+    pub channel_id: ChannelId,
+    pub progress_message_id: Option<MessageId>,
+    pub created_by: UserId,
+    pub created_at: DateTime<Utc>,
+    pub role_id: RoleId,
+    pub mode: BulkRoleMode,
+    pub filter: MemberFilter<RoleId>,
+    pub status: JobStatus,
+    /// Why the job failed, if it did
+    pub error: Option<String>,
+    pub progress: JobProgress,
+}
+
+struct BulkRoleJobRow {
+    bulk_role_job_id: i64,
+    guild_id: i64,
+    channel_id: i64,
+    progress_message_id: Option<i64>,
+    created_by: i64,
+    created_at: DateTime<Utc>,
+    role_id: i64,
+    mode: String,
+    all_role_ids: Vec<i64>,
+    any_role_ids: Vec<i64>,
+    none_role_ids: Vec<i64>,
+    target: String,
+    joined_before: Option<DateTime<Utc>>,
+    joined_after: Option<DateTime<Utc>>,
+    status: String,
+    error: Option<String>,
+    last_user_id: i64,
+    processed: i32,
+    changed: i32,
+    failed: i32,
+}
+
+impl BulkRoleJobRow {
+    fn into_job(self) -> Result<BulkRoleJob, DatabaseError> {
+        // Shouldn't fail, thanks to the CHECK constraints
+        let mode = BulkRoleMode::from_str(&self.mode)
+            .map_err(|_| DatabaseError::InvalidValue("mode".to_string(), self.mode.clone()))?;
+        let target = MemberKind::from_str(&self.target)
+            .map_err(|_| DatabaseError::InvalidValue("target".to_string(), self.target.clone()))?;
+        let status = JobStatus::from_str(&self.status)
+            .map_err(|_| DatabaseError::InvalidValue("status".to_string(), self.status.clone()))?;
+        let to_role_ids = |role_ids: Vec<i64>| {
+            role_ids
+                .into_iter()
+                .map(|role_id| RoleId(role_id as u64))
+                .collect()
+        };
+        Ok(BulkRoleJob {
+            bulk_role_job_id: self.bulk_role_job_id,
+            guild_id: GuildId(self.guild_id as u64),
+            channel_id: ChannelId(self.channel_id as u64),
+            progress_message_id: self
+                .progress_message_id
+                .map(|message_id| MessageId(message_id as u64)),
+            created_by: UserId(self.created_by as u64),
+            created_at: self.created_at,
+            role_id: RoleId(self.role_id as u64),
+            mode,
+            filter: MemberFilter {
+                all: to_role_ids(self.all_role_ids),
+                any: to_role_ids(self.any_role_ids),
+                none: to_role_ids(self.none_role_ids),
+                target,
+                joined_before: self.joined_before,
+                joined_after: self.joined_after,
+            },
+            status,
+            error: self.error,
+            progress: JobProgress {
+                last_user_id: UserId(self.last_user_id as u64),
+                processed: self.processed,
+                changed: self.changed,
+                failed: self.failed,
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct BulkRoleJobs {
+    pool: PgPool,
+    /// Jobs with a worker in this process, so a job isn't processed twice at once
+    active: Mutex<HashSet<i64>>,
+}
+
+impl BulkRoleJobs {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            active: Mutex::new(HashSet::new()),
+        }
+    }
+
+    #[instrument]
+    pub async fn create_job(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        created_by: UserId,
+        role_id: RoleId,
+        mode: BulkRoleMode,
+        filter: &MemberFilter<RoleId>,
+    ) -> Result<BulkRoleJob, DatabaseError> {
+        let to_ids = |role_ids: &[RoleId]| {
+            role_ids
+                .iter()
+                .map(|role_id| i64::from(*role_id))
+                .collect::<Vec<i64>>()
+        };
+        sqlx::query_as!(
+            BulkRoleJobRow,
+            "INSERT INTO bulk_role_jobs
+            (guild_id, channel_id, created_by, role_id, mode, all_role_ids, any_role_ids, none_role_ids,
+            target, joined_before, joined_after)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING *",
+            i64::from(guild_id),
+            i64::from(channel_id),
+            i64::from(created_by),
+            i64::from(role_id),
+            mode.as_ref(),
+            &to_ids(&filter.all),
+            &to_ids(&filter.any),
+            &to_ids(&filter.none),
+            filter.target.as_ref(),
+            filter.joined_before,
+            filter.joined_after
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .into_job()
+    }
+
+    #[instrument]
+    pub async fn set_progress_message(
+        &self,
+        job_id: i64,
+        message_id: MessageId,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "UPDATE bulk_role_jobs SET progress_message_id = $2 WHERE bulk_role_job_id = $1",
+            job_id,
+            i64::from(message_id)
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn get_job(
+        &self,
+        guild_id: GuildId,
+        job_id: i64,
+    ) -> Result<Option<BulkRoleJob>, DatabaseError> {
+        sqlx::query_as!(
+            BulkRoleJobRow,
+            "SELECT * FROM bulk_role_jobs WHERE guild_id = $1 AND bulk_role_job_id = $2",
+            i64::from(guild_id),
+            job_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(BulkRoleJobRow::into_job)
+        .transpose()
+    }
+
+    /// Most recent jobs of guild, newest first
+    #[instrument]
+    pub async fn get_jobs(&self, guild_id: GuildId, limit: i64) -> Result<Vec<BulkRoleJob>, DatabaseError> {
+        sqlx::query_as!(
+            BulkRoleJobRow,
+            "SELECT * FROM bulk_role_jobs WHERE guild_id = $1 ORDER BY bulk_role_job_id DESC LIMIT $2",
+            i64::from(guild_id),
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(BulkRoleJobRow::into_job)
+        .collect()
+    }
+
+    /// Jobs of all guilds which should be running, e.g. ones interrupted by restart
+    #[instrument]
+    pub async fn get_running_jobs(&self) -> Result<Vec<BulkRoleJob>, DatabaseError> {
+        sqlx::query_as!(
+            BulkRoleJobRow,
+            "SELECT * FROM bulk_role_jobs WHERE status = 'running' ORDER BY bulk_role_job_id"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(BulkRoleJobRow::into_job)
+        .collect()
+    }
+
+    /// Saves progress and returns current status, which might have been changed by `job cancel`
+    #[instrument]
+    pub async fn save_progress(
+        &self,
+        job_id: i64,
+        progress: JobProgress,
+    ) -> Result<JobStatus, DatabaseError> {
+        let row = sqlx::query!(
+            "UPDATE bulk_role_jobs SET last_user_id = $2, processed = $3, changed = $4, failed = $5
+            WHERE bulk_role_job_id = $1
+            RETURNING status",
+            job_id,
+            i64::from(progress.last_user_id),
+            progress.processed,
+            progress.changed,
+            progress.failed
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        JobStatus::from_str(&row.status)
+            .map_err(|_| DatabaseError::InvalidValue("status".to_string(), row.status))
+    }
+
+    /// Changes status of job, but only if it currently has one of `from` statuses.
+    /// Returns whether it was changed.
+    #[instrument]
+    pub async fn set_status(
+        &self,
+        job_id: i64,
+        status: JobStatus,
+        from: &[JobStatus],
+        error: Option<String>,
+    ) -> Result<bool, DatabaseError> {
+        let from = from
+            .iter()
+            .map(|status| status.as_ref().to_string())
+            .collect::<Vec<String>>();
+        sqlx::query!(
+            "UPDATE bulk_role_jobs SET status = $2, error = $4
+            WHERE bulk_role_job_id = $1 AND status = ANY($3)",
+            job_id,
+            status.as_ref(),
+            &from,
+            error
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected() > 0)
+        .map_err(|err| err.into())
+    }
+
+    #[instrument]
+    pub async fn add_job_error(
+        &self,
+        job_id: i64,
+        user_id: UserId,
+        error: String,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO bulk_role_job_errors (bulk_role_job_id, user_id, error) VALUES ($1, $2, $3)",
+            job_id,
+            i64::from(user_id),
+            error
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Members whose roles the job failed to change, with the reason
+    #[instrument]
+    pub async fn get_job_errors(
+        &self,
+        job_id: i64,
+        limit: i64,
+    ) -> Result<Vec<(UserId, String)>, DatabaseError> {
+        let rows = sqlx::query!(
+            "SELECT user_id, error FROM bulk_role_job_errors WHERE bulk_role_job_id = $1
+            ORDER BY bulk_role_job_error_id LIMIT $2",
+            job_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (UserId(row.user_id as u64), row.error))
+            .collect())
+    }
+
+    /// Marks job as processed by this process, returns false if it already is
+    pub async fn claim(&self, job_id: i64) -> bool {
+        self.active.lock().await.insert(job_id)
+    }
+
+    pub async fn release(&self, job_id: i64) {
+        self.active.lock().await.remove(&job_id);
+    }
+
+    pub async fn is_active(&self, job_id: i64) -> bool {
+        self.active.lock().await.contains(&job_id)
+    }
+}
+
+impl TypeMapKey for BulkRoleJobs {
+    type Value = Arc<Self>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum InfractionKind {
+    Warn,
+    Kick,
+    Ban,
+    Unban,
+    Softban,
+    Mute,
+    Unmute,
+}
+
+#[derive(Clone, Debug)]
+pub struct Infraction {
+    /// Number of the infraction in its guild
+    pub case_number: i32,
+    pub kind: InfractionKind,
+    pub moderator_id: UserId,
+    pub target_id: UserId,
+    pub reason: Option<String>,
+    /// How long the punishment lasts, if it's temporary
+    pub duration: Option<Duration>,
+    pub created_at: DateTime<Utc>,
+}
+
+struct InfractionRow {
+    case_number: i32,
+    kind: String,
+    moderator_id: i64,
+    target_id: i64,
+    reason: Option<String>,
+    duration: Option<i32>,
+    created_at: DateTime<Utc>,
+}
+
+impl InfractionRow {
+    fn into_infraction(self) -> Result<Infraction, DatabaseError> {
+        // Shouldn't fail, thanks to the CHECK constraint
+        let kind = InfractionKind::from_str(&self.kind)
+            .map_err(|_| DatabaseError::InvalidValue("kind".to_string(), self.kind.clone()))?;
+        Ok(Infraction {
+            case_number: self.case_number,
+            kind,
+            moderator_id: UserId(self.moderator_id as u64),
+            target_id: UserId(self.target_id as u64),
+            reason: self.reason,
+            duration: self
+                .duration
+                .map(|seconds| Duration::from_secs(seconds.max(0) as u64)),
+            created_at: self.created_at,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum PunishmentKind {
+    Mute,
+    Ban,
+    /// Temporarily granted role
+    Role,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TimedPunishment {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    pub kind: PunishmentKind,
+    /// Role taken away on expiry, for mutes and role grants
+    pub role_id: Option<RoleId>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct Infractions {
+    pool: PgPool,
+}
+
+impl Infractions {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Records infraction as the next case of guild
+    #[instrument]
+    pub async fn add_infraction(
+        &self,
+        guild_id: GuildId,
+        kind: InfractionKind,
+        moderator_id: UserId,
+        target_id: UserId,
+        reason: Option<String>,
+        duration: Option<Duration>,
+    ) -> Result<Infraction, DatabaseError> {
+        let mut transaction = self.pool.begin().await?;
+        // Counter row stays locked until commit, so concurrent infractions wait for their number
+        let case_number = sqlx::query!(
+            r#"INSERT INTO moderation_settings (guild_id, next_case_number) VALUES ($1, 2)
+            ON CONFLICT (guild_id) DO UPDATE SET next_case_number = moderation_settings.next_case_number + 1
+            RETURNING next_case_number - 1 AS "case_number!""#,
+            i64::from(guild_id)
+        )
+        .fetch_one(&mut transaction)
+        .await?
+        .case_number;
+        let infraction = sqlx::query_as!(
+            InfractionRow,
+            "INSERT INTO infractions (guild_id, case_number, kind, moderator_id, target_id, reason, duration)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING case_number, kind, moderator_id, target_id, reason, duration, created_at",
+            i64::from(guild_id),
+            case_number,
+            kind.as_ref(),
+            i64::from(moderator_id),
+            i64::from(target_id),
+            reason,
+            duration.map(|duration| duration.as_secs() as i32)
+        )
+        .fetch_one(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+        infraction.into_infraction()
+    }
+
+    #[instrument]
+    pub async fn get_infraction(
+        &self,
+        guild_id: GuildId,
+        case_number: i32,
+    ) -> Result<Option<Infraction>, DatabaseError> {
+        sqlx::query_as!(
+            InfractionRow,
+            "SELECT case_number, kind, moderator_id, target_id, reason, duration, created_at FROM infractions
+            WHERE guild_id = $1 AND case_number = $2",
+            i64::from(guild_id),
+            case_number
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(InfractionRow::into_infraction)
+        .transpose()
+    }
+
+    /// Infractions of user, oldest first
+    #[instrument]
+    pub async fn get_user_infractions(
+        &self,
+        guild_id: GuildId,
+        target_id: UserId,
+    ) -> Result<Vec<Infraction>, DatabaseError> {
+        sqlx::query_as!(
+            InfractionRow,
+            "SELECT case_number, kind, moderator_id, target_id, reason, duration, created_at FROM infractions
+            WHERE guild_id = $1 AND target_id = $2
+            ORDER BY case_number",
+            i64::from(guild_id),
+            i64::from(target_id)
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(InfractionRow::into_infraction)
+        .collect()
+    }
+
+    #[instrument]
+    pub async fn set_reason(
+        &self,
+        guild_id: GuildId,
+        case_number: i32,
+        reason: String,
+    ) -> Result<Option<Infraction>, DatabaseError> {
+        sqlx::query_as!(
+            InfractionRow,
+            "UPDATE infractions SET reason = $3 WHERE guild_id = $1 AND case_number = $2
+            RETURNING case_number, kind, moderator_id, target_id, reason, duration, created_at",
+            i64::from(guild_id),
+            case_number,
+            reason
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .map(InfractionRow::into_infraction)
+        .transpose()
+    }
+
+    #[instrument]
+    pub async fn get_mute_role(&self, guild_id: GuildId) -> Result<Option<RoleId>, DatabaseError> {
+        let row = sqlx::query!(
+            "SELECT mute_role_id FROM moderation_settings WHERE guild_id = $1",
+            i64::from(guild_id)
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row
+            .and_then(|row| row.mute_role_id)
+            .map(|role_id| RoleId(role_id as u64)))
+    }
+
+    #[instrument]
+    pub async fn set_mute_role(&self, guild_id: GuildId, role_id: RoleId) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO moderation_settings (guild_id, mute_role_id) VALUES ($1, $2)
+            ON CONFLICT (guild_id) DO UPDATE SET mute_role_id = EXCLUDED.mute_role_id",
+            i64::from(guild_id),
+            i64::from(role_id)
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Unsets mute role, if it's the given role
+    #[instrument]
+    pub async fn delete_mute_role(&self, guild_id: GuildId, role_id: RoleId) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "UPDATE moderation_settings SET mute_role_id = NULL WHERE guild_id = $1 AND mute_role_id = $2",
+            i64::from(guild_id),
+            i64::from(role_id)
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    /// Schedules lifting of punishment, replacing the previous expiry of the same punishment
+    #[instrument]
+    pub async fn set_expiry(&self, punishment: TimedPunishment) -> Result<(), DatabaseError> {
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM timed_punishments
+            WHERE guild_id = $1 AND user_id = $2 AND kind = $3 AND role_id IS NOT DISTINCT FROM $4",
+            i64::from(punishment.guild_id),
+            i64::from(punishment.user_id),
+            punishment.kind.as_ref(),
+            punishment.role_id.map(i64::from)
+        )
+        .execute(&mut transaction)
+        .await?;
+        sqlx::query!(
+            "INSERT INTO timed_punishments (guild_id, user_id, kind, role_id, expires_at)
+            VALUES ($1, $2, $3, $4, $5)",
+            i64::from(punishment.guild_id),
+            i64::from(punishment.user_id),
+            punishment.kind.as_ref(),
+            punishment.role_id.map(i64::from),
+            punishment.expires_at
+        )
+        .execute(&mut transaction)
+        .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    /// Cancels expiry of punishment, e.g. when it's lifted by hand.
+    /// No `role_id` matches expiries of any role.
+    #[instrument]
+    pub async fn delete_expiry(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        kind: PunishmentKind,
+        role_id: Option<RoleId>,
+    ) -> Result<u64, DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM timed_punishments
+            WHERE guild_id = $1 AND user_id = $2 AND kind = $3 AND ($4::bigint IS NULL OR role_id = $4)",
+            i64::from(guild_id),
+            i64::from(user_id),
+            kind.as_ref(),
+            role_id.map(i64::from)
+        )
+        .execute(&self.pool)
+        .await
+        .map(|done| done.rows_affected())
+        .map_err(|err| err.into())
+    }
+
+    /// Punishments which should have been lifted by `now`, including ones which expired while the bot was down
+    #[instrument]
+    pub async fn get_expired(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(i64, TimedPunishment)>, DatabaseError> {
+        let rows = sqlx::query!(
+            "SELECT timed_punishment_id, guild_id, user_id, kind, role_id, expires_at FROM timed_punishments
+            WHERE expires_at <= $1
+            ORDER BY expires_at",
+            now
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut expired = Vec::with_capacity(rows.len());
+        for row in rows {
+            // Shouldn't happen, thanks to the CHECK constraint
+            let kind = PunishmentKind::from_str(&row.kind)
+                .map_err(|_| DatabaseError::InvalidValue("kind".to_string(), row.kind.clone()))?;
+            expired.push((
+                row.timed_punishment_id,
+                TimedPunishment {
+                    guild_id: GuildId(row.guild_id as u64),
+                    user_id: UserId(row.user_id as u64),
+                    kind,
+                    role_id: row.role_id.map(|role_id| RoleId(role_id as u64)),
+                    expires_at: row.expires_at,
+                },
+            ));
+        }
+        Ok(expired)
+    }
+
+    /// Retries lifting the punishment later, the delay doubles with each failed attempt up to an hour
+    #[instrument]
+    pub async fn postpone_expiry(&self, timed_punishment_id: i64) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "UPDATE timed_punishments
+            SET expires_at = now() + LEAST(interval '10 seconds' * power(2, failed_attempts), interval '1 hour'),
+                failed_attempts = failed_attempts + 1
+            WHERE timed_punishment_id = $1",
+            timed_punishment_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn delete_expiry_by_id(&self, timed_punishment_id: i64) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "DELETE FROM timed_punishments WHERE timed_punishment_id = $1",
+            timed_punishment_id
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+impl TypeMapKey for Infractions {
+    type Value = Arc<Self>;
+}
+
+#[derive(Debug)]
+pub struct ModLogChannels {
+    pool: PgPool,
+    /// Looked up for every logged event, so it's cached, including guilds without the channel
+    channels: RwLock<HashMap<GuildId, Option<ChannelId>>>,
+}
+
+impl ModLogChannels {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            channels: RwLock::new(HashMap::new()),
+        }
+    }
+
+    #[instrument]
+    pub async fn get_mod_log_channel(&self, guild_id: GuildId) -> Result<Option<ChannelId>, DatabaseError> {
+        if let Some(channel_id) = self.channels.read().await.get(&guild_id) {
+            return Ok(*channel_id);
+        }
+        let row = sqlx::query!(
+            "SELECT mod_log_channel_id FROM moderation_settings WHERE guild_id = $1",
+            i64::from(guild_id)
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let channel_id = row
+            .and_then(|row| row.mod_log_channel_id)
+            .map(|channel_id| ChannelId(channel_id as u64));
+        self.channels.write().await.insert(guild_id, channel_id);
+        Ok(channel_id)
+    }
+
+    /// Sets mod-log channel, or disables mod-log if it's `None`
+    #[instrument]
+    pub async fn set_mod_log_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO moderation_settings (guild_id, mod_log_channel_id) VALUES ($1, $2)
+            ON CONFLICT (guild_id) DO UPDATE SET mod_log_channel_id = EXCLUDED.mod_log_channel_id",
+            i64::from(guild_id),
+            channel_id.map(i64::from)
+        )
+        .execute(&self.pool)
+        .await?;
+        self.channels.write().await.insert(guild_id, channel_id);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum MessageLogIgnoreKind {
+    Channel,
+    Role,
+}
+
+/// Where edited and deleted messages are logged, and which ones aren't
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MessageLogConfig {
+    pub channel_id: Option<ChannelId>,
+    pub ignored_channels: HashSet<ChannelId>,
+    /// Messages of members with any of these roles aren't logged
+    pub ignored_roles: HashSet<RoleId>,
+}
+
+#[derive(Debug)]
+pub struct MessageLogConfigs {
+    pool: PgPool,
+    /// Needed for every message, so it's cached, including guilds without message log
+    configs: RwLock<HashMap<GuildId, MessageLogConfig>>,
+}
+
+impl MessageLogConfigs {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            configs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    #[instrument]
+    pub async fn get_config(&self, guild_id: GuildId) -> Result<MessageLogConfig, DatabaseError> {
+        if let Some(config) = self.configs.read().await.get(&guild_id) {
+            return Ok(config.clone());
+        }
+        let channel_id = sqlx::query!(
+            "SELECT message_log_channel_id FROM moderation_settings WHERE guild_id = $1",
+            i64::from(guild_id)
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .and_then(|row| row.message_log_channel_id)
+        .map(|channel_id| ChannelId(channel_id as u64));
+        let ignores = sqlx::query!(
+            "SELECT kind, target_id FROM message_log_ignores WHERE guild_id = $1",
+            i64::from(guild_id)
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut config = MessageLogConfig {
+            channel_id,
+            ..Default::default()
+        };
+        for ignore in ignores {
+            let kind = MessageLogIgnoreKind::from_str(&ignore.kind)
+                .map_err(|_| DatabaseError::InvalidValue("kind".to_string(), ignore.kind.clone()))?;
+            match kind {
+                MessageLogIgnoreKind::Channel => {
+                    config.ignored_channels.insert(ChannelId(ignore.target_id as u64));
+                }
+                MessageLogIgnoreKind::Role => {
+                    config.ignored_roles.insert(RoleId(ignore.target_id as u64));
+                }
+            }
+        }
+        self.configs.write().await.insert(guild_id, config.clone());
+        Ok(config)
+    }
+
+    /// Sets message log channel, or disables message log if it's `None`
+    #[instrument]
+    pub async fn set_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+    ) -> Result<(), DatabaseError> {
+        sqlx::query!(
+            "INSERT INTO moderation_settings (guild_id, message_log_channel_id) VALUES ($1, $2)
+            ON CONFLICT (guild_id) DO UPDATE SET message_log_channel_id = EXCLUDED.message_log_channel_id",
+            i64::from(guild_id),
+            channel_id.map(i64::from)
+        )
+        .execute(&self.pool)
+        .await?;
+        self.configs.write().await.remove(&guild_id);
+        Ok(())
+    }
+
+    #[instrument]
+    pub async fn add_ignore(
+        &self,
+        guild_id: GuildId,
+        kind: MessageLogIgnoreKind,
+        target_id: u64,
+    ) -> Result<u64, DatabaseError> {
+        let added = sqlx::query!(
+            "INSERT INTO message_log_ignores (guild_id, kind, target_id) VALUES ($1, $2, $3)
+            ON CONFLICT (guild_id, target_id) DO NOTHING",
+            i64::from(guild_id),
+            kind.as_ref(),
+            target_id as i64
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        self.configs.write().await.remove(&guild_id);
+        Ok(added)
+    }
+
+    /// Stops ignoring channel or role, also used when it gets deleted
+    #[instrument]
+    pub async fn delete_ignore(&self, guild_id: GuildId, target_id: u64) -> Result<u64, DatabaseError> {
+        let deleted = sqlx::query!(
+            "DELETE FROM message_log_ignores WHERE guild_id = $1 AND target_id = $2",
+            i64::from(guild_id),
+            target_id as i64
+        )
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        self.configs.write().await.remove(&guild_id);
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest(
+        mode,
+        reaction_added,
+        has_role,
+        expected,
+        case::toggle_add(ReactionRoleMode::Toggle, true, false, Some(RoleChange::Add)),
+        case::toggle_again(ReactionRoleMode::Toggle, true, true, Some(RoleChange::Remove)),
+        case::toggle_unreact(ReactionRoleMode::Toggle, false, false, None),
+        case::toggle_unreact_with_role(ReactionRoleMode::Toggle, false, true, None),
+        case::add_only_add(ReactionRoleMode::AddOnly, true, false, Some(RoleChange::Add)),
+        case::add_only_has_role(ReactionRoleMode::AddOnly, true, true, None),
+        case::add_only_unreact(ReactionRoleMode::AddOnly, false, false, None),
+        case::add_only_unreact_with_role(ReactionRoleMode::AddOnly, false, true, None),
+        case::remove_only_without_role(ReactionRoleMode::RemoveOnly, true, false, None),
+        case::remove_only_remove(ReactionRoleMode::RemoveOnly, true, true, Some(RoleChange::Remove)),
+        case::remove_only_unreact(ReactionRoleMode::RemoveOnly, false, false, None),
+        case::remove_only_unreact_with_role(ReactionRoleMode::RemoveOnly, false, true, None),
+        case::verify_add(ReactionRoleMode::Verify, true, false, Some(RoleChange::Add)),
+        case::verify_has_role(ReactionRoleMode::Verify, true, true, None),
+        case::verify_unreact(ReactionRoleMode::Verify, false, false, None),
+        case::verify_unreact_with_role(ReactionRoleMode::Verify, false, true, None),
+        case::keep_reaction_add(ReactionRoleMode::KeepReaction, true, false, Some(RoleChange::Add)),
+        case::keep_reaction_has_role(ReactionRoleMode::KeepReaction, true, true, None),
+        case::keep_reaction_unreact_without_role(ReactionRoleMode::KeepReaction, false, false, None),
+        case::keep_reaction_remove(ReactionRoleMode::KeepReaction, false, true, Some(RoleChange::Remove))
+    )]
+    fn reaction_role_change(
+        mode: ReactionRoleMode,
+        reaction_added: bool,
+        has_role: bool,
+        expected: Option<RoleChange>,
+    ) {
+        assert_eq!(expected, mode.role_change(reaction_added, has_role));
+    }
+
+    #[rstest(
+        mode,
+        expected,
+        case::toggle(ReactionRoleMode::Toggle, true),
+        case::add_only(ReactionRoleMode::AddOnly, true),
+        case::remove_only(ReactionRoleMode::RemoveOnly, true),
+        case::verify(ReactionRoleMode::Verify, false),
+        case::keep_reaction(ReactionRoleMode::KeepReaction, false)
+    )]
+    fn reaction_removal(mode: ReactionRoleMode, expected: bool) {
+        assert_eq!(expected, mode.removes_reaction());
+    }
+}
@@ -1,8 +1,41 @@
 use crate::{
-    commands::{admin::reaction_role_handler, config::join_role_handler},
-    database::queries::{CustomCommands, GuildInfoTable},
-    utils::misc::send_rich_serialized_message,
+    commands::{
+        admin::reaction_role_handler,
+        config::{
+            join_role_handler,
+            join_role_removal_handler,
+            join_role_screening_handler,
+            sticky_role_handler,
+            sticky_role_removal_handler,
+            welcome_message_handler,
+        },
+        message_log::{message_delete_handler, message_log_cache_handler, message_update_handler},
+    },
+    database::queries::{
+        Autoresponders,
+        CommandResponse,
+        CustomCommands,
+        GuildInfoTable,
+        Infractions,
+        JoinRoles,
+        ReactionRoles,
+        StickyRoles,
+        WelcomeMessageKind,
+        WelcomeMessages,
+    },
+    structures::{
+        cooldowns::{CommandCooldowns, CooldownSubject},
+        message_log::MessageLog,
+        mod_log::{log_event, ModLog, ModLogEvent},
+    },
+    unwrap_or_return,
+    utils::{
+        misc::{custom_command_args, is_not_found, send_rendered, send_unrendered, template_variables},
+        permissions::is_command_allowed,
+    },
 };
+use anyhow::Context as AnyContext;
+use rand::seq::SliceRandom;
 use serenity::{
     async_trait,
     framework::standard::{
@@ -15,32 +48,34 @@ use serenity::{
         HelpOptions,
     },
     model::{
-        channel::{Message, Reaction},
-        event::ResumedEvent,
+        channel::{GuildChannel, Message, Reaction},
+        event::{MessageUpdateEvent, ResumedEvent},
         gateway::Ready,
-        guild::{Guild, GuildUnavailable, Member},
-        id::{GuildId, UserId},
+        guild::{Guild, GuildUnavailable, Member, Role},
+        id::{ChannelId, GuildId, MessageId, RoleId, UserId},
+        user::User,
     },
     prelude::*,
 };
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
 use tracing::{debug, error, info, instrument};
 
+const COOLDOWN_MESSAGE_LIFETIME: Duration = Duration::from_secs(5);
+/// Pause between fetches of reaction role messages after start, checking if they still exist
+const REACTION_ROLE_MESSAGE_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
 #[derive(Debug)]
 pub struct Handler;
 
 #[async_trait]
 impl EventHandler for Handler {
-    /*
     async fn message(&self, ctx: Context, msg: Message) {
-       if !msg.author.bot {
-           info!("Reacting to: {:?}", msg.content);
-           match msg.react(&ctx, '👌').await {
-               Ok(reaction) => info!("Successfully posted reaction {:?}", reaction.emoji.as_data()),
-               Err(e) => error!("Emoji error {:?}", e)
-           }
-       }
-    }*/
+        message_log_cache_handler(&ctx, &msg).await;
+        autoresponder_handler(&ctx, &msg).await;
+    }
 
     async fn ready(&self, _ctx: Context, ready: Ready) {
         info!("Connected as {}", ready.user.name);
@@ -76,6 +111,66 @@ impl EventHandler for Handler {
                 Err(e) => error!("Issue while deleting guild: {}", e),
             }
         }
+        let mut messages = HashSet::new();
+        for guild_id in guilds {
+            reconcile_deleted_entities(&ctx, guild_id, &mut messages).await;
+        }
+        tokio::spawn(check_reaction_role_messages(ctx, messages));
+    }
+
+    #[instrument(skip(ctx))]
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Some(guild_id) = guild_id {
+            delete_message_react_roles(&ctx, guild_id, channel_id, &[deleted_message_id]).await;
+            message_delete_handler(&ctx, guild_id, channel_id, &[deleted_message_id]).await;
+        }
+    }
+
+    #[instrument(skip(ctx))]
+    async fn message_delete_bulk(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        if let Some(guild_id) = guild_id {
+            delete_message_react_roles(&ctx, guild_id, channel_id, &multiple_deleted_messages_ids).await;
+            message_delete_handler(&ctx, guild_id, channel_id, &multiple_deleted_messages_ids).await;
+        }
+    }
+
+    #[instrument(skip(ctx))]
+    async fn message_update(
+        &self,
+        ctx: Context,
+        old_if_available: Option<Message>,
+        new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        message_update_handler(&ctx, old_if_available, new, &event).await;
+    }
+
+    #[instrument(skip(ctx))]
+    async fn channel_delete(&self, ctx: Context, channel: &GuildChannel) {
+        delete_channel_data(&ctx, channel.guild_id, channel.id).await;
+    }
+
+    #[instrument(skip(ctx))]
+    async fn guild_role_delete(
+        &self,
+        ctx: Context,
+        guild_id: GuildId,
+        removed_role_id: RoleId,
+        _removed_role_data_if_available: Option<Role>,
+    ) {
+        delete_role_data(&ctx, guild_id, removed_role_id).await;
     }
 
     #[instrument(skip(ctx))]
@@ -107,12 +202,269 @@ impl EventHandler for Handler {
 
     #[instrument(skip(ctx))]
     async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
-        reaction_role_handler(&ctx, &reaction).await;
+        reaction_role_handler(&ctx, &reaction, true).await;
+    }
+
+    #[instrument(skip(ctx))]
+    async fn reaction_remove(&self, ctx: Context, reaction: Reaction) {
+        reaction_role_handler(&ctx, &reaction, false).await;
     }
 
     #[instrument(skip(ctx))]
     async fn guild_member_addition(&self, ctx: Context, guild_id: GuildId, mut new_member: Member) {
         join_role_handler(&ctx, &guild_id, &mut new_member).await;
+        sticky_role_handler(&ctx, guild_id, &mut new_member).await;
+        welcome_message_handler(&ctx, guild_id, &new_member.user, WelcomeMessageKind::Welcome).await;
+        log_event(&ctx, guild_id, ModLogEvent::MemberJoined(new_member.user.clone())).await;
+    }
+
+    #[instrument(skip(ctx))]
+    async fn guild_member_removal(
+        &self,
+        ctx: Context,
+        guild_id: GuildId,
+        user: User,
+        member_data_if_available: Option<Member>,
+    ) {
+        join_role_removal_handler(&ctx, guild_id, user.id).await;
+        sticky_role_removal_handler(&ctx, guild_id, member_data_if_available.as_ref()).await;
+        welcome_message_handler(&ctx, guild_id, &user, WelcomeMessageKind::Goodbye).await;
+        log_event(&ctx, guild_id, ModLogEvent::MemberLeft(user)).await;
+    }
+
+    #[instrument(skip(ctx))]
+    async fn guild_member_update(&self, ctx: Context, old_if_available: Option<Member>, mut new: Member) {
+        join_role_screening_handler(&ctx, old_if_available.as_ref(), &mut new).await;
+    }
+}
+
+async fn delete_message_react_roles(
+    ctx: &Context,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_ids: &[MessageId],
+) {
+    let reaction_roles = {
+        let data = ctx.data.read().await;
+        unwrap_or_return!(data.get::<ReactionRoles>().cloned(), |_| {}, {
+            error!("Unable to get reaction roles!")
+        })
+    };
+    for message_id in message_ids {
+        match reaction_roles
+            .delete_react_roles(guild_id, channel_id, *message_id)
+            .await
+        {
+            Ok(0) => {}
+            Ok(deleted) => info!(
+                "Deleted {} reaction roles of deleted message {}",
+                deleted, message_id
+            ),
+            Err(e) => error!("Error deleting reaction roles of message {}: {:?}", message_id, e),
+        }
+    }
+}
+
+/// Forgets reaction roles, welcome messages and logs in a deleted channel
+async fn delete_channel_data(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) {
+    let (reaction_roles, welcome_messages, mod_log, message_log) = {
+        let data = ctx.data.read().await;
+        match (
+            data.get::<ReactionRoles>(),
+            data.get::<WelcomeMessages>(),
+            data.get::<ModLog>(),
+            data.get::<MessageLog>(),
+        ) {
+            (Some(reaction_roles), Some(welcome_messages), Some(mod_log), Some(message_log)) => (
+                reaction_roles.clone(),
+                welcome_messages.clone(),
+                mod_log.clone(),
+                message_log.clone(),
+            ),
+            _ => {
+                error!("Unable to get reaction roles, welcome messages, mod-log or message log!");
+                return;
+            }
+        }
+    };
+    match reaction_roles
+        .delete_channel_react_roles(guild_id, channel_id)
+        .await
+    {
+        Ok(0) => {}
+        Ok(deleted) => info!(
+            "Deleted {} reaction roles of deleted channel {}",
+            deleted, channel_id
+        ),
+        Err(e) => error!("Error deleting reaction roles of channel {}: {:?}", channel_id, e),
+    }
+    match welcome_messages
+        .delete_channel_welcome_messages(guild_id, channel_id)
+        .await
+    {
+        Ok(0) => {}
+        Ok(deleted) => info!(
+            "Disabled {} welcome messages of deleted channel {}",
+            deleted, channel_id
+        ),
+        Err(e) => error!(
+            "Error deleting welcome messages of channel {}: {:?}",
+            channel_id, e
+        ),
+    }
+    if let Err(e) = mod_log.forget_channel(guild_id, channel_id).await {
+        error!("Error disabling mod-log of channel {}: {:?}", channel_id, e);
+    }
+    if let Err(e) = message_log.forget_channel(guild_id, channel_id).await {
+        error!("Error updating message log of channel {}: {:?}", channel_id, e);
+    }
+}
+
+/// Forgets reaction roles, join roles, sticky roles, mute role and message log ignore of a deleted role
+async fn delete_role_data(ctx: &Context, guild_id: GuildId, role_id: RoleId) {
+    let (reaction_roles, join_roles, sticky_roles, infractions, message_log) = {
+        let data = ctx.data.read().await;
+        match (
+            data.get::<ReactionRoles>(),
+            data.get::<JoinRoles>(),
+            data.get::<StickyRoles>(),
+            data.get::<Infractions>(),
+            data.get::<MessageLog>(),
+        ) {
+            (
+                Some(reaction_roles),
+                Some(join_roles),
+                Some(sticky_roles),
+                Some(infractions),
+                Some(message_log),
+            ) => (
+                reaction_roles.clone(),
+                join_roles.clone(),
+                sticky_roles.clone(),
+                infractions.clone(),
+                message_log.clone(),
+            ),
+            _ => {
+                error!("Unable to get reaction roles, join roles, sticky roles, infractions or message log!");
+                return;
+            }
+        }
+    };
+    match reaction_roles.delete_role_react_roles(guild_id, role_id).await {
+        Ok(0) => {}
+        Ok(deleted) => info!("Deleted {} reaction roles of deleted role {}", deleted, role_id),
+        Err(e) => error!("Error deleting reaction roles of role {}: {:?}", role_id, e),
+    }
+    if let Err(e) = join_roles.delete_join_role(guild_id, role_id).await {
+        error!("Error deleting join role {}: {:?}", role_id, e);
+    }
+    if let Err(e) = sticky_roles.delete_sticky_role(guild_id, role_id).await {
+        error!("Error deleting sticky role {}: {:?}", role_id, e);
+    }
+    if let Err(e) = infractions.delete_mute_role(guild_id, role_id).await {
+        error!("Error unsetting mute role {}: {:?}", role_id, e);
+    }
+    if let Err(e) = message_log.configs().delete_ignore(guild_id, role_id.0).await {
+        error!("Error deleting message log ignore of role {}: {:?}", role_id, e);
+    }
+}
+
+/// Cleans up after channels and roles deleted while the bot was down.
+/// Reaction role messages in remaining channels are added to `messages`, as they need to be fetched to find out if they still exist.
+async fn reconcile_deleted_entities(
+    ctx: &Context,
+    guild_id: GuildId,
+    messages: &mut HashSet<(GuildId, ChannelId, MessageId)>,
+) {
+    let (reaction_roles, join_roles, sticky_roles) = {
+        let data = ctx.data.read().await;
+        match (
+            data.get::<ReactionRoles>(),
+            data.get::<JoinRoles>(),
+            data.get::<StickyRoles>(),
+        ) {
+            (Some(reaction_roles), Some(join_roles), Some(sticky_roles)) => {
+                (reaction_roles.clone(), join_roles.clone(), sticky_roles.clone())
+            }
+            _ => {
+                error!("Unable to get reaction roles, join roles or sticky roles!");
+                return;
+            }
+        }
+    };
+    let (channel_ids, role_ids) = unwrap_or_return!(
+        ctx.cache
+            .guild_field(guild_id, |g| {
+                (
+                    g.channels.keys().cloned().collect::<HashSet<ChannelId>>(),
+                    g.roles.keys().cloned().collect::<HashSet<RoleId>>(),
+                )
+            })
+            .await
+    );
+
+    let guild_join_roles = unwrap_or_return!(
+        join_roles.get_join_roles(guild_id).await,
+        |e| { error!("Error getting join roles: {:?}", e) },
+        {}
+    );
+    let guild_sticky_roles = unwrap_or_return!(
+        sticky_roles.get_sticky_roles(guild_id).await,
+        |e| { error!("Error getting sticky roles: {:?}", e) },
+        {}
+    );
+    let deleted_roles = guild_join_roles
+        .into_iter()
+        .map(|r| r.role_id)
+        .chain(guild_sticky_roles)
+        .filter(|r| !role_ids.contains(r))
+        .collect::<HashSet<RoleId>>();
+    for role_id in deleted_roles {
+        delete_role_data(ctx, guild_id, role_id).await;
+    }
+
+    let react_roles = unwrap_or_return!(
+        reaction_roles.get_react_roles(guild_id, None).await,
+        |e| { error!("Error getting reaction roles: {:?}", e) },
+        {}
+    );
+    let deleted_channels = react_roles
+        .iter()
+        .map(|r| r.channel_id)
+        .filter(|c| !channel_ids.contains(c))
+        .collect::<HashSet<ChannelId>>();
+    for channel_id in &deleted_channels {
+        delete_channel_data(ctx, guild_id, *channel_id).await;
+    }
+    let deleted_roles = react_roles
+        .iter()
+        .map(|r| r.role_id)
+        .filter(|r| !role_ids.contains(r))
+        .collect::<HashSet<RoleId>>();
+    for role_id in deleted_roles {
+        delete_role_data(ctx, guild_id, role_id).await;
+    }
+    messages.extend(
+        react_roles
+            .iter()
+            .filter(|r| !deleted_channels.contains(&r.channel_id))
+            .map(|r| (guild_id, r.channel_id, r.message_id)),
+    );
+}
+
+/// Cleans up after reaction role messages deleted while the bot was down.
+/// Each message has to be fetched, so they're checked one at a time to leave room for other requests.
+async fn check_reaction_role_messages(ctx: Context, messages: HashSet<(GuildId, ChannelId, MessageId)>) {
+    for (guild_id, channel_id, message_id) in messages {
+        match channel_id.message(&ctx, message_id).await {
+            Ok(_) => {}
+            // Other errors, like missing access, don't mean the message is gone
+            Err(e) if is_not_found(&e) => {
+                delete_message_react_roles(&ctx, guild_id, channel_id, &[message_id]).await
+            }
+            Err(e) => debug!("Unable to check reaction role message {}: {:?}", message_id, e),
+        }
+        tokio::time::sleep(REACTION_ROLE_MESSAGE_CHECK_INTERVAL).await;
     }
 }
 
@@ -162,30 +514,75 @@ pub async fn after(ctx: &Context, msg: &Message, cmd_name: &str, error: Result<(
 #[instrument(skip(ctx))]
 pub async fn unrecognised_command(ctx: &Context, msg: &Message, command_name: &str) {
     if let Some(guild_id) = msg.guild_id {
-        let custom_commands = {
+        let (custom_commands, command_cooldowns) = {
             let data = ctx.data.read().await;
-            data.get::<CustomCommands>().cloned()
+            let command_cooldowns = match data.get::<CommandCooldowns>() {
+                Some(cooldowns) => cooldowns.clone(),
+                None => {
+                    error!("Unable to get command cooldowns!");
+                    return;
+                }
+            };
+            (data.get::<CustomCommands>().cloned(), command_cooldowns)
         };
         match custom_commands {
             None => error!("Unable to get custom commands!"),
             Some(cc) => match cc.get_command(guild_id, command_name.to_string()).await {
                 Err(e) => error!("Error getting custom command: {:?}", e),
                 Ok(None) => {}
-                Ok(Some(content)) => {
-                    if content.starts_with("{") {
-                        if let Err(e) = send_rich_serialized_message(ctx, msg.channel_id, &*content).await {
-                            error!(
-                                "Unable to send rich message, content: {:?}, error: {:?}",
-                                content, e
-                            );
+                Ok(Some(command)) => {
+                    let restrictions = unwrap_or_return!(
+                        cc.get_restrictions(command.command_id).await,
+                        |e| { error!("Error getting custom command restrictions: {:?}", e) },
+                        {}
+                    );
+                    let roles = msg.member.as_ref().map(|m| &m.roles[..]).unwrap_or(&[]);
+                    if !is_command_allowed(&restrictions, guild_id, roles, msg.channel_id) {
+                        debug!("Custom command {:?} is restricted here", command.name);
+                        return;
+                    }
+                    let cooldowns = command.cooldowns(guild_id, msg.channel_id, msg.author.id);
+                    if let Err(remaining) = command_cooldowns
+                        .try_use(
+                            CooldownSubject::Command(command.command_id),
+                            &cooldowns,
+                            Instant::now(),
+                        )
+                        .await
+                    {
+                        debug!(
+                            "Custom command {:?} is on cooldown for {:?}",
+                            command.name, remaining
+                        );
+                        if command.cooldown_message {
+                            notify_on_cooldown(
+                                ctx,
+                                msg,
+                                &command_cooldowns,
+                                CooldownSubject::Command(command.command_id),
+                                remaining,
+                            )
+                            .await;
                         }
-                    } else {
-                        if let Err(e) = msg.channel_id.say(ctx, content.clone()).await {
-                            error!(
-                                "Unable to send simple custom response, content: {:?}, error: {:?}",
-                                content, e
-                            );
+                        return;
+                    }
+                    let responses = unwrap_or_return!(
+                        cc.get_responses(command.command_id).await,
+                        |e| { error!("Error getting custom command responses: {:?}", e) },
+                        {}
+                    );
+                    let response = match responses.choose_weighted(&mut rand::thread_rng(), |r| r.weight) {
+                        Ok(response) => response,
+                        Err(e) => {
+                            error!("Unable to pick response for {:?}: {:?}", command.name, e);
+                            return;
                         }
+                    };
+                    if let Err(e) = respond_with_custom_command(ctx, msg, command_name, response).await {
+                        error!(
+                            "Unable to send custom response, content: {:?}, error: {:?}",
+                            response.content, e
+                        );
                     }
                 }
             },
@@ -193,6 +590,142 @@ pub async fn unrecognised_command(ctx: &Context, msg: &Message, command_name: &s
     }
 }
 
+/// Tells the user they have to wait, removing the message after a while to not clutter the channel.
+/// They're told only once per cooldown of given command, so spamming the command doesn't spam notices.
+async fn notify_on_cooldown(
+    ctx: &Context,
+    msg: &Message,
+    cooldowns: &CommandCooldowns,
+    subject: CooldownSubject,
+    remaining: Duration,
+) {
+    if !cooldowns
+        .should_notify(
+            subject,
+            msg.author.id.0,
+            msg.channel_id.0,
+            remaining,
+            Instant::now(),
+        )
+        .await
+    {
+        return;
+    }
+    let content = format!(
+        "{} this command is on cooldown, try again in {}s",
+        msg.author.mention(),
+        remaining.as_secs() + 1
+    );
+    let reply = unwrap_or_return!(
+        msg.channel_id.say(ctx, content).await,
+        |e| { error!("Unable to send cooldown message: {:?}", e) },
+        {}
+    );
+    let http = ctx.http.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(COOLDOWN_MESSAGE_LIFETIME).await;
+        if let Err(e) = reply.channel_id.delete_message(&http, reply.id).await {
+            error!("Unable to delete cooldown message: {:?}", e);
+        }
+    });
+}
+
+/// Sends custom command response, with template variables expanded
+async fn respond_with_custom_command(
+    ctx: &Context,
+    msg: &Message,
+    command_name: &str,
+    response: &CommandResponse,
+) -> CommandResult {
+    if !response.templated {
+        return send_unrendered(ctx, msg.channel_id, &response.content).await;
+    }
+    let guild_info = {
+        let data = ctx.data.read().await;
+        let guild_info = data
+            .get::<GuildInfoTable>()
+            .context("Can't get guild info")?
+            .clone();
+        guild_info
+    };
+    let bot_id = ctx.cache.current_user_id().await;
+    let mut prefixes = vec![format!("<@{}>", bot_id), format!("<@!{}>", bot_id)];
+    if let Some(prefix) = guild_info
+        .get_prefix(msg.guild_id.context("Not in a guild")?)
+        .await
+    {
+        prefixes.push(prefix);
+    }
+    let args = custom_command_args(&msg.content, &prefixes, command_name);
+    let variables = template_variables(ctx, &msg.author, msg.guild_id, Some(msg.channel_id), args).await;
+    send_rendered(ctx, msg.channel_id, &response.content, &variables).await
+}
+
+/// Responds to messages matching one of guild's autoresponders, only first match is used
+async fn autoresponder_handler(ctx: &Context, msg: &Message) {
+    if msg.author.bot {
+        return;
+    }
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => return,
+    };
+    let (guild_info, autoresponders, command_cooldowns) = {
+        let data = ctx.data.read().await;
+        match (
+            data.get::<GuildInfoTable>(),
+            data.get::<Autoresponders>(),
+            data.get::<CommandCooldowns>(),
+        ) {
+            (Some(guild_info), Some(autoresponders), Some(cooldowns)) => {
+                (guild_info.clone(), autoresponders.clone(), cooldowns.clone())
+            }
+            _ => {
+                error!("Unable to get guild info, autoresponders or cooldowns!");
+                return;
+            }
+        }
+    };
+    // Commands are handled by the framework, don't respond to e.g. setting up an autoresponder
+    if let Some(prefix) = guild_info.get_prefix(guild_id).await {
+        if msg.content.starts_with(&prefix) {
+            return;
+        }
+    }
+    let guild_autoresponders = unwrap_or_return!(
+        autoresponders.get_autoresponders(guild_id).await,
+        |e| { error!("Error getting autoresponders: {:?}", e) },
+        {}
+    );
+    let autoresponder = match guild_autoresponders
+        .iter()
+        .find(|a| a.matches(msg.channel_id, &msg.content))
+    {
+        Some(autoresponder) => autoresponder,
+        None => return,
+    };
+    let cooldowns = autoresponder.cooldowns(msg.channel_id);
+    let subject = CooldownSubject::Autoresponder(autoresponder.autoresponder_id);
+    if let Err(remaining) = command_cooldowns
+        .try_use(subject, &cooldowns, Instant::now())
+        .await
+    {
+        debug!(
+            "Autoresponder {:?} is on cooldown for {:?}",
+            autoresponder.name, remaining
+        );
+        return;
+    }
+    let variables =
+        template_variables(ctx, &msg.author, Some(guild_id), Some(msg.channel_id), Vec::new()).await;
+    if let Err(e) = send_rendered(ctx, msg.channel_id, &autoresponder.content, &variables).await {
+        error!(
+            "Unable to send autoresponse, content: {:?}, error: {:?}",
+            autoresponder.content, e
+        );
+    }
+}
+
 #[help]
 #[lacking_role(strike)]
 #[lacking_permissions(strike)]
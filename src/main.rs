@@ -10,11 +10,31 @@ mod utils;
 mod version_data;
 
 use crate::{
-    database::queries::{CustomCommands, GuildInfoTable, JoinRoles, ReactionRoles},
+    commands::{
+        admin::resume_bulk_role_jobs,
+        config::delayed_join_role_scheduler,
+        moderation::punishment_expiry_scheduler,
+    },
+    database::queries::{
+        Autoresponders,
+        BulkRoleJobs,
+        CustomCommands,
+        GuildInfoTable,
+        Infractions,
+        JoinRoles,
+        MessageLogConfigs,
+        ModLogChannels,
+        ReactionRoles,
+        StickyRoles,
+        WelcomeMessages,
+    },
     event_handling::{after, before, dynamic_prefix, unrecognised_command, Handler, MY_HELP},
     structures::{
         commands::*,
         context::{ConnectionPool, PublicData, ShardManagerContainer, VersionDataContainer},
+        cooldowns::CommandCooldowns,
+        message_log::{MessageLog, MESSAGES_CACHED_PER_CHANNEL, MESSAGE_LOG_CACHE_CAPACITY},
+        mod_log::ModLog,
     },
     version_data::VersionData,
 };
@@ -101,11 +121,24 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .intents(GatewayIntents::all())
         .await
         .expect("Err creating client");
+    // Edited and deleted messages are logged from the cache
+    client
+        .cache_and_http
+        .cache
+        .set_max_messages(MESSAGES_CACHED_PER_CHANNEL)
+        .await;
 
     let guild_info = GuildInfoTable::new(prefix.clone(), pool.clone()).await?;
     let custom_commands = CustomCommands::new(pool.clone());
+    let autoresponders = Autoresponders::new(pool.clone());
     let reaction_roles = ReactionRoles::new(pool.clone());
-    let join_roles = JoinRoles::new(pool.clone());
+    let join_roles = Arc::new(JoinRoles::new(pool.clone()));
+    let sticky_roles = Arc::new(StickyRoles::new(pool.clone()));
+    let welcome_messages = WelcomeMessages::new(pool.clone());
+    let bulk_role_jobs = Arc::new(BulkRoleJobs::new(pool.clone()));
+    let infractions = Arc::new(Infractions::new(pool.clone()));
+    let mod_log = Arc::new(ModLog::new(ModLogChannels::new(pool.clone())));
+    let message_log = MessageLog::new(MessageLogConfigs::new(pool.clone()), MESSAGE_LOG_CACHE_CAPACITY);
     {
         let mut data = client.data.write().await;
         // Init shard manager
@@ -119,8 +152,16 @@ async fn main() -> Result<(), Box<dyn Error>> {
         data.insert::<VersionDataContainer>(Arc::new(build_data));
         data.insert::<GuildInfoTable>(Arc::new(guild_info));
         data.insert::<CustomCommands>(Arc::new(custom_commands));
+        data.insert::<Autoresponders>(Arc::new(autoresponders));
+        data.insert::<CommandCooldowns>(Arc::new(CommandCooldowns::new()));
         data.insert::<ReactionRoles>(Arc::new(reaction_roles));
-        data.insert::<JoinRoles>(Arc::new(join_roles));
+        data.insert::<JoinRoles>(join_roles.clone());
+        data.insert::<StickyRoles>(sticky_roles.clone());
+        data.insert::<WelcomeMessages>(Arc::new(welcome_messages));
+        data.insert::<BulkRoleJobs>(bulk_role_jobs.clone());
+        data.insert::<Infractions>(infractions.clone());
+        data.insert::<ModLog>(mod_log.clone());
+        data.insert::<MessageLog>(Arc::new(message_log));
     }
 
     // Listen to interrupts
@@ -139,6 +180,22 @@ async fn main() -> Result<(), Box<dyn Error>> {
         });
     }
 
+    tokio::spawn(delayed_join_role_scheduler(
+        client.cache_and_http.http.clone(),
+        join_roles,
+    ));
+    tokio::spawn(resume_bulk_role_jobs(
+        client.cache_and_http.http.clone(),
+        bulk_role_jobs,
+    ));
+    tokio::spawn(punishment_expiry_scheduler(
+        client.cache_and_http.http.clone(),
+        infractions,
+        sticky_roles,
+        mod_log,
+        bot_id,
+    ));
+
     if let Err(why) = client.start().await {
         error!("Client error: {:?}", why);
     }
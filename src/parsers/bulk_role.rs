@@ -0,0 +1,294 @@
+use crate::{
+    parsers::{
+        duration::parse_duration,
+        options::{Arg, OptionArgs},
+    },
+    structures::{errors::ParseError, member_kind::MemberKind},
+};
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, Utc};
+use strum_macros::{AsRefStr, EnumString};
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum BulkRoleMode {
+    Add,
+    Remove,
+}
+
+impl BulkRoleMode {
+    /// Whether member's roles need to change
+    pub fn needs_change(self, has_role: bool) -> bool {
+        match self {
+            Self::Add => !has_role,
+            Self::Remove => has_role,
+        }
+    }
+}
+
+/// Which members get their roles changed.
+/// Roles are names or mentions until they are resolved to ids.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemberFilter<R> {
+    /// Member must have all of these roles
+    pub all: Vec<R>,
+    /// Member must have at least one of these roles, unless it's empty
+    pub any: Vec<R>,
+    /// Member can't have any of these roles
+    pub none: Vec<R>,
+    pub target: MemberKind,
+    pub joined_before: Option<DateTime<Utc>>,
+    pub joined_after: Option<DateTime<Utc>>,
+}
+
+impl<R> Default for MemberFilter<R> {
+    fn default() -> Self {
+        Self {
+            all: Vec::new(),
+            any: Vec::new(),
+            none: Vec::new(),
+            target: MemberKind::Both,
+            joined_before: None,
+            joined_after: None,
+        }
+    }
+}
+
+impl<R: PartialEq> MemberFilter<R> {
+    /// Members with unknown join date don't match join date filters
+    pub fn matches(&self, roles: &[R], is_bot: bool, joined_at: Option<DateTime<Utc>>) -> bool {
+        if !self.target.applies_to(is_bot) {
+            return false;
+        }
+        if !self.all.iter().all(|role| roles.contains(role))
+            || (!self.any.is_empty() && !self.any.iter().any(|role| roles.contains(role)))
+            || self.none.iter().any(|role| roles.contains(role))
+        {
+            return false;
+        }
+        match (self.joined_before, self.joined_after, joined_at) {
+            (None, None, _) => true,
+            (_, _, None) => false,
+            (before, after, Some(joined_at)) => {
+                before.map(|before| joined_at < before).unwrap_or(true)
+                    && after.map(|after| joined_at > after).unwrap_or(true)
+            }
+        }
+    }
+}
+
+/// Arguments of `bulk_role` command, like `Member --any Red Blue --none Muted --humans --dry-run`
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulkRoleArgs {
+    /// Role which is added or removed
+    pub role: String,
+    pub mode: BulkRoleMode,
+    pub filter: MemberFilter<String>,
+    /// Only count members who would change
+    pub dry_run: bool,
+}
+
+impl BulkRoleArgs {
+    /// First argument which isn't an option is the changed role.
+    /// Other roles are required by default, `--any` and `--none` change how the roles following them are used.
+    /// Join dates are either `YYYY-MM-DD` or how long ago, relative to `now`.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I, now: DateTime<Utc>) -> Result<Self, ParseError> {
+        let mut role = None;
+        let mut mode = BulkRoleMode::Add;
+        let mut filter = MemberFilter::default();
+        let mut dry_run = false;
+        // Which of the filter lists roles are currently added to
+        let mut list = RoleList::All;
+
+        let mut args = OptionArgs::new(args);
+        while let Some(arg) = args.next() {
+            let option = match arg {
+                Arg::Option(option) => option,
+                Arg::Value(value) => {
+                    match (&role, list) {
+                        (None, _) => role = Some(value),
+                        (Some(_), RoleList::All) => filter.all.push(value),
+                        (Some(_), RoleList::Any) => filter.any.push(value),
+                        (Some(_), RoleList::None) => filter.none.push(value),
+                    }
+                    continue;
+                }
+            };
+            match option.as_str() {
+                "remove" => mode = BulkRoleMode::Remove,
+                "dry-run" | "dryrun" => dry_run = true,
+                "all" => list = RoleList::All,
+                "any" => list = RoleList::Any,
+                "none" => list = RoleList::None,
+                "bots" => filter.target = MemberKind::Bots,
+                "humans" => filter.target = MemberKind::Humans,
+                "joined-before" => {
+                    filter.joined_before = Some(parse_point_in_time(&args.value_of(&option)?, now)?)
+                }
+                "joined-after" => {
+                    filter.joined_after = Some(parse_point_in_time(&args.value_of(&option)?, now)?)
+                }
+                _ => return Err(Arg::Option(option).unexpected()),
+            }
+        }
+
+        Ok(Self {
+            role: role.ok_or_else(|| ParseError::MissingValue("role".to_string()))?,
+            mode,
+            filter,
+            dry_run,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RoleList {
+    All,
+    Any,
+    None,
+}
+
+/// Parses either date like `2021-02-28`, or how long ago like `30d`
+fn parse_point_in_time(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, ParseError> {
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Ok(DateTime::from_utc(date.and_hms(0, 0, 0), Utc));
+    }
+    let ago = parse_duration(input).map_err(|_| ParseError::InvalidDate(input.to_string()))?;
+    ChronoDuration::from_std(ago)
+        .ok()
+        .and_then(|ago| now.checked_sub_signed(ago))
+        .ok_or_else(|| ParseError::InvalidDate(input.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2021, 3, 1).and_hms(12, 0, 0)
+    }
+
+    fn parse(input: &str) -> Result<BulkRoleArgs, ParseError> {
+        BulkRoleArgs::parse(input.split_whitespace().map(ToString::to_string), now())
+    }
+
+    fn roles(names: &[&str]) -> Vec<String> {
+        names.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn parses_legacy_arguments() {
+        let expected = BulkRoleArgs {
+            role: "NewSuperRole".to_string(),
+            mode: BulkRoleMode::Add,
+            filter: MemberFilter {
+                all: roles(&["OldRole"]),
+                ..Default::default()
+            },
+            dry_run: false,
+        };
+        assert_eq!(expected, parse("NewSuperRole OldRole").unwrap());
+    }
+
+    #[test]
+    fn parses_all_options() {
+        let expected = BulkRoleArgs {
+            role: "Member".to_string(),
+            mode: BulkRoleMode::Remove,
+            filter: MemberFilter {
+                all: roles(&["Verified", "Active"]),
+                any: roles(&["Red", "Blue"]),
+                none: roles(&["Muted"]),
+                target: MemberKind::Humans,
+                joined_before: Some(Utc.ymd(2021, 2, 1).and_hms(0, 0, 0)),
+                joined_after: Some(Utc.ymd(2021, 2, 22).and_hms(12, 0, 0)),
+            },
+            dry_run: true,
+        };
+        let input = "Member Verified --any Red Blue --none Muted --all Active --remove --humans \
+                     --joined-before 2021-02-01 --joined-after 1w --dry-run";
+        assert_eq!(expected, parse(input).unwrap());
+    }
+
+    #[test]
+    fn options_can_precede_role() {
+        let parsed = parse("--dry-run --bots Bot").unwrap();
+        assert_eq!("Bot", parsed.role);
+        assert_eq!(MemberKind::Bots, parsed.filter.target);
+        assert!(parsed.dry_run);
+    }
+
+    #[test]
+    fn requires_role() {
+        match parse("--dry-run --humans") {
+            Err(ParseError::MissingValue(value)) => assert_eq!("role", value),
+            e => panic!("Expected missing value, got {:#?}", e),
+        };
+    }
+
+    #[rstest(input, case::not_a_date("2021-13-01"), case::gibberish("yesterday"))]
+    fn invalid_date(input: &str) {
+        match parse_point_in_time(input, now()) {
+            Err(ParseError::InvalidDate(passed)) => assert_eq!(input, passed),
+            e => panic!("Expected invalid date, got {:#?}", e),
+        };
+    }
+
+    #[rstest(
+        member_roles,
+        is_bot,
+        expected,
+        case::matches(&[1, 2, 3], false, true),
+        case::lacks_required(&[1, 3], false, false),
+        case::lacks_any(&[1, 2], false, false),
+        case::has_excluded(&[1, 2, 3, 4], false, false),
+        case::bot(&[1, 2, 3], true, false)
+    )]
+    fn matches_roles(member_roles: &[u64], is_bot: bool, expected: bool) {
+        let filter = MemberFilter {
+            all: vec![1, 2],
+            any: vec![3, 5],
+            none: vec![4],
+            target: MemberKind::Humans,
+            ..Default::default()
+        };
+        assert_eq!(expected, filter.matches(member_roles, is_bot, None));
+    }
+
+    #[rstest(
+        joined_at,
+        expected,
+        case::inside(Some(Utc.ymd(2021, 2, 10).and_hms(0, 0, 0)), true),
+        case::too_early(Some(Utc.ymd(2021, 1, 10).and_hms(0, 0, 0)), false),
+        case::too_late(Some(Utc.ymd(2021, 2, 25).and_hms(0, 0, 0)), false),
+        case::unknown(None, false)
+    )]
+    fn matches_join_date(joined_at: Option<DateTime<Utc>>, expected: bool) {
+        let filter = MemberFilter::<u64> {
+            joined_after: Some(Utc.ymd(2021, 2, 1).and_hms(0, 0, 0)),
+            joined_before: Some(Utc.ymd(2021, 2, 20).and_hms(0, 0, 0)),
+            ..Default::default()
+        };
+        assert_eq!(expected, filter.matches(&[], false, joined_at));
+    }
+
+    #[rstest(
+        mode,
+        has_role,
+        expected,
+        case::add_missing(BulkRoleMode::Add, false, true),
+        case::add_present(BulkRoleMode::Add, true, false),
+        case::remove_present(BulkRoleMode::Remove, true, true),
+        case::remove_missing(BulkRoleMode::Remove, false, false)
+    )]
+    fn needs_change(mode: BulkRoleMode, has_role: bool, expected: bool) {
+        assert_eq!(expected, mode.needs_change(has_role));
+    }
+
+    #[test]
+    fn empty_filter_matches_everyone() {
+        assert!(MemberFilter::<u64>::default().matches(&[], true, None));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::structures::errors::ParseError;
+use std::time::Duration;
+
+/// Parses durations like `30s`, `10m`, `2d`, `1w` or combined `1h30m`.
+/// Number without unit is in seconds.
+pub fn parse_duration(input: &str) -> Result<Duration, ParseError> {
+    let invalid = || ParseError::InvalidDuration(input.to_string());
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(invalid());
+    }
+    if let Ok(seconds) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let multiplier = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let value = number.parse::<u64>().map_err(|_| invalid())?;
+        number.clear();
+        total = value
+            .checked_mul(multiplier)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+    }
+    if !number.is_empty() {
+        // Trailing number without unit, like `1h30`
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// Formats duration the way `parse_duration` accepts it, like `1h30m`
+pub fn format_duration(duration: Duration) -> String {
+    let mut seconds = duration.as_secs();
+    if seconds == 0 {
+        return "0s".to_string();
+    }
+    let mut result = String::new();
+    for (unit, length) in &[
+        ("w", 7 * 24 * 60 * 60),
+        ("d", 24 * 60 * 60),
+        ("h", 60 * 60),
+        ("m", 60),
+        ("s", 1),
+    ] {
+        if seconds >= *length {
+            result.push_str(&format!("{}{}", seconds / length, unit));
+            seconds %= length;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use proptest::prelude::*;
+    use rstest::rstest;
+
+    #[rstest(
+        input,
+        seconds,
+        case::plain_number("90", 90),
+        case::seconds("30s", 30),
+        case::minutes("10m", 600),
+        case::hours("2h", 7200),
+        case::days("2d", 172_800),
+        case::weeks("1w", 604_800),
+        case::combined("1h30m", 5400),
+        case::uppercase("1H", 3600),
+        case::padded(" 5m ", 300),
+        case::zero("0s", 0)
+    )]
+    fn parses(input: &str, seconds: u64) {
+        assert_eq!(Duration::from_secs(seconds), parse_duration(input).unwrap());
+    }
+
+    #[rstest(
+        input,
+        case::empty(""),
+        case::unit_only("m"),
+        case::unknown_unit("5y"),
+        case::trailing_number("1h30"),
+        case::negative("-5m"),
+        case::overflow("99999999999999999999w")
+    )]
+    fn rejects(input: &str) {
+        match parse_duration(input) {
+            Err(ParseError::InvalidDuration(passed)) => assert_eq!(input, passed),
+            e => panic!("Expected invalid duration, got {:#?}", e),
+        };
+    }
+
+    #[rstest(
+        seconds,
+        expected,
+        case::zero(0, "0s"),
+        case::seconds(45, "45s"),
+        case::combined(5400, "1h30m"),
+        case::weeks(694_861, "1w1d1h1m1s")
+    )]
+    fn formats(seconds: u64, expected: &str) {
+        assert_eq!(expected, format_duration(Duration::from_secs(seconds)));
+    }
+
+    proptest! {
+        #[test]
+        fn format_roundtrips(seconds in 0u64..10_000_000) {
+            let duration = Duration::from_secs(seconds);
+            prop_assert_eq!(duration, parse_duration(&format_duration(duration)).unwrap());
+        }
+    }
+}
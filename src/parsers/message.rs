@@ -2,11 +2,14 @@ use crate::structures::errors::{
     ColourParseError::{self, InvalidColourHexLength, InvalidColourHexValue, UnknownColourName},
     ParseError,
 };
+use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use serenity::{
     builder::{CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateMessage},
-    utils::Colour,
+    model::channel::{Embed as SerenityEmbed, Message as SerenityMessage},
+    utils::{hashmap_to_json_map, Colour},
 };
 use std::{collections::HashMap, convert::TryFrom};
 
@@ -44,44 +47,237 @@ pub static NAME_TO_COLOUR_MAPPING: Lazy<HashMap<&str, Colour>> = Lazy::new(|| {
     };
 });
 
-#[derive(Deserialize, Debug, PartialEq, Default, Clone)]
+// Limits imposed by Discord, see https://discord.com/developers/docs/resources/channel#embed-limits
+pub const MAX_CONTENT_LENGTH: usize = 2000;
+pub const MAX_EMBEDS: usize = 10;
+pub const MAX_EMBEDS_TOTAL_LENGTH: usize = 6000;
+pub const MAX_EMBED_TITLE_LENGTH: usize = 256;
+pub const MAX_EMBED_DESCRIPTION_LENGTH: usize = 4096;
+pub const MAX_EMBED_FIELDS: usize = 25;
+pub const MAX_EMBED_FIELD_NAME_LENGTH: usize = 256;
+pub const MAX_EMBED_FIELD_VALUE_LENGTH: usize = 1024;
+pub const MAX_EMBED_FOOTER_LENGTH: usize = 2048;
+pub const MAX_EMBED_AUTHOR_NAME_LENGTH: usize = 256;
+
+/// Returns length of `value` in characters, or error naming `path` if it's over `limit`
+fn check_length(path: &str, value: &str, limit: usize) -> Result<usize, ParseError> {
+    let length = value.chars().count();
+    if length > limit {
+        Err(ParseError::TooLong(path.to_string(), length, limit))
+    } else {
+        Ok(length)
+    }
+}
+
+pub(crate) fn check_count(path: &str, count: usize, limit: usize) -> Result<(), ParseError> {
+    if count > limit {
+        Err(ParseError::TooManyEntries(path.to_string(), count, limit))
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default, Clone)]
 pub struct Message {
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "c")]
     pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "e")]
-    pub embed: Option<Embed>,
+    #[serde(alias = "embeds")]
+    pub embed: Option<EmbedEnum>,
+}
+
+impl Message {
+    /// Checks the message against Discord limits, so it's not rejected only once it's sent
+    pub fn validate(&self) -> Result<(), ParseError> {
+        if let Some(content) = &self.content {
+            check_length("content", content, MAX_CONTENT_LENGTH)?;
+        }
+        let total_length = match &self.embed {
+            None => 0,
+            Some(EmbedEnum::Single(embed)) => embed.validate("embed")?,
+            Some(EmbedEnum::Vector(embeds)) => {
+                check_count("embeds", embeds.len(), MAX_EMBEDS)?;
+                let mut total_length = 0;
+                for (idx, embed) in embeds.iter().enumerate() {
+                    total_length += embed.validate(&format!("embeds[{}]", idx))?;
+                }
+                total_length
+            }
+        };
+        if total_length > MAX_EMBEDS_TOTAL_LENGTH {
+            return Err(ParseError::TooLong(
+                "embeds (total)".to_string(),
+                total_length,
+                MAX_EMBEDS_TOTAL_LENGTH,
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl<'a> TryFrom<Message> for CreateMessage<'a> {
     type Error = ParseError;
 
     fn try_from(message: Message) -> Result<Self, Self::Error> {
+        message.validate()?;
         let mut builder = Self::default();
         message.content.map(|c| builder.content(c));
         if let Some(e) = message.embed {
-            let embed = CreateEmbed::try_from(e)?;
-            builder.embed(|e| {
-                e.0 = embed.0;
-                e
-            });
+            let embeds = match e {
+                EmbedEnum::Single(embed) => vec![*embed],
+                EmbedEnum::Vector(embeds) => embeds,
+            };
+            let mut embeds = embeds
+                .into_iter()
+                .map(CreateEmbed::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            if embeds.len() == 1 {
+                let embed = embeds.remove(0);
+                builder.embed(|e| {
+                    e.0 = embed.0;
+                    e
+                });
+            } else {
+                // Serenity only knows about a single embed, so build the array by hand
+                let embeds = embeds
+                    .into_iter()
+                    .map(|e| Value::Object(hashmap_to_json_map(e.0)))
+                    .collect();
+                builder.0.insert("embeds", Value::Array(embeds));
+            }
         }
         return Ok(builder);
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Default, Clone)]
+impl From<&SerenityMessage> for Message {
+    fn from(message: &SerenityMessage) -> Self {
+        // Only export embeds we could've sent - not link previews and the like
+        let mut embeds = message
+            .embeds
+            .iter()
+            .filter(|e| e.kind == "rich")
+            .map(Embed::from)
+            .collect::<Vec<Embed>>();
+        let embed = match embeds.len() {
+            0 => None,
+            1 => Some(EmbedEnum::Single(Box::new(embeds.remove(0)))),
+            _ => Some(EmbedEnum::Vector(embeds)),
+        };
+        Self {
+            content: Some(message.content.clone()).filter(|c| !c.is_empty()),
+            embed,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum EmbedEnum {
+    Single(Box<Embed>),
+    Vector(Vec<Embed>),
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Default, Clone)]
 pub struct Embed {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "t")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "u")]
+    #[serde(alias = "link")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "c")]
     #[serde(alias = "color")]
     pub colour: Option<EmbedColourEnum>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "d")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "f")]
     #[serde(alias = "fields")]
+    #[serde(rename(serialize = "fields"))]
     pub field: Option<EmbedFieldEnum>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub footer: Option<EmbedFooterEnum>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "a")]
     pub author: Option<EmbedAuthor>,
+    /// ISO-8601 timestamp, or `now` to use the time of sending
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "ts")]
+    pub timestamp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "th")]
+    pub thumbnail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "i")]
+    #[serde(alias = "img")]
+    pub image: Option<String>,
+}
+
+impl Embed {
+    /// Validates embed found under `path`, returning its length towards the total embed limit
+    fn validate(&self, path: &str) -> Result<usize, ParseError> {
+        let mut length = 0;
+        if let Some(title) = &self.title {
+            length += check_length(&format!("{}.title", path), title, MAX_EMBED_TITLE_LENGTH)?;
+        }
+        if let Some(description) = &self.description {
+            length += check_length(
+                &format!("{}.description", path),
+                description,
+                MAX_EMBED_DESCRIPTION_LENGTH,
+            )?;
+        }
+        let fields = match &self.field {
+            None => &[][..],
+            Some(EmbedFieldEnum::Single(field)) => std::slice::from_ref(field),
+            Some(EmbedFieldEnum::Vector(fields)) => &fields[..],
+        };
+        check_count(&format!("{}.fields", path), fields.len(), MAX_EMBED_FIELDS)?;
+        for (idx, field) in fields.iter().enumerate() {
+            length += check_length(
+                &format!("{}.fields[{}].name", path, idx),
+                &field.name,
+                MAX_EMBED_FIELD_NAME_LENGTH,
+            )?;
+            length += check_length(
+                &format!("{}.fields[{}].value", path, idx),
+                &field.value,
+                MAX_EMBED_FIELD_VALUE_LENGTH,
+            )?;
+        }
+        let footer_text = match &self.footer {
+            None => None,
+            Some(EmbedFooterEnum::TextOnly(text)) => Some(text),
+            Some(EmbedFooterEnum::Complex(footer)) => footer.text.as_ref(),
+        };
+        if let Some(text) = footer_text {
+            length += check_length(&format!("{}.footer", path), text, MAX_EMBED_FOOTER_LENGTH)?;
+        }
+        if let Some(author) = &self.author {
+            length += check_length(
+                &format!("{}.author.name", path),
+                &author.name,
+                MAX_EMBED_AUTHOR_NAME_LENGTH,
+            )?;
+        }
+        Ok(length)
+    }
+}
+
+/// Parses embed timestamp, which is either ISO-8601 date or `now`
+fn parse_timestamp(timestamp: String) -> Result<DateTime<Utc>, ParseError> {
+    if timestamp.eq_ignore_ascii_case("now") {
+        return Ok(Utc::now());
+    }
+    DateTime::parse_from_rfc3339(&timestamp)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| ParseError::InvalidTimestamp(timestamp, e))
 }
 
 impl TryFrom<Embed> for CreateEmbed {
@@ -89,6 +285,8 @@ impl TryFrom<Embed> for CreateEmbed {
 
     fn try_from(value: Embed) -> Result<Self, Self::Error> {
         let mut builder = Self::default();
+        value.title.map(|v| builder.title(v));
+        value.url.map(|v| builder.url(v));
         match value.colour {
             None => Result::<(), Self::Error>::Ok(()),
             Some(v) => {
@@ -119,11 +317,56 @@ impl TryFrom<Embed> for CreateEmbed {
                 a
             })
         });
+        if let Some(v) = value.timestamp {
+            builder.timestamp(&parse_timestamp(v)?);
+        }
+        value.thumbnail.map(|v| builder.thumbnail(v));
+        value.image.map(|v| builder.image(v));
         return Ok(builder);
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+impl From<&SerenityEmbed> for Embed {
+    fn from(embed: &SerenityEmbed) -> Self {
+        let fields = embed
+            .fields
+            .iter()
+            .map(|f| EmbedField {
+                name: f.name.clone(),
+                value: f.value.clone(),
+                inline: Some(true).filter(|_| f.inline),
+            })
+            .collect::<Vec<EmbedField>>();
+        let footer = embed.footer.as_ref().map(|f| match &f.icon_url {
+            None => EmbedFooterEnum::TextOnly(f.text.clone()),
+            Some(url) => EmbedFooterEnum::Complex(EmbedFooter {
+                text: Some(f.text.clone()),
+                url: Some(url.clone()),
+            }),
+        });
+        Self {
+            title: embed.title.clone(),
+            url: embed.url.clone(),
+            // Discord doesn't differentiate between black and no colour
+            colour: Some(embed.colour)
+                .filter(|c| c.0 != 0)
+                .map(|c| EmbedColourEnum::String(format!("#{}", c.hex()))),
+            description: embed.description.clone(),
+            field: Some(EmbedFieldEnum::Vector(fields)).filter(|_| !embed.fields.is_empty()),
+            footer,
+            author: embed.author.as_ref().map(|a| EmbedAuthor {
+                name: a.name.clone(),
+                link: a.url.clone(),
+                icon: a.icon_url.clone(),
+            }),
+            timestamp: embed.timestamp.clone(),
+            thumbnail: embed.thumbnail.as_ref().map(|t| t.url.clone()),
+            image: embed.image.as_ref().map(|i| i.url.clone()),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 #[serde(untagged)]
 pub enum EmbedColourEnum {
     Integer(u32),
@@ -158,7 +401,7 @@ impl TryFrom<EmbedColourEnum> for Colour {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub struct RGBColour {
     #[serde(alias = "r")]
     red: u8,
@@ -168,20 +411,21 @@ pub struct RGBColour {
     blue: u8,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 #[serde(untagged)]
 pub enum EmbedFieldEnum {
     Single(EmbedField),
     Vector(Vec<EmbedField>),
 }
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub struct EmbedField {
     name: String,
     value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     inline: Option<bool>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 #[serde(untagged)]
 pub enum EmbedFooterEnum {
     TextOnly(String),
@@ -207,9 +451,11 @@ impl From<EmbedFooterEnum> for CreateEmbedFooter {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub struct EmbedFooter {
+    #[serde(skip_serializing_if = "Option::is_none")]
     text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     url: Option<String>,
 }
 
@@ -222,14 +468,16 @@ impl From<EmbedFooter> for CreateEmbedFooter {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 pub struct EmbedAuthor {
     #[serde(alias = "n")]
     name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "u")]
     #[serde(alias = "url")]
     #[serde(alias = "l")]
     link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(alias = "i")]
     icon: Option<String>,
 }
@@ -280,13 +528,18 @@ mod tests {
         let deserialized: Message = json5::from_str(input).unwrap();
         let expected = Message {
             content: None,
-            embed: Some(Embed {
+            embed: Some(EmbedEnum::Single(Box::new(Embed {
+                title: None,
+                url: None,
                 colour: None,
                 description: Some("My Description".to_string()),
                 field: None,
                 footer: None,
                 author: None,
-            }),
+                timestamp: None,
+                thumbnail: None,
+                image: None,
+            }))),
         };
         assert_eq!(expected, deserialized);
     }
@@ -296,11 +549,16 @@ mod tests {
         let input = r#"{"colour": "RED"}"#;
         let deserialized: Embed = json5::from_str(input).unwrap();
         let expected = Embed {
+            title: None,
+            url: None,
             colour: Some(EmbedColourEnum::String("RED".to_string())),
             description: None,
             field: None,
             footer: None,
             author: None,
+            timestamp: None,
+            thumbnail: None,
+            image: None,
         };
         assert_eq!(expected, deserialized);
     }
@@ -330,7 +588,9 @@ mod tests {
         let deserialized = json5::from_str::<Message>(input);
         let expected = Message {
             content: Some("Content".to_string()),
-            embed: Some(Embed {
+            embed: Some(EmbedEnum::Single(Box::new(Embed {
+                title: None,
+                url: None,
                 colour: Some(EmbedColourEnum::String("RED".to_string())),
                 description: Some("Description".to_string()),
                 field: Some(EmbedFieldEnum::Vector(vec![EmbedField {
@@ -344,7 +604,10 @@ mod tests {
                     link: None,
                     icon: None,
                 }),
-            }),
+                timestamp: None,
+                thumbnail: None,
+                image: None,
+            }))),
         };
         match deserialized {
             Ok(content) => assert_eq!(expected, content),
@@ -359,7 +622,9 @@ mod tests {
     fn complex_cast() {
         let input = Message {
             content: Some("Content".to_string()),
-            embed: Some(Embed {
+            embed: Some(EmbedEnum::Single(Box::new(Embed {
+                title: None,
+                url: None,
                 colour: Some(EmbedColourEnum::String("RED".to_string())),
                 description: Some("Description".to_string()),
                 field: Some(EmbedFieldEnum::Vector(vec![EmbedField {
@@ -373,7 +638,10 @@ mod tests {
                     link: None,
                     icon: None,
                 }),
-            }),
+                timestamp: None,
+                thumbnail: None,
+                image: None,
+            }))),
         };
         let mut expected = CreateMessage::default();
         expected.content("Content").embed(|e| {
@@ -388,6 +656,346 @@ mod tests {
         assert_eq!(expected.0, result.unwrap().0); // Only compare HashMap
     }
 
+    #[test]
+    fn message_with_multiple_embeds() {
+        let input = r#"{"embeds": [{"t": "First"}, {"t": "Second"}]}"#;
+        let deserialized: Message = json5::from_str(input).unwrap();
+        let expected = Message {
+            content: None,
+            embed: Some(EmbedEnum::Vector(vec![
+                Embed {
+                    title: Some("First".to_string()),
+                    ..Embed::default()
+                },
+                Embed {
+                    title: Some("Second".to_string()),
+                    ..Embed::default()
+                },
+            ])),
+        };
+        assert_eq!(expected, deserialized);
+    }
+
+    #[test]
+    fn multiple_embeds_cast() {
+        let input = Message {
+            content: None,
+            embed: Some(EmbedEnum::Vector(vec![
+                Embed {
+                    title: Some("First".to_string()),
+                    ..Embed::default()
+                },
+                Embed {
+                    description: Some("Second".to_string()),
+                    ..Embed::default()
+                },
+            ])),
+        };
+        let result = CreateMessage::try_from(input).unwrap();
+        let expected = serde_json::json!([
+            {"type": "rich", "title": "First"},
+            {"type": "rich", "description": "Second"}
+        ]);
+        assert_eq!(None, result.0.get("embed"));
+        assert_eq!(Some(&expected), result.0.get("embeds"));
+    }
+
+    #[test]
+    fn single_element_embeds_cast() {
+        let input = r#"{"embeds": [{"t": "Only"}]}"#;
+        let deserialized: Message = json5::from_str(input).unwrap();
+        let result = CreateMessage::try_from(deserialized).unwrap();
+        let mut expected = CreateMessage::default();
+        expected.embed(|e| e.title("Only"));
+        assert_eq!(expected.0, result.0);
+    }
+
+    #[test]
+    fn valid_message_passes_validation() {
+        let input = Message {
+            content: Some("c".repeat(MAX_CONTENT_LENGTH)),
+            embed: Some(EmbedEnum::Single(Box::new(Embed {
+                title: Some("t".repeat(MAX_EMBED_TITLE_LENGTH)),
+                field: Some(EmbedFieldEnum::Vector(vec![
+                    EmbedField {
+                        name: "Name".to_string(),
+                        value: "v".repeat(200),
+                        inline: None,
+                    };
+                    MAX_EMBED_FIELDS
+                ])),
+                ..Embed::default()
+            }))),
+        };
+        assert!(input.validate().is_ok());
+    }
+
+    #[test]
+    fn too_long_content() {
+        let input = Message {
+            content: Some("c".repeat(MAX_CONTENT_LENGTH + 1)),
+            embed: None,
+        };
+        match input.validate() {
+            Err(ParseError::TooLong(path, length, limit)) => {
+                assert_eq!("content", path);
+                assert_eq!(MAX_CONTENT_LENGTH + 1, length);
+                assert_eq!(MAX_CONTENT_LENGTH, limit);
+            }
+            e => panic!("Expected too long content, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn too_long_field_value_names_path() {
+        let mut fields = vec![
+            EmbedField {
+                name: "Name".to_string(),
+                value: "Value".to_string(),
+                inline: None,
+            };
+            4
+        ];
+        fields[3].value = "v".repeat(1200);
+        let input = Message {
+            content: None,
+            embed: Some(EmbedEnum::Single(Box::new(Embed {
+                field: Some(EmbedFieldEnum::Vector(fields)),
+                ..Embed::default()
+            }))),
+        };
+        let result = input.validate();
+        match &result {
+            Err(ParseError::TooLong(path, 1200, MAX_EMBED_FIELD_VALUE_LENGTH)) => {
+                assert_eq!("embed.fields[3].value", path)
+            }
+            e => panic!("Expected too long field value, got {:#?}", e),
+        };
+        assert_eq!(
+            "embed.fields[3].value: 1200 > 1024 chars",
+            result.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn too_many_fields() {
+        let field = EmbedField {
+            name: "Name".to_string(),
+            value: "Value".to_string(),
+            inline: None,
+        };
+        let input = Message {
+            content: None,
+            embed: Some(EmbedEnum::Vector(vec![
+                Embed::default(),
+                Embed {
+                    field: Some(EmbedFieldEnum::Vector(vec![field; MAX_EMBED_FIELDS + 1])),
+                    ..Embed::default()
+                },
+            ])),
+        };
+        match input.validate() {
+            Err(ParseError::TooManyEntries(path, count, MAX_EMBED_FIELDS)) => {
+                assert_eq!("embeds[1].fields", path);
+                assert_eq!(MAX_EMBED_FIELDS + 1, count);
+            }
+            e => panic!("Expected too many fields, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn too_many_embeds() {
+        let input = Message {
+            content: None,
+            embed: Some(EmbedEnum::Vector(vec![Embed::default(); MAX_EMBEDS + 1])),
+        };
+        match input.validate() {
+            Err(ParseError::TooManyEntries(path, _, MAX_EMBEDS)) => assert_eq!("embeds", path),
+            e => panic!("Expected too many embeds, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn too_long_embeds_total() {
+        let embed = Embed {
+            description: Some("d".repeat(MAX_EMBED_DESCRIPTION_LENGTH)),
+            ..Embed::default()
+        };
+        let input = Message {
+            content: None,
+            embed: Some(EmbedEnum::Vector(vec![embed; 2])),
+        };
+        match input.validate() {
+            Err(ParseError::TooLong(path, length, MAX_EMBEDS_TOTAL_LENGTH)) => {
+                assert_eq!("embeds (total)", path);
+                assert_eq!(2 * MAX_EMBED_DESCRIPTION_LENGTH, length);
+            }
+            e => panic!("Expected too long embeds, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn cast_validates_message() {
+        let input = format!(r#"{{"e": {{"t": "{}"}}}}"#, "t".repeat(300));
+        let deserialized: Message = json5::from_str(&input).unwrap();
+        let result = CreateMessage::try_from(deserialized);
+        assert!(
+            matches!(result, Err(ParseError::TooLong(path, 300, MAX_EMBED_TITLE_LENGTH)) if path == "embed.title")
+        );
+    }
+
+    #[test]
+    fn embed_from_discord() {
+        let input = serde_json::json!({
+            "type": "rich",
+            "title": "Title",
+            "description": "Description",
+            "color": 15158332,
+            "fields": [{"name": "Name", "value": "Value", "inline": true}],
+            "footer": {"text": "Footer"},
+            "author": {"name": "Author", "url": "https://example.com"},
+            "timestamp": "2021-02-04T19:58:12+00:00",
+            "thumbnail": {"url": "https://example.com/th.png", "proxy_url": "", "height": 1, "width": 1}
+        });
+        let discord_embed = serde_json::from_value::<SerenityEmbed>(input).unwrap();
+        let expected = Embed {
+            title: Some("Title".to_string()),
+            colour: Some(EmbedColourEnum::String("#E74C3C".to_string())),
+            description: Some("Description".to_string()),
+            field: Some(EmbedFieldEnum::Vector(vec![EmbedField {
+                name: "Name".to_string(),
+                value: "Value".to_string(),
+                inline: Some(true),
+            }])),
+            footer: Some(EmbedFooterEnum::TextOnly("Footer".to_string())),
+            author: Some(EmbedAuthor {
+                name: "Author".to_string(),
+                link: Some("https://example.com".to_string()),
+                icon: None,
+            }),
+            timestamp: Some("2021-02-04T19:58:12+00:00".to_string()),
+            thumbnail: Some("https://example.com/th.png".to_string()),
+            ..Embed::default()
+        };
+        assert_eq!(expected, Embed::from(&discord_embed));
+    }
+
+    #[test]
+    fn serialization_round_trip() {
+        let input = Message {
+            content: Some("Content".to_string()),
+            embed: Some(EmbedEnum::Vector(vec![
+                Embed {
+                    title: Some("Title".to_string()),
+                    colour: Some(EmbedColourEnum::String("#E74C3C".to_string())),
+                    field: Some(EmbedFieldEnum::Vector(vec![EmbedField {
+                        name: "Name".to_string(),
+                        value: "Value".to_string(),
+                        inline: None,
+                    }])),
+                    footer: Some(EmbedFooterEnum::Complex(EmbedFooter {
+                        text: Some("Footer".to_string()),
+                        url: Some("https://example.com/f.png".to_string()),
+                    })),
+                    ..Embed::default()
+                },
+                Embed {
+                    image: Some("https://example.com/i.png".to_string()),
+                    ..Embed::default()
+                },
+            ])),
+        };
+        let serialized = serde_json::to_string_pretty(&input).unwrap();
+        let deserialized = json5::from_str::<Message>(&serialized).unwrap();
+        assert_eq!(input, deserialized);
+        assert!(!serialized.contains("null"));
+    }
+
+    #[test]
+    fn embed_with_short_aliases() {
+        let input = r#"{"t": "Title", "u": "https://example.com", "ts": "now", "th": "https://example.com/th.png", "i": "https://example.com/i.png"}"#;
+        let deserialized: Embed = json5::from_str(input).unwrap();
+        let expected = Embed {
+            title: Some("Title".to_string()),
+            url: Some("https://example.com".to_string()),
+            timestamp: Some("now".to_string()),
+            thumbnail: Some("https://example.com/th.png".to_string()),
+            image: Some("https://example.com/i.png".to_string()),
+            ..Embed::default()
+        };
+        assert_eq!(expected, deserialized);
+    }
+
+    #[test]
+    fn embed_with_full_names() {
+        let input = r#"
+            {
+                title: "Title",
+                link: "https://example.com",
+                timestamp: "2021-02-04T19:58:12Z",
+                thumbnail: "https://example.com/th.png",
+                img: "https://example.com/i.png",
+            }
+        "#;
+        let deserialized: Embed = json5::from_str(input).unwrap();
+        let expected = Embed {
+            title: Some("Title".to_string()),
+            url: Some("https://example.com".to_string()),
+            timestamp: Some("2021-02-04T19:58:12Z".to_string()),
+            thumbnail: Some("https://example.com/th.png".to_string()),
+            image: Some("https://example.com/i.png".to_string()),
+            ..Embed::default()
+        };
+        assert_eq!(expected, deserialized);
+    }
+
+    #[test]
+    fn embed_extras_cast() {
+        let input = Embed {
+            title: Some("Title".to_string()),
+            url: Some("https://example.com".to_string()),
+            timestamp: Some("2021-02-04T21:58:12+02:00".to_string()),
+            thumbnail: Some("https://example.com/th.png".to_string()),
+            image: Some("https://example.com/i.png".to_string()),
+            ..Embed::default()
+        };
+        let mut expected = CreateEmbed::default();
+        expected
+            .title("Title")
+            .url("https://example.com")
+            .timestamp("2021-02-04T19:58:12+00:00")
+            .thumbnail("https://example.com/th.png")
+            .image("https://example.com/i.png");
+        let result = CreateEmbed::try_from(input).unwrap();
+        assert_eq!(expected.0, result.0);
+    }
+
+    #[rstest(
+        timestamp,
+        case::now("now"),
+        case::now_upper("NOW"),
+        case::utc("2021-02-04T19:58:12Z")
+    )]
+    fn valid_timestamp(timestamp: &str) {
+        let result = parse_timestamp(timestamp.to_string());
+        assert!(result.is_ok(), "Expected valid timestamp, got {:#?}", result);
+    }
+
+    #[rstest(
+        timestamp,
+        case::empty(""),
+        case::tomorrow("tomorrow"),
+        case::date_only("2021-02-04"),
+        case::no_timezone("2021-02-04T19:58:12")
+    )]
+    fn invalid_timestamp(timestamp: &str) {
+        let result = parse_timestamp(timestamp.to_string());
+        match result {
+            Err(ParseError::InvalidTimestamp(passed_string, _)) => assert_eq!(timestamp, passed_string),
+            e => panic!("Expected invalid timestamp, got {:#?}", e),
+        };
+    }
+
     #[rstest(
         hex,
         case::one("#1"),
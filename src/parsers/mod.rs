@@ -1 +1,7 @@
+pub mod bulk_role;
+pub mod duration;
 pub mod message;
+pub mod options;
+pub mod purge;
+pub mod role_menu;
+pub mod template;
@@ -0,0 +1,102 @@
+use crate::structures::errors::ParseError;
+
+/// Single command argument, options start with `--` like `--dry-run`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+    /// Name of the option without the dashes, in lowercase
+    Option(String),
+    Value(String),
+}
+
+impl Arg {
+    /// Error for argument the command doesn't know what to do with
+    pub fn unexpected(self) -> ParseError {
+        match self {
+            Self::Option(name) => ParseError::UnknownOption(format!("--{}", name)),
+            Self::Value(value) => ParseError::UnknownOption(value),
+        }
+    }
+}
+
+/// Splits command arguments into options and values, options can take the argument following them
+pub struct OptionArgs<I> {
+    args: I,
+}
+
+impl<I: Iterator<Item = String>> OptionArgs<I> {
+    pub fn new<A: IntoIterator<IntoIter = I>>(args: A) -> Self {
+        Self {
+            args: args.into_iter(),
+        }
+    }
+
+    /// Takes the argument following `--option`, even if it looks like an option
+    pub fn value_of(&mut self, option: &str) -> Result<String, ParseError> {
+        self.args
+            .next()
+            .ok_or_else(|| ParseError::MissingValue(format!("--{}", option)))
+    }
+}
+
+impl<I: Iterator<Item = String>> Iterator for OptionArgs<I> {
+    type Item = Arg;
+
+    fn next(&mut self) -> Option<Arg> {
+        let arg = self.args.next()?;
+        Some(match arg.strip_prefix("--") {
+            Some(option) => Arg::Option(option.to_ascii_lowercase()),
+            None => Arg::Value(arg),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn option_args(input: &str) -> OptionArgs<impl Iterator<Item = String> + '_> {
+        OptionArgs::new(input.split_whitespace().map(ToString::to_string))
+    }
+
+    #[test]
+    fn splits_options_and_values() {
+        let args = option_args("50 --Links --user 123").collect::<Vec<Arg>>();
+        let expected = vec![
+            Arg::Value("50".to_string()),
+            Arg::Option("links".to_string()),
+            Arg::Option("user".to_string()),
+            Arg::Value("123".to_string()),
+        ];
+        assert_eq!(expected, args);
+    }
+
+    #[test]
+    fn takes_option_value() {
+        let mut args = option_args("--contains --bots --links");
+        assert_eq!(Some(Arg::Option("contains".to_string())), args.next());
+        assert_eq!("--bots", args.value_of("contains").unwrap());
+        assert_eq!(Some(Arg::Option("links".to_string())), args.next());
+        assert_eq!(None, args.next());
+    }
+
+    #[test]
+    fn missing_value() {
+        match option_args("").value_of("user") {
+            Err(ParseError::MissingValue(option)) => assert_eq!("--user", option),
+            e => panic!("Expected missing value, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn unexpected_argument() {
+        match Arg::Option("everyone".to_string()).unexpected() {
+            ParseError::UnknownOption(option) => assert_eq!("--everyone", option),
+            e => panic!("Expected unknown option, got {:#?}", e),
+        };
+        match Arg::Value("6".to_string()).unexpected() {
+            ParseError::UnknownOption(value) => assert_eq!("6", value),
+            e => panic!("Expected unknown option, got {:#?}", e),
+        };
+    }
+}
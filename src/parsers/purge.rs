@@ -0,0 +1,270 @@
+use crate::{
+    parsers::options::{Arg, OptionArgs},
+    structures::{
+        autoresponders::{Trigger, TriggerKind},
+        errors::ParseError,
+    },
+    utils::misc::parse_user_id,
+};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serenity::model::id::{MessageId, UserId};
+
+/// Discord refuses to bulk delete messages older than 14 days, the margin covers slow purges
+const BULK_DELETE_MAX_AGE_HOURS: i64 = 14 * 24 - 1;
+
+/// Which messages `purge` deletes, message has to match every given filter
+#[derive(Clone, Debug, Default)]
+pub struct PurgeFilter {
+    /// Message has to be from one of these users, unless it's empty
+    pub users: Vec<UserId>,
+    pub bots: bool,
+    /// Content has to match all of these
+    pub content: Vec<Trigger>,
+    pub attachments: bool,
+    pub links: bool,
+}
+
+impl PurgeFilter {
+    pub fn matches(&self, author_id: UserId, is_bot: bool, content: &str, has_attachments: bool) -> bool {
+        (self.users.is_empty() || self.users.contains(&author_id))
+            && (!self.bots || is_bot)
+            && self.content.iter().all(|trigger| trigger.matches(content))
+            && (!self.attachments || has_attachments)
+            && (!self.links || has_link(content))
+    }
+
+    /// Filters in words, like `from <@123>, containing "spam"`, empty when there are none
+    pub fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.users.is_empty() {
+            let users = self
+                .users
+                .iter()
+                .map(|user_id| format!("<@{}>", user_id))
+                .collect::<Vec<String>>();
+            parts.push(format!("from {}", users.join(", ")));
+        }
+        if self.bots {
+            parts.push("by bots".to_string());
+        }
+        for trigger in &self.content {
+            match trigger.kind() {
+                TriggerKind::Regex => parts.push(format!("matching `{}`", trigger.pattern())),
+                _ => parts.push(format!("containing \"{}\"", trigger.pattern())),
+            }
+        }
+        if self.attachments {
+            parts.push("with attachments".to_string());
+        }
+        if self.links {
+            parts.push("with links".to_string());
+        }
+        parts.join(", ")
+    }
+}
+
+fn has_link(content: &str) -> bool {
+    let content = content.to_lowercase();
+    content.contains("http://") || content.contains("https://")
+}
+
+/// Arguments of `purge` command, like `50 --user @Spammer --links --before 12345678`
+#[derive(Clone, Debug)]
+pub struct PurgeArgs {
+    /// How many matching messages get deleted
+    pub count: u64,
+    pub filter: PurgeFilter,
+    /// Only messages sent before this one are deleted
+    pub before: Option<MessageId>,
+    /// Only messages sent after this one are deleted
+    pub after: Option<MessageId>,
+}
+
+impl PurgeArgs {
+    /// First argument which isn't an option is the number of messages.
+    /// `--user` can be repeated, `--contains` and `--regex` take a single (quoted) argument.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ParseError> {
+        let mut count = None;
+        let mut filter = PurgeFilter::default();
+        let mut before = None;
+        let mut after = None;
+
+        let mut args = OptionArgs::new(args);
+        while let Some(arg) = args.next() {
+            let option = match arg {
+                Arg::Value(value) if count.is_none() => {
+                    count = Some(
+                        value
+                            .parse::<u64>()
+                            .map_err(|e| ParseError::InvalidNumber(value.clone(), e))?,
+                    );
+                    continue;
+                }
+                Arg::Option(option) => option,
+                arg => return Err(arg.unexpected()),
+            };
+            match option.as_str() {
+                "bots" => filter.bots = true,
+                "attachments" | "files" => filter.attachments = true,
+                "links" => filter.links = true,
+                "user" => {
+                    let value = args.value_of(&option)?;
+                    filter
+                        .users
+                        .push(parse_user_id(&value).ok_or(ParseError::InvalidUser(value))?);
+                }
+                "contains" => filter
+                    .content
+                    .push(Trigger::new(TriggerKind::Contains, &args.value_of(&option)?)?),
+                "regex" => filter
+                    .content
+                    .push(Trigger::new(TriggerKind::Regex, &args.value_of(&option)?)?),
+                "before" => before = Some(parse_message_id(&args.value_of(&option)?)?),
+                "after" => after = Some(parse_message_id(&args.value_of(&option)?)?),
+                _ => return Err(Arg::Option(option).unexpected()),
+            }
+        }
+
+        Ok(Self {
+            count: count.ok_or_else(|| ParseError::MissingValue("number of messages".to_string()))?,
+            filter,
+            before,
+            after,
+        })
+    }
+}
+
+fn parse_message_id(value: &str) -> Result<MessageId, ParseError> {
+    value
+        .parse::<u64>()
+        .map(MessageId)
+        .map_err(|e| ParseError::InvalidNumber(value.to_string(), e))
+}
+
+/// Whether message is recent enough to be deleted in bulk
+pub fn is_bulk_deletable(message_id: MessageId, now: DateTime<Utc>) -> bool {
+    now.signed_duration_since(message_id.created_at()) < ChronoDuration::hours(BULK_DELETE_MAX_AGE_HOURS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn parse(input: &str) -> Result<PurgeArgs, ParseError> {
+        PurgeArgs::parse(input.split_whitespace().map(ToString::to_string))
+    }
+
+    #[test]
+    fn parses_count_only() {
+        let parsed = parse("50").unwrap();
+        assert_eq!(50, parsed.count);
+        assert_eq!("", parsed.filter.describe());
+        assert_eq!(None, parsed.before);
+        assert_eq!(None, parsed.after);
+    }
+
+    #[test]
+    fn parses_all_options() {
+        let input = "--user <@!123> 20 --user 456 --bots --contains Spam --regex ^a+$ --attachments --links \
+                     --before 900 --after 100";
+        let parsed = parse(input).unwrap();
+        assert_eq!(20, parsed.count);
+        assert_eq!(vec![UserId(123), UserId(456)], parsed.filter.users);
+        assert!(parsed.filter.bots);
+        assert!(parsed.filter.attachments);
+        assert!(parsed.filter.links);
+        assert_eq!(Some(MessageId(900)), parsed.before);
+        assert_eq!(Some(MessageId(100)), parsed.after);
+        assert_eq!(
+            "from <@123>, <@456>, by bots, containing \"spam\", matching `^a+$`, with attachments, with links",
+            parsed.filter.describe()
+        );
+    }
+
+    #[test]
+    fn requires_count() {
+        match parse("--bots --links") {
+            Err(ParseError::MissingValue(value)) => assert_eq!("number of messages", value),
+            e => panic!("Expected missing value, got {:#?}", e),
+        };
+    }
+
+    #[rstest(input, case::count("many"), case::message_id("5 --before latest"))]
+    fn invalid_number(input: &str) {
+        match parse(input) {
+            Err(ParseError::InvalidNumber(..)) => {}
+            e => panic!("Expected invalid number, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn invalid_user() {
+        match parse("5 --user Bob") {
+            Err(ParseError::InvalidUser(user)) => assert_eq!("Bob", user),
+            e => panic!("Expected invalid user, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn invalid_regex() {
+        match parse("5 --regex (") {
+            Err(ParseError::InvalidRegex(..)) => {}
+            e => panic!("Expected invalid regex, got {:#?}", e),
+        };
+    }
+
+    #[rstest(
+        author_id,
+        is_bot,
+        content,
+        has_attachments,
+        expected,
+        case::matches(1, true, "Buy SPAM at https://spam.example", true, true),
+        case::other_user(3, true, "Buy SPAM at https://spam.example", true, false),
+        case::human(1, false, "Buy SPAM at https://spam.example", true, false),
+        case::no_keyword(1, true, "Buy at https://shop.example", true, false),
+        case::no_link(1, true, "Buy SPAM here", true, false),
+        case::no_attachment(1, true, "Buy SPAM at https://spam.example", false, false)
+    )]
+    fn matches_filters(author_id: u64, is_bot: bool, content: &str, has_attachments: bool, expected: bool) {
+        let filter = PurgeFilter {
+            users: vec![UserId(1), UserId(2)],
+            bots: true,
+            content: vec![Trigger::new(TriggerKind::Contains, "spam").unwrap()],
+            attachments: true,
+            links: true,
+        };
+        assert_eq!(
+            expected,
+            filter.matches(UserId(author_id), is_bot, content, has_attachments)
+        );
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        assert!(PurgeFilter::default().matches(UserId(1), false, "", false));
+    }
+
+    /// Id of message sent at given time, Discord epoch is the start of 2015
+    fn message_id_at(time: DateTime<Utc>) -> MessageId {
+        let discord_epoch = Utc.ymd(2015, 1, 1).and_hms(0, 0, 0);
+        MessageId((time.signed_duration_since(discord_epoch).num_milliseconds() as u64) << 22)
+    }
+
+    #[rstest(
+        days_ago,
+        expected,
+        case::fresh(0, true),
+        case::almost_too_old(13, true),
+        case::too_old(14, false),
+        case::ancient(400, false)
+    )]
+    fn bulk_deletable_age(days_ago: i64, expected: bool) {
+        let now = Utc.ymd(2021, 3, 1).and_hms(12, 0, 0);
+        let message_id = message_id_at(now - ChronoDuration::days(days_ago));
+        assert_eq!(expected, is_bulk_deletable(message_id, now));
+    }
+}
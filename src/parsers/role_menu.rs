@@ -0,0 +1,176 @@
+use crate::{
+    parsers::message::{check_count, Embed, EmbedColourEnum, EmbedEnum, Message},
+    structures::errors::ParseError,
+};
+use serde::Deserialize;
+use serenity::{model::id::RoleId, prelude::Mentionable};
+use std::collections::HashSet;
+
+/// Discord allows only this many different reactions on a message
+pub const MAX_MENU_ENTRIES: usize = 20;
+
+/// Reaction role menu, posted as an embed listing its emojis and roles
+/// Example:
+/// ```
+/// {
+///   title: "Colours",
+///   description: "Pick your favourite colour",
+///   mode: "toggle",
+///   roles: [
+///     { emoji: "🔴", role: "Red" },
+///     { emoji: "🔵", role: "Blue" },
+///   ]
+/// }
+/// ```
+#[derive(Deserialize, Debug, PartialEq, Default, Clone)]
+pub struct RoleMenu {
+    #[serde(alias = "t")]
+    pub title: Option<String>,
+    #[serde(alias = "d")]
+    pub description: Option<String>,
+    #[serde(alias = "c")]
+    #[serde(alias = "color")]
+    pub colour: Option<EmbedColourEnum>,
+    /// Reaction role mode used for all roles, like `toggle` or `keep-reaction`
+    #[serde(alias = "m")]
+    pub mode: Option<String>,
+    #[serde(alias = "r")]
+    #[serde(alias = "entries")]
+    pub roles: Vec<RoleMenuEntry>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+pub struct RoleMenuEntry {
+    #[serde(alias = "e")]
+    #[serde(alias = "reaction")]
+    pub emoji: String,
+    /// Role name or mention
+    #[serde(alias = "r")]
+    pub role: String,
+}
+
+impl RoleMenu {
+    pub fn validate(&self) -> Result<(), ParseError> {
+        if self.roles.is_empty() {
+            return Err(ParseError::NoEntries("roles".to_string()));
+        }
+        check_count("roles", self.roles.len(), MAX_MENU_ENTRIES)?;
+        let mut emojis = HashSet::new();
+        for entry in &self.roles {
+            if !emojis.insert(entry.emoji.as_str()) {
+                return Err(ParseError::DuplicateEntry(
+                    "roles".to_string(),
+                    entry.emoji.clone(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Message to post, `role_ids` are the resolved `roles`, in the same order
+    pub fn to_message(&self, role_ids: &[RoleId]) -> Message {
+        let listing = self
+            .roles
+            .iter()
+            .zip(role_ids)
+            .map(|(entry, role_id)| format!("{} {}", entry.emoji, role_id.mention()))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let description = match &self.description {
+            Some(description) => format!("{}\n\n{}", description, listing),
+            None => listing,
+        };
+        let embed = Embed {
+            title: Some(self.title.clone().unwrap_or_else(|| "Reaction roles".to_string())),
+            colour: self.colour.clone(),
+            description: Some(description),
+            ..Default::default()
+        };
+        Message {
+            content: None,
+            embed: Some(EmbedEnum::Single(Box::new(embed))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn entry(emoji: &str, role: &str) -> RoleMenuEntry {
+        RoleMenuEntry {
+            emoji: emoji.to_string(),
+            role: role.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_with_aliases() {
+        let input = r#"{t: "Colours", d: "Pick one", m: "verify", r: [{e: "🔴", r: "Red"}, {emoji: "🔵", role: "Blue"}]}"#;
+        let expected = RoleMenu {
+            title: Some("Colours".to_string()),
+            description: Some("Pick one".to_string()),
+            colour: None,
+            mode: Some("verify".to_string()),
+            roles: vec![entry("🔴", "Red"), entry("🔵", "Blue")],
+        };
+        assert_eq!(expected, json5::from_str::<RoleMenu>(input).unwrap());
+    }
+
+    #[test]
+    fn requires_roles() {
+        let menu = RoleMenu::default();
+        match menu.validate() {
+            Err(ParseError::NoEntries(path)) => assert_eq!("roles", path),
+            e => panic!("Expected no entries error, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn rejects_duplicate_emojis() {
+        let menu = RoleMenu {
+            roles: vec![entry("🔴", "Red"), entry("🔴", "Crimson")],
+            ..Default::default()
+        };
+        match menu.validate() {
+            Err(ParseError::DuplicateEntry(_, emoji)) => assert_eq!("🔴", emoji),
+            e => panic!("Expected duplicate entry error, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn rejects_too_many_roles() {
+        let menu = RoleMenu {
+            roles: (0..=MAX_MENU_ENTRIES)
+                .map(|idx| entry(&idx.to_string(), "Role"))
+                .collect(),
+            ..Default::default()
+        };
+        match menu.validate() {
+            Err(ParseError::TooManyEntries(_, count, MAX_MENU_ENTRIES)) => {
+                assert_eq!(MAX_MENU_ENTRIES + 1, count)
+            }
+            e => panic!("Expected too many entries error, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn lists_roles_in_description() {
+        let menu = RoleMenu {
+            description: Some("Pick one".to_string()),
+            roles: vec![entry("🔴", "Red"), entry("🔵", "Blue")],
+            ..Default::default()
+        };
+        let message = menu.to_message(&[RoleId(1), RoleId(2)]);
+        let embed = match message.embed {
+            Some(EmbedEnum::Single(embed)) => embed,
+            e => panic!("Expected single embed, got {:#?}", e),
+        };
+        assert_eq!(Some("Reaction roles".to_string()), embed.title);
+        assert_eq!(
+            Some("Pick one\n\n🔴 <@&1>\n🔵 <@&2>".to_string()),
+            embed.description
+        );
+    }
+}
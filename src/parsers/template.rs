@@ -0,0 +1,228 @@
+use crate::structures::errors::{ParseError, TemplateError};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Values which can be referenced from templates, like `{user.mention}`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TemplateVariables {
+    variables: HashMap<String, String>,
+    args: Vec<String>,
+}
+
+impl TemplateVariables {
+    /// Arguments come from whoever invoked the command, so they can't mention everyone or roles through the bot
+    pub fn new(args: Vec<String>) -> Self {
+        let args = args
+            .iter()
+            .map(|arg| escape_mass_mentions(arg))
+            .collect::<Vec<String>>();
+        let mut variables = HashMap::new();
+        variables.insert("args".to_string(), args.join(" "));
+        Self { variables, args }
+    }
+
+    pub fn set<K: ToString, V: ToString>(&mut self, name: K, value: V) -> &mut Self {
+        self.variables.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Looks up variable by its name.
+    /// Positional arguments (`arg1`, `arg2`, ...) that weren't passed are empty.
+    pub fn get(&self, name: &str) -> Option<String> {
+        if let Some(value) = self.variables.get(name) {
+            return Some(value.clone());
+        }
+        let position = name.strip_prefix("arg")?.parse::<usize>().ok()?;
+        if position == 0 {
+            return None;
+        }
+        Some(self.args.get(position - 1).cloned().unwrap_or_default())
+    }
+}
+
+/// Breaks `@everyone`, `@here` and role mentions with a zero width space, so they don't ping anyone
+fn escape_mass_mentions(value: &str) -> String {
+    value
+        .replace("@everyone", "@\u{200b}everyone")
+        .replace("@here", "@\u{200b}here")
+        .replace("<@&", "<@\u{200b}&")
+}
+
+/// Replaces `{variable}` placeholders in `template`.
+/// Literal braces can be written as `{{` and `}}`.
+pub fn render(template: &str, variables: &TemplateVariables) -> Result<String, TemplateError> {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '{' if matches!(chars.peek(), Some((_, '{'))) => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if matches!(chars.peek(), Some((_, '}'))) => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '}')) => break,
+                        Some((_, c)) => name.push(c),
+                        None => return Err(TemplateError::UnclosedPlaceholder(idx)),
+                    }
+                }
+                let name = name.trim();
+                let value = variables
+                    .get(name)
+                    .ok_or_else(|| TemplateError::UnknownVariable(name.to_string()))?;
+                result.push_str(&value);
+            }
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+/// Like `render`, but for json5 messages - only string values get expanded,
+/// so substituted values can't break the message structure.
+pub fn render_rich(serialized_message: &str, variables: &TemplateVariables) -> Result<String, ParseError> {
+    let mut value = json5::from_str::<Value>(serialized_message).map_err(ParseError::InvalidJson)?;
+    render_value(&mut value, variables)?;
+    Ok(value.to_string())
+}
+
+fn render_value(value: &mut Value, variables: &TemplateVariables) -> Result<(), TemplateError> {
+    match value {
+        Value::String(string) => *string = render(string, variables)?,
+        Value::Array(values) => {
+            for v in values {
+                render_value(v, variables)?;
+            }
+        }
+        Value::Object(map) => {
+            for v in map.values_mut() {
+                render_value(v, variables)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Whether content begins with a placeholder, like `{user} joined!`.
+/// Such content is plain text, even though it starts with a brace like json5 does.
+pub fn starts_with_placeholder(content: &str) -> bool {
+    content
+        .strip_prefix('{')
+        .and_then(|rest| rest.split_once('}'))
+        .map(|(name, _)| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn variables() -> TemplateVariables {
+        let mut variables = TemplateVariables::new(vec!["first".to_string(), "second".to_string()]);
+        variables
+            .set("user", "Bob")
+            .set("user.mention", "<@1234>")
+            .set("guild.name", "Stone");
+        variables
+    }
+
+    #[rstest(
+        template,
+        expected,
+        case::no_placeholders("Hello there", "Hello there"),
+        case::single("Hello {user}", "Hello Bob"),
+        case::dotted("Hi {user.mention}!", "Hi <@1234>!"),
+        case::padded("Hi { user }", "Hi Bob"),
+        case::multiple("{user} joined {guild.name}", "Bob joined Stone"),
+        case::args("{args}", "first second"),
+        case::positional("{arg2} {arg1}", "second first"),
+        case::missing_positional("[{arg3}]", "[]"),
+        case::escaped("{{user}} is {user}", "{user} is Bob"),
+        case::closing_escaped("}} and }", "} and }"),
+        case::unicode("Zażółć {user}", "Zażółć Bob")
+    )]
+    fn renders(template: &str, expected: &str) {
+        assert_eq!(expected, render(template, &variables()).unwrap());
+    }
+
+    #[rstest(
+        arg,
+        expected,
+        case::everyone("@everyone", "@\u{200b}everyone"),
+        case::here("hey @here!", "hey @\u{200b}here!"),
+        case::role("<@&42>", "<@\u{200b}&42>"),
+        case::user("<@!42>", "<@!42>")
+    )]
+    fn args_cant_mass_mention(arg: &str, expected: &str) {
+        let variables = TemplateVariables::new(vec![arg.to_string()]);
+        assert_eq!(expected, render("{arg1}", &variables).unwrap());
+        assert_eq!(expected, render("{args}", &variables).unwrap());
+    }
+
+    #[rstest(
+        template,
+        name,
+        case::unknown("{nope}", "nope"),
+        case::arg_zero("{arg0}", "arg0"),
+        case::empty("{}", "")
+    )]
+    fn unknown_variable(template: &str, name: &str) {
+        match render(template, &variables()) {
+            Err(TemplateError::UnknownVariable(passed_name)) => assert_eq!(name, passed_name),
+            e => panic!("Expected unknown variable, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn unclosed_placeholder() {
+        match render("Hi {user", &variables()) {
+            Err(TemplateError::UnclosedPlaceholder(3)) => {}
+            e => panic!("Expected unclosed placeholder, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn renders_rich_strings_only() {
+        let input = r#"{content: "Hi {user}", embed: {d: "Said \"{args}\""}}"#;
+        let rendered = render_rich(input, &variables()).unwrap();
+        let expected = serde_json::json!({"content": "Hi Bob", "embed": {"d": "Said \"first second\""}});
+        assert_eq!(expected, serde_json::from_str::<Value>(&rendered).unwrap());
+    }
+
+    #[test]
+    fn rendered_rich_values_are_escaped() {
+        let mut variables = variables();
+        variables.set("user", r#"Bob", "embed": "#);
+        let rendered = render_rich(r#"{content: "{user}"}"#, &variables).unwrap();
+        let expected = serde_json::json!({"content": r#"Bob", "embed": "#});
+        assert_eq!(expected, serde_json::from_str::<Value>(&rendered).unwrap());
+    }
+
+    #[rstest(
+        content,
+        expected,
+        case::placeholder("{user} joined", true),
+        case::dotted_placeholder("{user.mention}", true),
+        case::json("{content: \"Hi\"}", false),
+        case::json_no_spaces("{c:\"{user}\"}", false),
+        case::empty_braces("{}", false),
+        case::plain("Hello", false)
+    )]
+    fn detects_leading_placeholder(content: &str, expected: bool) {
+        assert_eq!(expected, starts_with_placeholder(content));
+    }
+}
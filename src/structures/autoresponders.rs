@@ -0,0 +1,100 @@
+use crate::structures::errors::ParseError;
+use regex::{Regex, RegexBuilder};
+use strum_macros::{AsRefStr, EnumString};
+
+/// Keeps compiled user provided regexes reasonably small
+const MAX_REGEX_SIZE: usize = 1 << 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum TriggerKind {
+    Contains,
+    Exact,
+    Regex,
+}
+
+/// What message content makes an autoresponder respond.
+/// `Contains` and `Exact` ignore letter case, regexes are used as written.
+#[derive(Clone, Debug)]
+pub enum Trigger {
+    Contains(String),
+    Exact(String),
+    Regex(Regex),
+}
+
+impl Trigger {
+    pub fn new(kind: TriggerKind, pattern: &str) -> Result<Self, ParseError> {
+        Ok(match kind {
+            TriggerKind::Contains => Self::Contains(pattern.to_lowercase()),
+            TriggerKind::Exact => Self::Exact(pattern.trim().to_lowercase()),
+            TriggerKind::Regex => Self::Regex(
+                RegexBuilder::new(pattern)
+                    .size_limit(MAX_REGEX_SIZE)
+                    .build()
+                    .map_err(|e| ParseError::InvalidRegex(pattern.to_string(), e))?,
+            ),
+        })
+    }
+
+    pub fn kind(&self) -> TriggerKind {
+        match self {
+            Self::Contains(_) => TriggerKind::Contains,
+            Self::Exact(_) => TriggerKind::Exact,
+            Self::Regex(_) => TriggerKind::Regex,
+        }
+    }
+
+    pub fn pattern(&self) -> &str {
+        match self {
+            Self::Contains(pattern) | Self::Exact(pattern) => pattern,
+            Self::Regex(regex) => regex.as_str(),
+        }
+    }
+
+    pub fn matches(&self, content: &str) -> bool {
+        match self {
+            Self::Contains(keyword) => content.to_lowercase().contains(keyword.as_str()),
+            Self::Exact(phrase) => content.trim().to_lowercase() == *phrase,
+            Self::Regex(regex) => regex.is_match(content),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest(
+        kind,
+        pattern,
+        content,
+        expected,
+        case::contains("contains", "hello", "Well, HELLO there", true),
+        case::contains_missing("contains", "hello", "Hi there", false),
+        case::exact("exact", "Good Morning", "  good morning ", true),
+        case::exact_with_more("exact", "good morning", "good morning all", false),
+        case::regex(r"regex", r"^!?ping\b", "ping pong", true),
+        case::regex_case_sensitive("regex", "^ping", "Ping", false),
+        case::regex_flags("regex", "(?i)^ping", "Ping", true)
+    )]
+    fn matches(kind: &str, pattern: &str, content: &str, expected: bool) {
+        let kind = kind.parse::<TriggerKind>().unwrap();
+        let trigger = Trigger::new(kind, pattern).unwrap();
+        assert_eq!(expected, trigger.matches(content));
+        assert_eq!(kind, trigger.kind());
+    }
+
+    #[test]
+    fn invalid_regex() {
+        match Trigger::new(TriggerKind::Regex, "(unclosed") {
+            Err(ParseError::InvalidRegex(pattern, _)) => assert_eq!("(unclosed", pattern),
+            e => panic!("Expected invalid regex, got {:#?}", e),
+        };
+    }
+
+    #[test]
+    fn oversized_regex() {
+        assert!(Trigger::new(TriggerKind::Regex, r"\w{1000}\w{1000}").is_err());
+    }
+}
@@ -1,11 +1,11 @@
-use crate::commands::{admin::*, config::*, meta::*, owner::*, support::*};
+use crate::commands::{admin::*, config::*, message_log::*, meta::*, moderation::*, owner::*, support::*};
 use serenity::framework::standard::macros::group;
 
 // All command groups
 // Doesn't currently work as hoped for - see conversation from serenity discord:
 // https://discordapp.com/channels/381880193251409931/381912587505500160/754058417420632236
 #[group]
-#[sub_groups(General, Config, Support, Owner, Admin)]
+#[sub_groups(General, Config, Support, Owner, Admin, Moderation)]
 pub struct All;
 
 #[group]
@@ -13,13 +13,33 @@ pub struct All;
 pub struct General;
 
 #[group]
-#[commands(prefix, command, join_role)]
+#[commands(prefix, command, autoresponder, join_role, sticky_role, welcome)]
 pub struct Config;
 
 #[group]
-#[commands(message, reaction_role, bulk_role, react)]
+#[commands(message, reaction_role, bulk_role, job, react)]
 pub struct Admin;
 
+#[group]
+#[commands(
+    warn,
+    kick,
+    ban,
+    softban,
+    unban,
+    mute,
+    unmute,
+    temprole,
+    mute_role,
+    mod_log,
+    message_log,
+    purge,
+    case,
+    infractions,
+    reason
+)]
+pub struct Moderation;
+
 #[group]
 #[commands(support, info)]
 pub struct Support;
@@ -0,0 +1,279 @@
+use serenity::prelude::{Mutex, TypeMapKey};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use strum_macros::{AsRefStr, EnumString};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum CooldownScope {
+    Guild,
+    User,
+    Channel,
+}
+
+/// What is being rate limited, ids of custom commands and autoresponders can overlap
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CooldownSubject {
+    Command(i64),
+    Autoresponder(i64),
+}
+
+/// Cooldown applied to a command within a single scope,
+/// for example 30 seconds for user with given id.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cooldown {
+    pub scope: CooldownScope,
+    pub id: u64,
+    pub duration: Duration,
+}
+
+/// In-memory rate limiter for custom commands and autoresponders
+#[derive(Debug, Default)]
+pub struct CommandCooldowns {
+    expiries: Mutex<HashMap<(CooldownSubject, CooldownScope, u64), Instant>>,
+    /// Until when user was told about the cooldown in a channel, keyed by what is on cooldown, user and channel ids
+    notices: Mutex<HashMap<(CooldownSubject, u64, u64), Instant>>,
+}
+
+impl CommandCooldowns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks whether command can be used right now and if so, starts all of its cooldowns.
+    /// Otherwise returns how long until it can be used again.
+    pub async fn try_use(
+        &self,
+        subject: CooldownSubject,
+        cooldowns: &[Cooldown],
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut expiries = self.expiries.lock().await;
+        // Forget expired entries, so the map doesn't keep growing
+        expiries.retain(|_, expiry| *expiry > now);
+
+        let remaining = cooldowns
+            .iter()
+            .filter_map(|c| expiries.get(&(subject, c.scope, c.id)))
+            .map(|expiry| *expiry - now)
+            .max();
+        if let Some(remaining) = remaining {
+            return Err(remaining);
+        }
+
+        for cooldown in cooldowns.iter().filter(|c| c.duration > Duration::from_secs(0)) {
+            expiries.insert((subject, cooldown.scope, cooldown.id), now + cooldown.duration);
+        }
+        Ok(())
+    }
+
+    /// Whether user should be told they're on cooldown, which happens once per cooldown of each subject in a channel.
+    /// `remaining` is how long the cooldown lasts.
+    pub async fn should_notify(
+        &self,
+        subject: CooldownSubject,
+        user_id: u64,
+        channel_id: u64,
+        remaining: Duration,
+        now: Instant,
+    ) -> bool {
+        let mut notices = self.notices.lock().await;
+        notices.retain(|_, until| *until > now);
+        if notices.contains_key(&(subject, user_id, channel_id)) {
+            return false;
+        }
+        notices.insert((subject, user_id, channel_id), now + remaining);
+        true
+    }
+}
+
+impl TypeMapKey for CommandCooldowns {
+    type Value = Arc<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn cooldowns(user_id: u64) -> Vec<Cooldown> {
+        vec![
+            Cooldown {
+                scope: CooldownScope::User,
+                id: user_id,
+                duration: Duration::from_secs(30),
+            },
+            Cooldown {
+                scope: CooldownScope::Channel,
+                id: 1,
+                duration: Duration::from_secs(10),
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn blocks_until_longest_cooldown_expires() {
+        let limiter = CommandCooldowns::new();
+        let start = Instant::now();
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(CooldownSubject::Command(1), &cooldowns(7), start)
+                .await
+        );
+        assert_eq!(
+            Err(Duration::from_secs(25)),
+            limiter
+                .try_use(
+                    CooldownSubject::Command(1),
+                    &cooldowns(7),
+                    start + Duration::from_secs(5)
+                )
+                .await
+        );
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(
+                    CooldownSubject::Command(1),
+                    &cooldowns(7),
+                    start + Duration::from_secs(30)
+                )
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn shared_scope_blocks_other_users() {
+        let limiter = CommandCooldowns::new();
+        let start = Instant::now();
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(CooldownSubject::Command(1), &cooldowns(7), start)
+                .await
+        );
+        // Channel cooldown is shared, so other user has to wait for it too
+        assert_eq!(
+            Err(Duration::from_secs(9)),
+            limiter
+                .try_use(
+                    CooldownSubject::Command(1),
+                    &cooldowns(8),
+                    start + Duration::from_secs(1)
+                )
+                .await
+        );
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(
+                    CooldownSubject::Command(1),
+                    &cooldowns(8),
+                    start + Duration::from_secs(10)
+                )
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn commands_have_separate_cooldowns() {
+        let limiter = CommandCooldowns::new();
+        let start = Instant::now();
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(CooldownSubject::Command(1), &cooldowns(7), start)
+                .await
+        );
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(CooldownSubject::Command(2), &cooldowns(7), start)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn autoresponders_dont_share_command_cooldowns() {
+        let limiter = CommandCooldowns::new();
+        let start = Instant::now();
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(CooldownSubject::Command(1), &cooldowns(7), start)
+                .await
+        );
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(CooldownSubject::Autoresponder(1), &cooldowns(7), start)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn zero_duration_never_blocks() {
+        let limiter = CommandCooldowns::new();
+        let start = Instant::now();
+        let no_cooldown = vec![Cooldown {
+            scope: CooldownScope::Guild,
+            id: 1,
+            duration: Duration::from_secs(0),
+        }];
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(CooldownSubject::Command(1), &no_cooldown, start)
+                .await
+        );
+        assert_eq!(
+            Ok(()),
+            limiter
+                .try_use(CooldownSubject::Command(1), &no_cooldown, start)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn notifies_once_per_cooldown() {
+        let limiter = CommandCooldowns::new();
+        let start = Instant::now();
+        let remaining = Duration::from_secs(10);
+        let command = CooldownSubject::Command(1);
+        assert!(limiter.should_notify(command, 7, 1, remaining, start).await);
+        assert!(
+            !limiter
+                .should_notify(command, 7, 1, remaining, start + Duration::from_secs(5))
+                .await
+        );
+        // Other user or channel gets their own notice
+        assert!(limiter.should_notify(command, 8, 1, remaining, start).await);
+        assert!(limiter.should_notify(command, 7, 2, remaining, start).await);
+        assert!(
+            limiter
+                .should_notify(command, 7, 1, remaining, start + Duration::from_secs(10))
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn notifies_about_each_command() {
+        let limiter = CommandCooldowns::new();
+        let start = Instant::now();
+        let remaining = Duration::from_secs(10);
+        assert!(
+            limiter
+                .should_notify(CooldownSubject::Command(1), 7, 1, remaining, start)
+                .await
+        );
+        assert!(
+            limiter
+                .should_notify(CooldownSubject::Command(2), 7, 1, remaining, start)
+                .await
+        );
+    }
+}
@@ -1,4 +1,6 @@
+use chrono::ParseError as ChronoParseError;
 use json5::Error as Json5Error;
+use regex::Error as RegexError;
 use serenity::Error as SerenityError;
 use std::{
     error::Error,
@@ -9,7 +11,7 @@ use thiserror::Error as ThisError;
 
 #[derive(ThisError, Debug)]
 pub enum BotstoneError {
-    #[error("error parsing")]
+    #[error("error parsing: {0}")]
     ParseError(#[from] ParseError),
     #[error("database error")]
     DatabaseError(#[from] DatabaseError),
@@ -27,12 +29,36 @@ pub enum BotstoneError {
 pub enum ParseError {
     #[error(transparent)]
     ColourParseError(#[from] ColourParseError),
+    #[error(transparent)]
+    TemplateError(#[from] TemplateError),
     #[error("invalid number `{0:?}`, cause by `{1:?}`")]
     InvalidNumber(String, ParseIntError),
     #[error("invalid json `{0:?}`")]
     InvalidJson(Json5Error),
     #[error("invalid role mention: {0:?}")]
     InvalidRoleMention(String),
+    #[error("invalid user `{0}`, expected mention or id")]
+    InvalidUser(String),
+    #[error("invalid regex `{0:?}`, caused by: {1}")]
+    InvalidRegex(String, RegexError),
+    #[error("invalid duration `{0}`, expected something like `90s`, `30m`, `2d` or `1h30m`")]
+    InvalidDuration(String),
+    #[error("invalid date `{0}`, expected `YYYY-MM-DD` or how long ago, like `30d`")]
+    InvalidDate(String),
+    #[error("invalid timestamp `{0:?}`, caused by `{1:?}`")]
+    InvalidTimestamp(String, ChronoParseError),
+    #[error("{0}: {1} > {2} chars")]
+    TooLong(String, usize, usize),
+    #[error("{0}: {1} > {2} entries")]
+    TooManyEntries(String, usize, usize),
+    #[error("{0}: at least one entry is required")]
+    NoEntries(String),
+    #[error("{0}: duplicate entry `{1}`")]
+    DuplicateEntry(String, String),
+    #[error("unknown option `{0}`")]
+    UnknownOption(String),
+    #[error("missing value of `{0}`")]
+    MissingValue(String),
 }
 
 #[derive(ThisError, Debug)]
@@ -45,12 +71,22 @@ pub enum ColourParseError {
     UnknownColourName(String),
 }
 
+#[derive(ThisError, Debug)]
+pub enum TemplateError {
+    #[error("unknown template variable: `{{{0}}}`")]
+    UnknownVariable(String),
+    #[error("unclosed template placeholder starting at position {0}, use `{{{{` for a literal brace")]
+    UnclosedPlaceholder(usize),
+}
+
 #[derive(ThisError, Debug)]
 pub enum DatabaseError {
     #[error(transparent)]
     SqlxError(#[from] sqlx::Error),
     #[error("nothing was deleted")]
     NothingDeleted,
+    #[error("invalid value of `{0}` column: {1:?}")]
+    InvalidValue(String, String),
 }
 
 #[derive(ThisError, Debug)]
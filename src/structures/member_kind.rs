@@ -0,0 +1,20 @@
+use strum_macros::{AsRefStr, EnumString};
+
+/// Whether something applies to human members, bots or both, e.g. join roles and bulk role changes
+#[derive(Clone, Copy, Debug, PartialEq, EnumString, AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum MemberKind {
+    Humans,
+    Bots,
+    Both,
+}
+
+impl MemberKind {
+    pub fn applies_to(self, is_bot: bool) -> bool {
+        match self {
+            Self::Humans => !is_bot,
+            Self::Bots => is_bot,
+            Self::Both => true,
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use crate::{
+    database::queries::{MessageLogConfig, MessageLogConfigs},
+    parsers::message::MAX_EMBED_FIELD_VALUE_LENGTH,
+    structures::errors::DatabaseError,
+    utils::{
+        defaults::DEFAULT_MOD_LOG_EMBED_COLOUR,
+        misc::{get_message_url, truncate},
+    },
+};
+use chrono::{DateTime, Utc};
+use serenity::{
+    builder::CreateEmbed,
+    model::{
+        channel::Message,
+        id::{ChannelId, GuildId, MessageId, RoleId, UserId},
+    },
+    prelude::{Mentionable, Mutex, TypeMapKey},
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+use tracing::info;
+
+/// How many messages serenity's cache keeps in each channel
+pub const MESSAGES_CACHED_PER_CHANNEL: usize = 100;
+/// How many messages are remembered in case serenity's cache no longer has them
+pub const MESSAGE_LOG_CACHE_CAPACITY: usize = 10_000;
+
+/// What is kept of a message, to show it once it gets edited or deleted
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedMessage {
+    pub id: MessageId,
+    pub channel_id: ChannelId,
+    pub author_id: UserId,
+    pub author_tag: String,
+    pub author_is_bot: bool,
+    pub content: String,
+    /// Attachment urls
+    pub attachments: Vec<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<&Message> for CachedMessage {
+    fn from(msg: &Message) -> Self {
+        Self {
+            id: msg.id,
+            channel_id: msg.channel_id,
+            author_id: msg.author.id,
+            author_tag: msg.author.tag(),
+            author_is_bot: msg.author.bot,
+            content: msg.content.clone(),
+            attachments: msg.attachments.iter().map(|a| a.url.clone()).collect(),
+            timestamp: msg.timestamp,
+        }
+    }
+}
+
+/// Keeps up to `capacity` messages, forgetting the oldest ones first
+#[derive(Debug)]
+pub struct MessageCache {
+    capacity: usize,
+    messages: HashMap<MessageId, CachedMessage>,
+    /// Ids in order of insertion
+    order: VecDeque<MessageId>,
+}
+
+impl MessageCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            messages: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn insert(&mut self, message: CachedMessage) {
+        let id = message.id;
+        if self.messages.insert(id, message).is_none() {
+            self.order.push_back(id);
+        }
+        while self.messages.len() > self.capacity {
+            match self.order.pop_front() {
+                Some(oldest) => self.messages.remove(&oldest),
+                None => break,
+            };
+        }
+    }
+
+    /// Replaces content of cached message, returning the message as it was before
+    pub fn update(&mut self, id: MessageId, content: String) -> Option<CachedMessage> {
+        let message = self.messages.get_mut(&id)?;
+        let before = message.clone();
+        message.content = content;
+        Some(before)
+    }
+
+    pub fn remove(&mut self, id: MessageId) -> Option<CachedMessage> {
+        let message = self.messages.remove(&id)?;
+        self.order.retain(|cached_id| *cached_id != id);
+        Some(message)
+    }
+}
+
+/// Logs edited and deleted messages to guild's message log channel
+#[derive(Debug)]
+pub struct MessageLog {
+    configs: MessageLogConfigs,
+    cache: Mutex<MessageCache>,
+}
+
+impl MessageLog {
+    pub fn new(configs: MessageLogConfigs, capacity: usize) -> Self {
+        Self {
+            configs,
+            cache: Mutex::new(MessageCache::new(capacity)),
+        }
+    }
+
+    pub fn configs(&self) -> &MessageLogConfigs {
+        &self.configs
+    }
+
+    pub async fn get_config(&self, guild_id: GuildId) -> Result<MessageLogConfig, DatabaseError> {
+        self.configs.get_config(guild_id).await
+    }
+
+    /// Remembers message, unless guild doesn't log it anyway
+    pub async fn remember(&self, guild_id: GuildId, msg: &Message) -> Result<(), DatabaseError> {
+        let config = self.get_config(guild_id).await?;
+        let roles = msg
+            .member
+            .as_ref()
+            .map(|member| member.roles.clone())
+            .unwrap_or_default();
+        if !msg.author.bot && log_channel(&config, msg.channel_id, &roles).is_some() {
+            self.cache.lock().await.insert(CachedMessage::from(msg));
+        }
+        Ok(())
+    }
+
+    /// Disables message log in a deleted channel, and stops ignoring it
+    pub async fn forget_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Result<(), DatabaseError> {
+        let config = self.get_config(guild_id).await?;
+        if config.channel_id == Some(channel_id) {
+            info!(
+                "Message log channel {} of {} is gone, disabling it",
+                channel_id, guild_id
+            );
+            self.configs.set_channel(guild_id, None).await?;
+        }
+        if config.ignored_channels.contains(&channel_id) {
+            self.configs.delete_ignore(guild_id, channel_id.0).await?;
+        }
+        Ok(())
+    }
+
+    /// Updates remembered message, returning how it was before
+    pub async fn update(&self, id: MessageId, content: String) -> Option<CachedMessage> {
+        self.cache.lock().await.update(id, content)
+    }
+
+    pub async fn take(&self, id: MessageId) -> Option<CachedMessage> {
+        self.cache.lock().await.remove(id)
+    }
+}
+
+impl TypeMapKey for MessageLog {
+    type Value = Arc<Self>;
+}
+
+/// Channel message should be logged to, `None` if it's ignored or guild has no message log
+pub fn log_channel(config: &MessageLogConfig, channel_id: ChannelId, roles: &[RoleId]) -> Option<ChannelId> {
+    let log_channel_id = config.channel_id?;
+    if channel_id == log_channel_id
+        || config.ignored_channels.contains(&channel_id)
+        || roles.iter().any(|role_id| config.ignored_roles.contains(role_id))
+    {
+        return None;
+    }
+    Some(log_channel_id)
+}
+
+pub fn edit_embed<'a>(
+    e: &'a mut CreateEmbed,
+    guild_id: GuildId,
+    before: Option<&CachedMessage>,
+    after: &CachedMessage,
+) -> &'a mut CreateEmbed {
+    e.colour(DEFAULT_MOD_LOG_EMBED_COLOUR);
+    e.title("Message edited");
+    e.description(format!(
+        "[Jump to message]({})",
+        get_message_url(guild_id, after.channel_id, after.id)
+    ));
+    author_fields(e, after);
+    let before = match before {
+        Some(before) => quote(&before.content),
+        None => "Unknown, message was sent before it could be cached".to_string(),
+    };
+    e.field("Before", before, false);
+    e.field("After", quote(&after.content), false);
+    e.footer(|f| f.text(format!("Message ID: {}", after.id)));
+    e.timestamp(&Utc::now());
+    e
+}
+
+/// Content of messages which weren't cached is unknown, so only their id is shown
+pub fn delete_embed<'a>(
+    e: &'a mut CreateEmbed,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    message: Option<&CachedMessage>,
+) -> &'a mut CreateEmbed {
+    e.colour(DEFAULT_MOD_LOG_EMBED_COLOUR);
+    e.title("Message deleted");
+    match message {
+        Some(message) => {
+            author_fields(e, message);
+            e.field("Content", quote(&message.content), false);
+            if !message.attachments.is_empty() {
+                e.field(
+                    "Attachments",
+                    truncate(&message.attachments.join("\n"), MAX_EMBED_FIELD_VALUE_LENGTH),
+                    false,
+                );
+            }
+        }
+        None => {
+            e.description("Content is unknown, message was sent before it could be cached");
+            e.field("Channel", channel_id.mention(), true);
+        }
+    }
+    e.footer(|f| f.text(format!("Message ID: {}", message_id)));
+    e.timestamp(&Utc::now());
+    e
+}
+
+/// Deleted messages themselves are attached as a transcript
+pub fn bulk_delete_embed(e: &mut CreateEmbed, channel_id: ChannelId, count: usize) -> &mut CreateEmbed {
+    e.colour(DEFAULT_MOD_LOG_EMBED_COLOUR);
+    e.title("Messages bulk deleted");
+    e.description(format!("{} messages deleted in {}", count, channel_id.mention()));
+    e.timestamp(&Utc::now());
+    e
+}
+
+fn author_fields(e: &mut CreateEmbed, message: &CachedMessage) {
+    e.field(
+        "Author",
+        format!("{} {}", message.author_id.mention(), message.author_tag),
+        true,
+    );
+    e.field("Channel", message.channel_id.mention(), true);
+}
+
+/// Empty content can't be put in an embed field
+fn quote(content: &str) -> String {
+    if content.is_empty() {
+        "*No text*".to_string()
+    } else {
+        truncate(content, MAX_EMBED_FIELD_VALUE_LENGTH)
+    }
+}
+
+/// Plain text log of deleted messages, oldest first.
+/// Messages which weren't cached are listed only by their id.
+pub fn transcript(messages: &[(MessageId, Option<CachedMessage>)]) -> String {
+    let mut messages = messages.iter().collect::<Vec<_>>();
+    messages.sort_by_key(|(id, _)| *id);
+    let mut lines = Vec::with_capacity(messages.len());
+    for (id, message) in messages {
+        match message {
+            Some(message) => {
+                lines.push(format!(
+                    "[{}] {} ({}): {}",
+                    message.timestamp.format("%Y-%m-%d %H:%M:%S UTC"),
+                    message.author_tag,
+                    message.author_id,
+                    message.content
+                ));
+                for url in &message.attachments {
+                    lines.push(format!("    Attachment: {}", url));
+                }
+            }
+            None => lines.push(format!("[unknown] Message {} wasn't cached", id)),
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn message(id: u64, content: &str) -> CachedMessage {
+        CachedMessage {
+            id: MessageId(id),
+            channel_id: ChannelId(10),
+            author_id: UserId(20),
+            author_tag: "Bob#0001".to_string(),
+            author_is_bot: false,
+            content: content.to_string(),
+            attachments: Vec::new(),
+            timestamp: Utc.ymd(2021, 3, 1).and_hms(12, 0, 0),
+        }
+    }
+
+    #[test]
+    fn cache_forgets_oldest_messages() {
+        let mut cache = MessageCache::new(2);
+        cache.insert(message(1, "one"));
+        cache.insert(message(2, "two"));
+        cache.insert(message(3, "three"));
+        assert_eq!(2, cache.messages.len());
+        assert_eq!(None, cache.messages.get(&MessageId(1)));
+        assert_eq!(Some(&message(3, "three")), cache.messages.get(&MessageId(3)));
+    }
+
+    #[test]
+    fn cache_update_returns_previous_version() {
+        let mut cache = MessageCache::new(2);
+        cache.insert(message(1, "one"));
+        assert_eq!(
+            Some(message(1, "one")),
+            cache.update(MessageId(1), "uno".to_string())
+        );
+        assert_eq!(Some(&message(1, "uno")), cache.messages.get(&MessageId(1)));
+        assert_eq!(None, cache.update(MessageId(2), "dos".to_string()));
+    }
+
+    #[test]
+    fn removed_messages_free_space() {
+        let mut cache = MessageCache::new(2);
+        cache.insert(message(1, "one"));
+        cache.insert(message(2, "two"));
+        assert_eq!(Some(message(1, "one")), cache.remove(MessageId(1)));
+        cache.insert(message(3, "three"));
+        assert_eq!(Some(&message(2, "two")), cache.messages.get(&MessageId(2)));
+        assert_eq!(2, cache.messages.len());
+    }
+
+    #[test]
+    fn reinserting_keeps_single_entry() {
+        let mut cache = MessageCache::new(2);
+        cache.insert(message(1, "one"));
+        cache.insert(message(1, "one"));
+        cache.insert(message(2, "two"));
+        assert_eq!(Some(&message(1, "one")), cache.messages.get(&MessageId(1)));
+    }
+
+    fn config() -> MessageLogConfig {
+        MessageLogConfig {
+            channel_id: Some(ChannelId(100)),
+            ignored_channels: vec![ChannelId(11)].into_iter().collect(),
+            ignored_roles: vec![RoleId(5)].into_iter().collect(),
+        }
+    }
+
+    #[rstest(
+        channel_id,
+        roles,
+        expected,
+        case::logged(10, &[1, 2], Some(ChannelId(100))),
+        case::ignored_channel(11, &[], None),
+        case::ignored_role(10, &[1, 5], None),
+        case::log_channel_itself(100, &[], None)
+    )]
+    fn picks_log_channel(channel_id: u64, roles: &[u64], expected: Option<ChannelId>) {
+        let roles = roles.iter().map(|id| RoleId(*id)).collect::<Vec<RoleId>>();
+        assert_eq!(expected, log_channel(&config(), ChannelId(channel_id), &roles));
+    }
+
+    #[test]
+    fn disabled_log_has_no_channel() {
+        assert_eq!(
+            None,
+            log_channel(&MessageLogConfig::default(), ChannelId(10), &[])
+        );
+    }
+
+    #[test]
+    fn transcript_is_chronological() {
+        let mut with_attachment = message(1, "first");
+        with_attachment
+            .attachments
+            .push("https://cdn/cat.png".to_string());
+        let messages = vec![
+            (MessageId(3), Some(message(3, "third"))),
+            (MessageId(2), None),
+            (MessageId(1), Some(with_attachment)),
+        ];
+        let expected = "[2021-03-01 12:00:00 UTC] Bob#0001 (20): first\n    \
+                        Attachment: https://cdn/cat.png\n\
+                        [unknown] Message 2 wasn't cached\n\
+                        [2021-03-01 12:00:00 UTC] Bob#0001 (20): third";
+        assert_eq!(expected, transcript(&messages));
+    }
+
+    #[test]
+    fn edit_embed_shows_both_versions() {
+        let mut embed = CreateEmbed::default();
+        edit_embed(
+            &mut embed,
+            GuildId(1),
+            Some(&message(1, "before")),
+            &message(1, ""),
+        );
+        let fields = embed.0["fields"].as_array().unwrap();
+        let value = |name: &str| {
+            fields
+                .iter()
+                .find(|field| field["name"] == name)
+                .map(|field| field["value"].clone())
+        };
+        assert_eq!(Some(serde_json::json!("before")), value("Before"));
+        assert_eq!(Some(serde_json::json!("*No text*")), value("After"));
+    }
+}
@@ -1,3 +1,8 @@
+pub mod autoresponders;
 pub mod commands;
 pub mod context;
+pub mod cooldowns;
 pub mod errors;
+pub mod member_kind;
+pub mod message_log;
+pub mod mod_log;
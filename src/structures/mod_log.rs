@@ -0,0 +1,358 @@
+use crate::{
+    database::queries::{Infraction, InfractionKind, ModLogChannels},
+    parsers::{duration::format_duration, message::MAX_EMBED_FIELD_VALUE_LENGTH},
+    structures::errors::DatabaseError,
+    utils::{
+        defaults::DEFAULT_MOD_LOG_EMBED_COLOUR,
+        misc::{is_not_found, truncate},
+    },
+};
+use chrono::Utc;
+use serenity::{
+    builder::CreateEmbed,
+    http::Http,
+    model::{
+        id::{ChannelId, GuildId, RoleId, UserId},
+        user::User,
+    },
+    prelude::{Context, Mentionable, TypeMapKey},
+};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info};
+
+/// Something worth recording in guild's mod-log channel
+#[derive(Clone, Debug)]
+pub enum ModLogEvent {
+    Infraction(Infraction),
+    PrefixChanged {
+        moderator: UserId,
+        prefix: String,
+    },
+    CustomCommandChanged {
+        moderator: UserId,
+        name: String,
+        change: String,
+    },
+    ReactionRoleChanged {
+        moderator: UserId,
+        change: String,
+    },
+    JoinRoleChanged {
+        moderator: UserId,
+        change: String,
+    },
+    MessagesPurged {
+        moderator: UserId,
+        channel_id: ChannelId,
+        count: usize,
+        filters: String,
+    },
+    TemporaryRoleGiven {
+        moderator: UserId,
+        user_id: UserId,
+        role_id: RoleId,
+        duration: Duration,
+    },
+    TemporaryRoleExpired {
+        user_id: UserId,
+        role_id: RoleId,
+    },
+    MemberJoined(User),
+    MemberLeft(User),
+}
+
+impl ModLogEvent {
+    pub fn embed<'a>(&self, e: &'a mut CreateEmbed) -> &'a mut CreateEmbed {
+        e.colour(DEFAULT_MOD_LOG_EMBED_COLOUR);
+        match self {
+            Self::Infraction(infraction) => return infraction_embed(e, infraction),
+            Self::PrefixChanged { moderator, prefix } => {
+                e.title("Prefix changed");
+                e.description(format!("New prefix is `{}`", prefix));
+                e.field("Moderator", moderator.mention(), true);
+            }
+            Self::CustomCommandChanged {
+                moderator,
+                name,
+                change,
+            } => {
+                e.title("Custom command changed");
+                e.description(format!("`{}`: {}", name, change));
+                e.field("Moderator", moderator.mention(), true);
+            }
+            Self::ReactionRoleChanged { moderator, change } => {
+                e.title("Reaction roles changed");
+                e.description(change);
+                e.field("Moderator", moderator.mention(), true);
+            }
+            Self::JoinRoleChanged { moderator, change } => {
+                e.title("Join roles changed");
+                e.description(change);
+                e.field("Moderator", moderator.mention(), true);
+            }
+            Self::MessagesPurged {
+                moderator,
+                channel_id,
+                count,
+                filters,
+            } => {
+                e.title("Messages purged");
+                e.description(format!("{} messages deleted in {}", count, channel_id.mention()));
+                if !filters.is_empty() {
+                    e.field("Filters", truncate(filters, MAX_EMBED_FIELD_VALUE_LENGTH), false);
+                }
+                e.field("Moderator", moderator.mention(), true);
+            }
+            Self::TemporaryRoleGiven {
+                moderator,
+                user_id,
+                role_id,
+                duration,
+            } => {
+                e.title("Temporary role given");
+                e.description(format!("{} got {}", user_id.mention(), role_id.mention()));
+                e.field("Duration", format_duration(*duration), true);
+                e.field("Moderator", moderator.mention(), true);
+            }
+            Self::TemporaryRoleExpired { user_id, role_id } => {
+                e.title("Temporary role expired");
+                e.description(format!(
+                    "{} was taken from {}",
+                    role_id.mention(),
+                    user_id.mention()
+                ));
+            }
+            Self::MemberJoined(user) => {
+                e.title("Member joined");
+                e.description(format!("{} {}", user.mention(), user.tag()));
+                e.field(
+                    "Account created",
+                    user.created_at().format("%Y-%m-%d %H:%M UTC"),
+                    true,
+                );
+                e.thumbnail(user.face());
+                e.footer(|f| f.text(format!("ID: {}", user.id)));
+            }
+            Self::MemberLeft(user) => {
+                e.title("Member left");
+                e.description(format!("{} {}", user.mention(), user.tag()));
+                e.thumbnail(user.face());
+                e.footer(|f| f.text(format!("ID: {}", user.id)));
+            }
+        }
+        e.timestamp(&Utc::now());
+        e
+    }
+}
+
+pub fn infraction_embed<'a>(e: &'a mut CreateEmbed, infraction: &Infraction) -> &'a mut CreateEmbed {
+    e.colour(DEFAULT_MOD_LOG_EMBED_COLOUR);
+    e.title(format!(
+        "Case #{} | {}",
+        infraction.case_number,
+        infraction_kind_name(infraction.kind)
+    ));
+    e.field(
+        "User",
+        format!("{} ({})", infraction.target_id.mention(), infraction.target_id),
+        true,
+    );
+    e.field("Moderator", infraction.moderator_id.mention(), true);
+    let reason = match &infraction.reason {
+        Some(reason) => truncate(reason, MAX_EMBED_FIELD_VALUE_LENGTH),
+        None => format!(
+            "No reason given, set it with `reason {} <reason>`",
+            infraction.case_number
+        ),
+    };
+    e.field("Reason", reason, false);
+    if let Some(duration) = infraction.duration {
+        e.field("Duration", format_duration(duration), true);
+    }
+    e.timestamp(&infraction.created_at);
+    e
+}
+
+pub fn infraction_kind_name(kind: InfractionKind) -> &'static str {
+    match kind {
+        InfractionKind::Warn => "Warn",
+        InfractionKind::Kick => "Kick",
+        InfractionKind::Ban => "Ban",
+        InfractionKind::Unban => "Unban",
+        InfractionKind::Softban => "Softban",
+        InfractionKind::Mute => "Mute",
+        InfractionKind::Unmute => "Unmute",
+    }
+}
+
+/// Posts events to mod-log channels of guilds which have one set
+#[derive(Debug)]
+pub struct ModLog {
+    channels: ModLogChannels,
+}
+
+impl ModLog {
+    pub fn new(channels: ModLogChannels) -> Self {
+        Self { channels }
+    }
+
+    pub async fn get_channel(&self, guild_id: GuildId) -> Result<Option<ChannelId>, DatabaseError> {
+        self.channels.get_mod_log_channel(guild_id).await
+    }
+
+    pub async fn set_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+    ) -> Result<(), DatabaseError> {
+        self.channels.set_mod_log_channel(guild_id, channel_id).await
+    }
+
+    /// Disables mod-log if it was in a deleted channel
+    pub async fn forget_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Result<(), DatabaseError> {
+        if self.get_channel(guild_id).await? == Some(channel_id) {
+            info!(
+                "Mod-log channel {} of {} is gone, disabling it",
+                channel_id, guild_id
+            );
+            self.set_channel(guild_id, None).await?;
+        }
+        Ok(())
+    }
+
+    /// Failures are only logged, so that they don't interrupt whatever is being logged
+    pub async fn log(&self, http: impl AsRef<Http>, guild_id: GuildId, event: ModLogEvent) {
+        let channel_id = match self.get_channel(guild_id).await {
+            Ok(Some(channel_id)) => channel_id,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Error getting mod-log channel: {:?}", e);
+                return;
+            }
+        };
+        let result = channel_id
+            .send_message(http, |m| m.embed(|e| event.embed(e)))
+            .await;
+        match result {
+            Ok(_) => {}
+            Err(e) if is_not_found(&e) => {
+                if let Err(e) = self.forget_channel(guild_id, channel_id).await {
+                    error!("Error disabling mod-log: {:?}", e);
+                }
+            }
+            Err(e) => error!("Unable to post to mod-log channel {}: {:?}", channel_id, e),
+        }
+    }
+}
+
+impl TypeMapKey for ModLog {
+    type Value = Arc<Self>;
+}
+
+/// Posts event to mod-log of guild, if it has one
+pub async fn log_event(ctx: &Context, guild_id: GuildId, event: ModLogEvent) {
+    let mod_log = {
+        let data = ctx.data.read().await;
+        match data.get::<ModLog>() {
+            Some(mod_log) => mod_log.clone(),
+            None => {
+                error!("Unable to get mod-log!");
+                return;
+            }
+        }
+    };
+    mod_log.log(&ctx.http, guild_id, event).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    fn infraction(reason: Option<String>) -> Infraction {
+        Infraction {
+            case_number: 7,
+            kind: InfractionKind::Mute,
+            moderator_id: UserId(1),
+            target_id: UserId(2),
+            reason,
+            duration: Some(Duration::from_secs(3600)),
+            created_at: Utc.ymd(2021, 3, 1).and_hms(12, 0, 0),
+        }
+    }
+
+    fn field(embed: &CreateEmbed, name: &str) -> Option<Value> {
+        embed.0["fields"]
+            .as_array()?
+            .iter()
+            .find(|field| field["name"] == name)
+            .map(|field| field["value"].clone())
+    }
+
+    #[test]
+    fn infraction_embed_lists_case() {
+        let mut embed = CreateEmbed::default();
+        ModLogEvent::Infraction(infraction(Some("Spam".to_string()))).embed(&mut embed);
+        assert_eq!(json!("Case #7 | Mute"), embed.0["title"]);
+        assert_eq!(Some(json!("Spam")), field(&embed, "Reason"));
+        assert_eq!(Some(json!("<@1>")), field(&embed, "Moderator"));
+        assert_eq!(Some(json!("1h")), field(&embed, "Duration"));
+        assert_eq!(json!(DEFAULT_MOD_LOG_EMBED_COLOUR.0), embed.0["color"]);
+    }
+
+    #[test]
+    fn infraction_embed_truncates_reason() {
+        let mut embed = CreateEmbed::default();
+        infraction_embed(&mut embed, &infraction(Some("a".repeat(2000))));
+        let reason = field(&embed, "Reason").unwrap();
+        assert_eq!(
+            MAX_EMBED_FIELD_VALUE_LENGTH,
+            reason.as_str().unwrap().chars().count()
+        );
+    }
+
+    #[test]
+    fn infraction_embed_explains_missing_reason() {
+        let mut embed = CreateEmbed::default();
+        infraction_embed(&mut embed, &infraction(None));
+        assert_eq!(
+            Some(json!("No reason given, set it with `reason 7 <reason>`")),
+            field(&embed, "Reason")
+        );
+    }
+
+    #[test]
+    fn configuration_changes_credit_moderator() {
+        let mut embed = CreateEmbed::default();
+        let event = ModLogEvent::PrefixChanged {
+            moderator: UserId(5),
+            prefix: "?".to_string(),
+        };
+        event.embed(&mut embed);
+        assert_eq!(json!("Prefix changed"), embed.0["title"]);
+        assert_eq!(json!("New prefix is `?`"), embed.0["description"]);
+        assert_eq!(Some(json!("<@5>")), field(&embed, "Moderator"));
+    }
+
+    #[test]
+    fn temporary_role_embed_shows_duration() {
+        let mut embed = CreateEmbed::default();
+        let event = ModLogEvent::TemporaryRoleGiven {
+            moderator: UserId(5),
+            user_id: UserId(2),
+            role_id: RoleId(3),
+            duration: Duration::from_secs(2 * 86400),
+        };
+        event.embed(&mut embed);
+        assert_eq!(json!("Temporary role given"), embed.0["title"]);
+        assert_eq!(json!("<@2> got <@&3>"), embed.0["description"]);
+        assert_eq!(Some(json!("2d")), field(&embed, "Duration"));
+        assert_eq!(Some(json!("<@5>")), field(&embed, "Moderator"));
+    }
+}
@@ -1,2 +1,3 @@
 use serenity::utils::Colour;
 pub static DEFAULT_HELP_EMBED_COLOUR: Colour = Colour::DARK_GREEN;
+pub static DEFAULT_MOD_LOG_EMBED_COLOUR: Colour = Colour::ORANGE;
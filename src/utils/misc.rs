@@ -1,5 +1,13 @@
-use crate::{parsers::message::Message, structures::errors::*, utils::prompts};
+use crate::{
+    parsers::{
+        message::Message,
+        template::{render, render_rich, starts_with_placeholder, TemplateVariables},
+    },
+    structures::errors::*,
+    utils::prompts,
+};
 
+use chrono::Utc;
 use serenity::{
     builder::CreateMessage,
     framework::standard::{Args, CommandResult},
@@ -8,7 +16,7 @@ use serenity::{
         prelude::*,
     },
     prelude::*,
-    utils::parse_role,
+    utils::{parse_role, parse_username},
 };
 use std::convert::TryFrom;
 
@@ -19,6 +27,46 @@ pub fn get_message_url(guild_id: GuildId, channel_id: ChannelId, message_id: Mes
     )
 }
 
+/// Joins parts with newlines into as few chunks as possible, each at most `max_length` characters long.
+/// Parts longer than that are cut.
+pub fn split_into_chunks(parts: &[String], max_length: usize) -> Vec<String> {
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for part in parts {
+        let part = if part.chars().count() > max_length {
+            part.chars().take(max_length).collect::<String>()
+        } else {
+            part.clone()
+        };
+        if !current.is_empty() && current.chars().count() + 1 + part.chars().count() > max_length {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(&part);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Parses user mention or plain user ID
+pub fn parse_user_id(user_str: &str) -> Option<UserId> {
+    parse_username(user_str)
+        .or_else(|| user_str.parse::<u64>().ok())
+        .map(UserId)
+}
+
+/// Whether Discord responded that requested entity doesn't exist (anymore)
+pub fn is_not_found(error: &SerenityError) -> bool {
+    match error {
+        SerenityError::Http(e) => e.status_code().map(|code| code.as_u16() == 404).unwrap_or(false),
+        _ => false,
+    }
+}
+
 pub async fn send_rich_serialized_message(
     ctx: &Context,
     channel_id: ChannelId,
@@ -40,6 +88,81 @@ pub fn deserialize_rich_message(serialized_message: &str) -> Result<CreateMessag
     CreateMessage::try_from(deserialized_message)
 }
 
+/// Sends plain or json5 content, with template variables expanded
+pub async fn send_rendered(
+    ctx: &Context,
+    channel_id: ChannelId,
+    content: &str,
+    variables: &TemplateVariables,
+) -> CommandResult {
+    if content.starts_with("{") && !starts_with_placeholder(content) {
+        let rendered = render_rich(content, variables)?;
+        send_rich_serialized_message(ctx, channel_id, &rendered).await?;
+    } else {
+        let rendered = render(content, variables)?;
+        channel_id.say(ctx, rendered).await?;
+    }
+    Ok(())
+}
+
+/// Sends content without expanding template variables, rich if it's json5
+pub async fn send_unrendered(ctx: &Context, channel_id: ChannelId, content: &str) -> CommandResult {
+    if content.starts_with("{") {
+        send_rich_serialized_message(ctx, channel_id, content).await?;
+    } else {
+        channel_id.say(ctx, content).await?;
+    }
+    Ok(())
+}
+
+/// Arguments following command name in the message which invoked the command.
+/// Message has to start with one of `prefixes`, otherwise there are no arguments.
+pub fn custom_command_args(content: &str, prefixes: &[String], command_name: &str) -> Vec<String> {
+    prefixes
+        .iter()
+        .find_map(|prefix| content.strip_prefix(prefix.as_str()))
+        .and_then(|rest| rest.trim_start().strip_prefix(command_name))
+        .map(|args| args.split_whitespace().map(ToString::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Builds variables that can be used in custom command templates
+pub async fn template_variables(
+    ctx: &Context,
+    user: &User,
+    guild_id: Option<GuildId>,
+    channel_id: Option<ChannelId>,
+    args: Vec<String>,
+) -> TemplateVariables {
+    let mut variables = TemplateVariables::new(args);
+    variables
+        .set("user", &user.name)
+        .set("user.name", &user.name)
+        .set("user.tag", user.tag())
+        .set("user.id", user.id)
+        .set("user.mention", user.mention())
+        .set("date", Utc::now().format("%Y-%m-%d"));
+    if let Some(channel_id) = channel_id {
+        variables
+            .set("channel", channel_id.mention())
+            .set("channel.id", channel_id);
+    }
+    if let Some(guild_id) = guild_id {
+        variables.set("guild.id", guild_id);
+        let guild_data = ctx
+            .cache
+            .guild_field(guild_id, |g| (g.name.clone(), g.member_count))
+            .await;
+        if let Some((name, member_count)) = guild_data {
+            variables
+                .set("guild", &name)
+                .set("guild.name", name)
+                .set("guild.member_count", member_count);
+        }
+    }
+    variables
+}
+
 pub async fn get_rich_from_args_or_prompt<'a>(
     ctx: &'a Context,
     channel: ChannelId,
@@ -57,8 +180,10 @@ pub async fn get_rich_from_args_or_prompt<'a>(
             // Assume this is special content, which needs to be parsed
             deserialize_rich_message(content)?
         } else {
-            let mut message = CreateMessage::default();
-            message.content(content).to_owned()
+            CreateMessage::try_from(Message {
+                content: Some(content.to_string()),
+                embed: None,
+            })?
         }
     };
     return Ok(Some(rich_message));
@@ -91,3 +216,87 @@ pub async fn role_from_name_or_mention(
     };
     return Ok(role_id);
 }
+
+/// Cuts text to `max_length` characters, marking that it was cut with an ellipsis
+pub fn truncate(text: &str, max_length: usize) -> String {
+    if text.chars().count() > max_length {
+        let mut truncated = text.chars().take(max_length - 1).collect::<String>();
+        truncated.push('…');
+        truncated
+    } else {
+        text.to_string()
+    }
+}
+
+/// Cuts text to `max_bytes` bytes of UTF-8, for limits which Discord counts in bytes.
+/// The ellipsis marking that it was cut counts towards the limit too.
+pub fn truncate_bytes(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let mut end = max_bytes.saturating_sub('…'.len_utf8());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn parts(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(ToString::to_string).collect()
+    }
+
+    #[rstest(
+        input,
+        max_length,
+        expected,
+        case::empty(&[], 10, &[]),
+        case::single(&["abc"], 10, &["abc"]),
+        case::exactly_fits(&["aaaa", "bbbb"], 9, &["aaaa\nbbbb"]),
+        case::one_over(&["aaaa", "bbbb"], 8, &["aaaa", "bbbb"]),
+        case::fills_chunks(&["aa", "bb", "cc", "dd"], 5, &["aa\nbb", "cc\ndd"]),
+        case::long_part_cut(&["abcdefghij"], 4, &["abcd"]),
+        case::long_part_between(&["ab", "abcdefghij", "cd"], 4, &["ab", "abcd", "cd"]),
+        case::counts_characters(&["żółw", "żółw"], 9, &["żółw\nżółw"])
+    )]
+    fn splits_into_chunks(input: &[&str], max_length: usize, expected: &[&str]) {
+        assert_eq!(parts(expected), split_into_chunks(&parts(input), max_length));
+    }
+
+    #[rstest(
+        content,
+        expected,
+        case::prefix("!roll 1 6", vec!["1", "6"]),
+        case::space_after_prefix("! roll 1  6", vec!["1", "6"]),
+        case::mention("<@!42> roll 1 6", vec!["1", "6"]),
+        case::name_in_prefix("rollroll 1 6", vec!["1", "6"]),
+        case::no_args("!roll", vec![]),
+        case::no_prefix("roll 1 6", vec![])
+    )]
+    fn custom_command_args_after_prefix(content: &str, expected: Vec<&str>) {
+        let prefixes = vec!["<@!42>".to_string(), "roll".to_string(), "!".to_string()];
+        assert_eq!(expected, custom_command_args(content, &prefixes, "roll"));
+    }
+
+    #[rstest(
+        text,
+        max_bytes,
+        expected,
+        case::fits("abc", 3, "abc"),
+        case::ascii("abcdef", 5, "ab…"),
+        case::multibyte_fits("żółw", 7, "żółw"),
+        case::inside_char("żółw", 6, "ż…"),
+        case::on_char_boundary("żółw", 5, "ż…"),
+        case::emoji("🐢🐢", 7, "🐢…")
+    )]
+    fn truncates_bytes(text: &str, max_bytes: usize, expected: &str) {
+        let truncated = truncate_bytes(text, max_bytes);
+        assert_eq!(expected, truncated);
+        assert!(truncated.len() <= max_bytes);
+    }
+}
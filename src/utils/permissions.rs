@@ -1,3 +1,4 @@
+use crate::database::queries::{CommandRestriction, RestrictionKind};
 use serenity::{model::prelude::*, prelude::*};
 use tracing::debug;
 
@@ -44,3 +45,96 @@ pub async fn check_permission(ctx: &Context, msg: &Message, permission: Permissi
 
     return false;
 }
+
+/// Whether custom command with given restrictions can be used by member with `roles` in `channel_id`.
+/// Denies take precedence - and if there are any allows, at least one has to match.
+/// Everyone has the @everyone role, its id is the same as guild's.
+pub fn is_command_allowed(
+    restrictions: &[CommandRestriction],
+    guild_id: GuildId,
+    roles: &[RoleId],
+    channel_id: ChannelId,
+) -> bool {
+    let matches = |restriction: &CommandRestriction| match restriction.kind {
+        RestrictionKind::AllowRole | RestrictionKind::DenyRole => {
+            restriction.target_id == guild_id.0 || roles.contains(&RoleId(restriction.target_id))
+        }
+        RestrictionKind::AllowChannel | RestrictionKind::DenyChannel => channel_id.0 == restriction.target_id,
+    };
+    let of_kind = |kind: RestrictionKind| restrictions.iter().filter(move |r| r.kind == kind);
+
+    let denied = of_kind(RestrictionKind::DenyRole)
+        .chain(of_kind(RestrictionKind::DenyChannel))
+        .any(matches);
+    let role_allowed = of_kind(RestrictionKind::AllowRole).next().is_none()
+        || of_kind(RestrictionKind::AllowRole).any(matches);
+    let channel_allowed = of_kind(RestrictionKind::AllowChannel).next().is_none()
+        || of_kind(RestrictionKind::AllowChannel).any(matches);
+
+    !denied && role_allowed && channel_allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    const GUILD_ID: u64 = 1;
+    const MEMBER_ROLE: u64 = 10;
+    const OTHER_ROLE: u64 = 11;
+    const CHANNEL: u64 = 20;
+    const OTHER_CHANNEL: u64 = 21;
+
+    fn restriction(kind: RestrictionKind, target_id: u64) -> CommandRestriction {
+        CommandRestriction { kind, target_id }
+    }
+
+    #[rstest(
+        restrictions,
+        expected,
+        case::unrestricted(vec![], true),
+        case::allowed_role(vec![restriction(RestrictionKind::AllowRole, MEMBER_ROLE)], true),
+        case::other_role_allowed(vec![restriction(RestrictionKind::AllowRole, OTHER_ROLE)], false),
+        case::one_of_allowed_roles(
+            vec![
+                restriction(RestrictionKind::AllowRole, OTHER_ROLE),
+                restriction(RestrictionKind::AllowRole, MEMBER_ROLE)
+            ],
+            true
+        ),
+        case::denied_role(vec![restriction(RestrictionKind::DenyRole, MEMBER_ROLE)], false),
+        case::other_role_denied(vec![restriction(RestrictionKind::DenyRole, OTHER_ROLE)], true),
+        case::allowed_channel(vec![restriction(RestrictionKind::AllowChannel, CHANNEL)], true),
+        case::other_channel_allowed(vec![restriction(RestrictionKind::AllowChannel, OTHER_CHANNEL)], false),
+        case::denied_channel(vec![restriction(RestrictionKind::DenyChannel, CHANNEL)], false),
+        case::other_channel_denied(vec![restriction(RestrictionKind::DenyChannel, OTHER_CHANNEL)], true),
+        case::deny_beats_allow(
+            vec![
+                restriction(RestrictionKind::AllowRole, MEMBER_ROLE),
+                restriction(RestrictionKind::DenyChannel, CHANNEL)
+            ],
+            false
+        ),
+        case::role_and_channel_both_needed(
+            vec![
+                restriction(RestrictionKind::AllowRole, MEMBER_ROLE),
+                restriction(RestrictionKind::AllowChannel, OTHER_CHANNEL)
+            ],
+            false
+        ),
+        case::everyone_allowed(vec![restriction(RestrictionKind::AllowRole, GUILD_ID)], true),
+        case::everyone_denied(vec![restriction(RestrictionKind::DenyRole, GUILD_ID)], false)
+    )]
+    fn command_allowed(restrictions: Vec<CommandRestriction>, expected: bool) {
+        assert_eq!(
+            expected,
+            is_command_allowed(
+                &restrictions,
+                GuildId(GUILD_ID),
+                &[RoleId(MEMBER_ROLE)],
+                ChannelId(CHANNEL)
+            )
+        );
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{
-    parsers::message::{Embed as EmbedBuilder, Message as MessageBuilder},
+    parsers::{
+        message::{Embed as EmbedBuilder, EmbedEnum, Message as MessageBuilder},
+        role_menu::{RoleMenu, RoleMenuEntry},
+    },
     structures::errors::*,
 };
 use once_cell::sync::Lazy;
@@ -111,7 +114,7 @@ pub async fn get_rich_message<'a>(
         PromptResult::Message(msg) => {
             let mut embed = EmbedBuilder::default();
             embed.description = Some(msg);
-            builder.embed = Some(embed);
+            builder.embed = Some(EmbedEnum::Single(Box::new(embed)));
             ()
         }
         PromptResult::Accept => {
@@ -133,6 +136,101 @@ pub async fn get_rich_message<'a>(
         .map_err(|e| e.into())
 }
 
+pub static PROMPT_MENU_DESCRIPTION: Lazy<CreateMessage<'static>> = Lazy::new(|| {
+    let mut msg = CreateMessage::default();
+    msg.content("What should the description of the role menu be?");
+    msg.clone()
+});
+
+pub static PROMPT_MENU_ENTRY: Lazy<CreateMessage<'static>> = Lazy::new(|| {
+    let mut msg = CreateMessage::default();
+    msg.content("Send emoji followed by role name or mention, like `:cat: CatPeople`. Accept once all roles are added.");
+    msg.clone()
+});
+
+/// Builds reaction role menu from user inputs
+pub async fn get_role_menu(
+    ctx: &Context,
+    channel_id: ChannelId,
+    user: &User,
+) -> Result<Option<RoleMenu>, BotstoneError> {
+    let mut menu = RoleMenu::default();
+
+    channel_id
+        .send_message(ctx, |m| {
+            m.0 = PROMPT_USAGE_DESCRIPTION.clone().0;
+            m
+        })
+        .await?;
+
+    let preview = |menu: &RoleMenu| {
+        let mut preview = CreateMessage::default();
+        preview.content(format!(
+            "Description: {}\nRoles: {}",
+            menu.description.as_deref().unwrap_or("-"),
+            menu.roles
+                .iter()
+                .map(|entry| format!("{} {}", entry.emoji, entry.role))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+        preview
+    };
+
+    match prompt_for_message_part_previewed(
+        ctx,
+        &PROMPT_MENU_DESCRIPTION,
+        channel_id,
+        &preview(&menu),
+        user,
+        300.0,
+    )
+    .await?
+    {
+        PromptResult::Message(msg) => menu.description = Some(msg),
+        PromptResult::Cancel | PromptResult::TimedOut => return Ok(None),
+        PromptResult::Accept | PromptResult::Skip => (),
+        PromptResult::Preview => {
+            return Err(BotstoneError::ImpossibleError(Box::new(BotstoneError::Other(
+                "Returned preview as result!".to_string(),
+            ))))
+        }
+    }
+
+    loop {
+        match prompt_for_message_part_previewed(
+            ctx,
+            &PROMPT_MENU_ENTRY,
+            channel_id,
+            &preview(&menu),
+            user,
+            300.0,
+        )
+        .await?
+        {
+            PromptResult::Message(msg) => match msg.trim().split_once(char::is_whitespace) {
+                Some((emoji, role)) => menu.roles.push(RoleMenuEntry {
+                    emoji: emoji.to_string(),
+                    role: role.trim().to_string(),
+                }),
+                None => {
+                    channel_id
+                        .say(ctx, "Expected emoji and role separated by space, try again")
+                        .await?;
+                }
+            },
+            PromptResult::Accept => return Ok(Some(menu)),
+            PromptResult::Cancel | PromptResult::TimedOut => return Ok(None),
+            PromptResult::Skip => (),
+            PromptResult::Preview => {
+                return Err(BotstoneError::ImpossibleError(Box::new(BotstoneError::Other(
+                    "Returned preview as result!".to_string(),
+                ))))
+            }
+        }
+    }
+}
+
 #[derive(EnumIter, Debug, PartialEq)]
 pub enum PromptResult {
     Accept,